@@ -49,8 +49,10 @@ pub fn run() -> Result<(), color_eyre::Report> {
                     "    {:<15}{:<15}{:<25}{:<25}{:<15}{:<25}{:<15}{:<15}{:<15}",
                     dev.name,
                     dev.slot_name,
-                    dev.node_guid,
-                    port.guid.unwrap_or("-".to_string()),
+                    format!("{:#}", dev.node_guid),
+                    port.guid
+                        .map(|g| format!("{g:#}"))
+                        .unwrap_or("-".to_string()),
                     port.lid,
                     port.subnet.unwrap_or("-".to_string()),
                     port.link_type.to_string(),
@@ -17,6 +17,7 @@ limitations under the License.
 use clap::{Parser, Subcommand};
 
 mod list;
+mod show;
 
 #[derive(Parser)]
 #[command(name = "hcactl")]
@@ -32,6 +33,11 @@ struct Options {
 enum Commands {
     /// List all HCAs
     List,
+    /// Show the attributes of the device, e.g. its limits of QPs, CQs and MRs
+    Show {
+        /// The name of the device, e.g. mlx5_0
+        name: String,
+    },
 }
 
 #[tokio::main]
@@ -42,6 +48,7 @@ async fn main() -> Result<(), color_eyre::Report> {
 
     match &opt.command {
         Some(Commands::List) => list::run()?,
+        Some(Commands::Show { name }) => show::run(name)?,
         None => {}
     }
     Ok(())
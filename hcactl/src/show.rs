@@ -0,0 +1,160 @@
+/*
+Copyright 2023 The xflops Authors.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use ::libhca;
+use color_eyre::eyre::eyre;
+
+pub fn run(name: &str) -> Result<(), color_eyre::Report> {
+    let hcas = libhca::list_pci_devices()?;
+
+    let (hca, dev) = hcas
+        .iter()
+        .find_map(|hca| {
+            hca.ib_devices
+                .iter()
+                .find(|d| d.name == name)
+                .map(|d| (hca, d))
+        })
+        .ok_or_else(|| eyre!("device {} not found", name))?;
+    let attr = &dev.attr;
+
+    println!("{:<28}: {}", "Name", dev.name);
+    println!("{:<28}: {}", "Slot", dev.slot_name);
+    println!("{:<28}: {}", "Model", hca.model_name);
+    println!("{:<28}: {}", "FW", dev.fw_ver);
+    println!("{:<28}: {}", "Board", dev.board_id);
+    println!("{:<28}: {:#}", "Node GUID", dev.node_guid);
+    println!("{:<28}: {:#}", "System Image GUID", dev.sys_image_guid);
+    println!("{:<28}: {:#x}", "Vendor ID", attr.vendor_id);
+    println!("{:<28}: {}", "Vendor Part ID", attr.vendor_part_id);
+    println!("{:<28}: {:#x}", "HW Version", attr.hw_ver);
+    println!("{:<28}: {}", "Physical Ports", attr.phys_port_cnt);
+
+    println!();
+
+    println!("{:<28}: {}", "max_mr_size", attr.max_mr_size);
+    println!("{:<28}: {:#x}", "page_size_cap", attr.page_size_cap);
+    println!("{:<28}: {:#x}", "device_cap_flags", attr.device_cap_flags);
+    println!("{:<28}: {}", "max_qp", attr.max_qp);
+    println!("{:<28}: {}", "max_qp_wr", attr.max_qp_wr);
+    println!("{:<28}: {}", "max_sge", attr.max_sge);
+    println!("{:<28}: {}", "max_sge_rd", attr.max_sge_rd);
+    println!("{:<28}: {}", "max_cq", attr.max_cq);
+    println!("{:<28}: {}", "max_cqe", attr.max_cqe);
+    println!("{:<28}: {}", "max_mr", attr.max_mr);
+    println!("{:<28}: {}", "max_pd", attr.max_pd);
+    println!("{:<28}: {}", "max_qp_rd_atom", attr.max_qp_rd_atom);
+    println!("{:<28}: {}", "max_ee_rd_atom", attr.max_ee_rd_atom);
+    println!("{:<28}: {}", "max_res_rd_atom", attr.max_res_rd_atom);
+    println!(
+        "{:<28}: {}",
+        "max_qp_init_rd_atom", attr.max_qp_init_rd_atom
+    );
+    println!(
+        "{:<28}: {}",
+        "max_ee_init_rd_atom", attr.max_ee_init_rd_atom
+    );
+    println!("{:<28}: {}", "atomic_cap", attr.atomic_cap);
+    println!("{:<28}: {}", "max_ee", attr.max_ee);
+    println!("{:<28}: {}", "max_rdd", attr.max_rdd);
+    println!("{:<28}: {}", "max_mw", attr.max_mw);
+    println!("{:<28}: {}", "max_raw_ipv6_qp", attr.max_raw_ipv6_qp);
+    println!("{:<28}: {}", "max_raw_ethy_qp", attr.max_raw_ethy_qp);
+    println!("{:<28}: {}", "max_mcast_grp", attr.max_mcast_grp);
+    println!(
+        "{:<28}: {}",
+        "max_mcast_qp_attach", attr.max_mcast_qp_attach
+    );
+    println!(
+        "{:<28}: {}",
+        "max_total_mcast_qp_attach", attr.max_total_mcast_qp_attach
+    );
+    println!("{:<28}: {}", "max_ah", attr.max_ah);
+    println!("{:<28}: {}", "max_fmr", attr.max_fmr);
+    println!("{:<28}: {}", "max_map_per_fmr", attr.max_map_per_fmr);
+    println!("{:<28}: {}", "max_srq", attr.max_srq);
+    println!("{:<28}: {}", "max_srq_wr", attr.max_srq_wr);
+    println!("{:<28}: {}", "max_srq_sge", attr.max_srq_sge);
+    println!("{:<28}: {}", "max_pkeys", attr.max_pkeys);
+    println!("{:<28}: {}", "local_ca_ack_delay", attr.local_ca_ack_delay);
+
+    println!();
+
+    let Some(ex) = &attr.ex else {
+        println!("{:<28}: not supported", "Extended Attributes");
+        return Ok(());
+    };
+    let odp = &ex.odp_caps;
+
+    println!("{:<28}: {}", "odp_supported", odp.is_supported());
+    println!("{:<28}: {}", "odp_implicit", odp.is_implicit_supported());
+    println!("{:<28}: {:#x}", "rc_odp_caps", odp.rc_odp_caps);
+    println!("{:<28}: {:#x}", "uc_odp_caps", odp.uc_odp_caps);
+    println!("{:<28}: {:#x}", "ud_odp_caps", odp.ud_odp_caps);
+    println!("{:<28}: {:#x}", "xrc_odp_caps", odp.xrc_odp_caps);
+    println!(
+        "{:<28}: {:#x}",
+        "completion_timestamp_mask", ex.completion_timestamp_mask
+    );
+    println!("{:<28}: {} kHz", "hca_core_clock", ex.hca_core_clock);
+    println!(
+        "{:<28}: {:#x}",
+        "device_cap_flags_ex", ex.device_cap_flags_ex
+    );
+    println!("{:<28}: {}", "max_tso", ex.tso_caps.max_tso);
+    println!(
+        "{:<28}: {:#x}",
+        "tso_supported_qpts", ex.tso_caps.supported_qpts
+    );
+    println!(
+        "{:<28}: {:#x}",
+        "rss_supported_qpts", ex.rss_caps.supported_qpts
+    );
+    println!(
+        "{:<28}: {}",
+        "max_rwq_indirection_tables", ex.rss_caps.max_rwq_indirection_tables
+    );
+    println!(
+        "{:<28}: {}",
+        "max_rwq_indirection_table_size", ex.rss_caps.max_rwq_indirection_table_size
+    );
+    println!(
+        "{:<28}: {:#x}",
+        "rx_hash_fields_mask", ex.rss_caps.rx_hash_fields_mask
+    );
+    println!(
+        "{:<28}: {:#x}",
+        "rx_hash_function", ex.rss_caps.rx_hash_function
+    );
+    println!("{:<28}: {}", "max_wq_type_rq", ex.max_wq_type_rq);
+    println!(
+        "{:<28}: {} kbps",
+        "qp_rate_limit_min", ex.packet_pacing_caps.qp_rate_limit_min
+    );
+    println!(
+        "{:<28}: {} kbps",
+        "qp_rate_limit_max", ex.packet_pacing_caps.qp_rate_limit_max
+    );
+    println!(
+        "{:<28}: {:#x}",
+        "pacing_supported_qpts", ex.packet_pacing_caps.supported_qpts
+    );
+    println!("{:<28}: {:#x}", "raw_packet_caps", ex.raw_packet_caps);
+    println!("{:<28}: {}", "max_dm_size", ex.max_dm_size);
+    println!("{:<28}: {}", "phys_port_cnt_ex", ex.phys_port_cnt_ex);
+
+    Ok(())
+}
@@ -23,8 +23,14 @@ mod types;
 mod utils;
 mod wrappers;
 
+pub use types::{
+    Guid, IbAtomicCap, IbDeviceAttr, IbDeviceAttrEx, IbOdpCaps, IbPacketPacingCaps, IbRssCaps,
+    IbTsoCaps,
+};
+
 use std::alloc::{self, Layout};
 use std::collections::HashMap;
+use std::mem;
 
 use std::os::raw::c_int;
 use std::ptr::NonNull;
@@ -35,8 +41,9 @@ use numeric_cast::NumericCast;
 use scopeguard::defer;
 
 use wrappers::ib::{
-    ibv_close_device, ibv_device_attr, ibv_free_device_list, ibv_get_device_list, ibv_gid,
-    ibv_open_device, ibv_port_attr, ibv_query_device, ibv_query_gid, ibv_query_port,
+    ibv_close_device, ibv_context, ibv_device_attr, ibv_device_attr_ex, ibv_free_device_list,
+    ibv_get_device_list, ibv_gid, ibv_open_device, ibv_port_attr, ibv_query_device,
+    ibv_query_device_ex_input, ibv_query_gid, ibv_query_port, verbs_context, ENOSYS, EOPNOTSUPP,
 };
 
 use types::{DevicePtr, IbDevice, IbPort, IbPortLinkType, IbPortPhysState, IbPortState, PciDevice};
@@ -44,7 +51,7 @@ use utils::cstr_to_string;
 
 /// List the HCAs on the host.
 pub fn list_pci_devices() -> io::Result<Vec<PciDevice>> {
-    let ib_ports = list_ib_ports()?;
+    let mut ib_devs = query_ib_devices()?;
     let context = libudev::Context::new()?;
 
     let mut enumerator = libudev::Enumerator::new(&context)?;
@@ -58,10 +65,10 @@ pub fn list_pci_devices() -> io::Result<Vec<PciDevice>> {
             let pci_dev = pci_devs.entry(pci_dev.subsys_id.clone()).or_insert(pci_dev);
 
             let mut ib_dev = IbDevice::try_from(device)?;
-            ib_dev.ib_ports = ib_ports
-                .get(&ib_dev.name)
-                .unwrap_or(&Vec::<IbPort>::new())
-                .to_vec();
+            if let Some((attr, ports)) = ib_devs.remove(&ib_dev.name) {
+                ib_dev.attr = attr;
+                ib_dev.ib_ports = ports;
+            }
 
             pci_dev.fw_ver = ib_dev.fw_ver.clone();
             pci_dev.board_id = ib_dev.board_id.clone();
@@ -73,8 +80,9 @@ pub fn list_pci_devices() -> io::Result<Vec<PciDevice>> {
     Ok(pci_devs.into_values().collect())
 }
 
-fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
-    let mut ib_ports = HashMap::<String, Vec<IbPort>>::new();
+/// Query the attributes and the ports of the devices by their names.
+fn query_ib_devices() -> io::Result<HashMap<String, (IbDeviceAttr, Vec<IbPort>)>> {
+    let mut ib_devs = HashMap::<String, (IbDeviceAttr, Vec<IbPort>)>::new();
 
     unsafe {
         let mut num_devices: c_int = 0;
@@ -92,6 +100,7 @@ fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
         let devices = slice::from_raw_parts(device_list.as_ptr(), len);
 
         for devptr in devices {
+            let name = cstr_to_string((*devptr.ffi_ptr()).name.as_ptr());
             let ctx = ibv_open_device(devptr.ffi_ptr());
             if ctx.is_null() {
                 return Err(io::Error::last_os_error());
@@ -110,6 +119,16 @@ fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
                 return Err(io::Error::last_os_error());
             };
 
+            let mut dev_attr = IbDeviceAttr::from(&*dev_attr_ptr);
+            // The extended attributes are optional, so a failure does not fail the device.
+            dev_attr.ex = match query_device_ex(ctx) {
+                Ok(ex) => ex,
+                Err(e) => {
+                    log::warn!("Failed to query the extended attributes of {}: {}", name, e);
+                    None
+                }
+            };
+
             let mut ports = vec![];
 
             for i in 1..=(*dev_attr_ptr).phys_port_cnt {
@@ -148,8 +167,7 @@ fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
                             (*gid_ptr).raw[6],
                             (*gid_ptr).raw[7]
                         )),
-                        Some(format!(
-                            "{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}",
+                        Some(Guid::from([
                             (*gid_ptr).raw[8],
                             (*gid_ptr).raw[9],
                             (*gid_ptr).raw[10],
@@ -157,8 +175,8 @@ fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
                             (*gid_ptr).raw[12],
                             (*gid_ptr).raw[13],
                             (*gid_ptr).raw[14],
-                            (*gid_ptr).raw[15]
-                        )),
+                            (*gid_ptr).raw[15],
+                        ])),
                     ),
                 };
 
@@ -173,9 +191,47 @@ fn list_ib_ports() -> io::Result<HashMap<String, Vec<IbPort>>> {
                 });
             }
 
-            ib_ports.insert(cstr_to_string((*devptr.ffi_ptr()).name.as_ptr()), ports);
+            ib_devs.insert(name, (dev_attr, ports));
         }
     };
 
-    Ok(ib_ports)
+    Ok(ib_devs)
+}
+
+/// Query the extended attributes of the device; `None` if its provider does not support it.
+///
+/// `ibv_query_device_ex` is an inline function of libibverbs, so this calls the op of the
+/// provider as it does, without falling back to `ibv_query_device`.
+unsafe fn query_device_ex(ctx: *mut ibv_context) -> io::Result<Option<IbDeviceAttrEx>> {
+    // Only the extended contexts, i.e. `__VERBS_ABI_IS_EXTENDED`, are in a `verbs_context`.
+    if (*ctx).abi_compat as usize != usize::MAX {
+        return Ok(None);
+    }
+    let vctx = ctx
+        .cast::<u8>()
+        .sub(mem::offset_of!(verbs_context, context))
+        .cast::<verbs_context>();
+
+    // The ops are prepended to the context, so the older providers have fewer of them.
+    if (*vctx).sz
+        < mem::size_of::<verbs_context>() - mem::offset_of!(verbs_context, query_device_ex)
+    {
+        return Ok(None);
+    }
+    let Some(query) = (*vctx).query_device_ex else {
+        return Ok(None);
+    };
+
+    let attr_ptr =
+        alloc::alloc_zeroed(Layout::new::<ibv_device_attr_ex>()) as *mut ibv_device_attr_ex;
+    defer! {
+        alloc::dealloc(attr_ptr as *mut u8, Layout::new::<ibv_device_attr_ex>());
+    };
+
+    let input = ibv_query_device_ex_input::default();
+    match query(ctx, &input, attr_ptr, mem::size_of::<ibv_device_attr_ex>()) {
+        0 => Ok(Some(IbDeviceAttrEx::from(&*attr_ptr))),
+        ret if ret as u32 == ENOSYS || ret as u32 == EOPNOTSUPP => Ok(None),
+        ret => Err(io::Error::from_raw_os_error(ret)),
+    }
 }
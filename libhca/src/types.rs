@@ -17,11 +17,12 @@ limitations under the License.
 use std::fmt::{self, Display};
 use std::io;
 use std::ptr::NonNull;
+use std::str::FromStr;
 
 use libudev::Device;
 
 use super::utils::{get_property, get_sysattr};
-use super::wrappers::ib::{self, ibv_device, ibv_device_attr};
+use super::wrappers::ib::{self, ibv_device, ibv_device_attr, ibv_device_attr_ex};
 
 #[derive(Clone)]
 pub struct PciDevice {
@@ -50,15 +51,108 @@ impl TryFrom<Device> for PciDevice {
     }
 }
 
+/// The 64-bit GUID of a port or a node.
+///
+/// It accepts the common notations when parsing, e.g. `0011:2233:4456:0200`,
+/// `00:11:22:33:44:56:02:00`, `0x0011223344560200` or `0011223344560200`.
+/// It is displayed in UFM's notation (`0011223344560200`) by default, and in
+/// the colon-separated notation (`0011:2233:4456:0200`) with `{:#}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Guid(u64);
+
+impl Guid {
+    /// The GUID in UFM's notation, e.g. `0011223344560200`.
+    pub fn to_hex_string(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+
+    /// The GUID in colon-separated notation, e.g. `0011:2233:4456:0200`.
+    pub fn to_colon_string(&self) -> String {
+        format!(
+            "{:04x}:{:04x}:{:04x}:{:04x}",
+            (self.0 >> 48) & 0xffff,
+            (self.0 >> 32) & 0xffff,
+            (self.0 >> 16) & 0xffff,
+            self.0 & 0xffff
+        )
+    }
+}
+
+impl From<u64> for Guid {
+    fn from(v: u64) -> Self {
+        Guid(v)
+    }
+}
+
+impl From<Guid> for u64 {
+    fn from(v: Guid) -> u64 {
+        v.0
+    }
+}
+
+impl From<[u8; 8]> for Guid {
+    fn from(raw: [u8; 8]) -> Self {
+        Guid(u64::from_be_bytes(raw))
+    }
+}
+
+impl FromStr for Guid {
+    type Err = io::Error;
+
+    fn from_str(guid: &str) -> io::Result<Self> {
+        let invalid =
+            || io::Error::new(io::ErrorKind::InvalidData, format!("invalid guid '{guid}'"));
+
+        let s = guid.trim().to_lowercase();
+        let s = s.strip_prefix("0x").unwrap_or(&s);
+
+        let digits = if s.contains(':') {
+            // All groups have the same width, either "xxxx:xxxx:xxxx:xxxx" or "xx:xx:xx:xx:xx:xx:xx:xx".
+            let groups: Vec<&str> = s.split(':').collect();
+            let width = match groups.len() {
+                4 => 4,
+                8 => 2,
+                _ => return Err(invalid()),
+            };
+            if groups.iter().any(|g| g.len() != width) {
+                return Err(invalid());
+            }
+            groups.concat()
+        } else {
+            s.to_string()
+        };
+
+        if digits.is_empty() || digits.len() > 16 || !digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(invalid());
+        }
+
+        u64::from_str_radix(&digits, 16)
+            .map(Guid)
+            .map_err(|_| invalid())
+    }
+}
+
+impl Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.pad(&self.to_colon_string())
+        } else {
+            f.pad(&self.to_hex_string())
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IbDevice {
     pub name: String,
     pub slot_name: String,
-    pub node_guid: String,
+    pub node_guid: Guid,
     pub node_desc: String,
-    pub sys_image_guid: String,
+    pub sys_image_guid: Guid,
     pub fw_ver: String,
     pub board_id: String,
+    pub attr: IbDeviceAttr,
     pub ib_ports: Vec<IbPort>,
 }
 
@@ -72,16 +166,253 @@ impl TryFrom<Device> for IbDevice {
         Ok(Self {
             name: get_property(&dev, "NAME")?.to_string(),
             slot_name,
-            node_guid: get_sysattr(&dev, "node_guid")?.to_string(),
+            node_guid: get_sysattr(&dev, "node_guid")?.parse()?,
             node_desc: get_sysattr(&dev, "node_desc")?.to_string(),
-            sys_image_guid: get_sysattr(&dev, "sys_image_guid")?.to_string(),
+            sys_image_guid: get_sysattr(&dev, "sys_image_guid")?.parse()?,
             fw_ver: get_sysattr(&dev, "fw_ver")?.to_string(),
             board_id: get_sysattr(&dev, "board_id")?.to_string(),
+            attr: IbDeviceAttr::default(),
             ib_ports: vec![],
         })
     }
 }
 
+/// The support of the atomic operations of a device.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IbAtomicCap {
+    #[default]
+    None,
+    /// Atomic among the QPs of this device only.
+    Hca,
+    /// Atomic among all the devices and the CPUs.
+    Global,
+    /// A capability unknown to this version, e.g. of a newer libibverbs.
+    Unknown(u32),
+}
+
+impl Display for IbAtomicCap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Hca => f.write_str("HCA"),
+            Self::Global => f.write_str("Global"),
+            Self::Unknown(v) => write!(f, "Unknown({v})"),
+        }
+    }
+}
+
+impl From<u32> for IbAtomicCap {
+    fn from(v: u32) -> Self {
+        match v {
+            ib::IBV_ATOMIC_NONE => Self::None,
+            ib::IBV_ATOMIC_HCA => Self::Hca,
+            ib::IBV_ATOMIC_GLOB => Self::Global,
+
+            _ => Self::Unknown(v),
+        }
+    }
+}
+
+/// The attributes of a device from `ibv_query_device`, i.e. its limits and capabilities.
+#[derive(Clone, Debug, Default)]
+pub struct IbDeviceAttr {
+    pub vendor_id: u32,
+    pub vendor_part_id: u32,
+    pub hw_ver: u32,
+    pub max_mr_size: u64,
+    pub page_size_cap: u64,
+    /// The `IBV_DEVICE_*` capabilities.
+    pub device_cap_flags: u32,
+    pub max_qp: i32,
+    pub max_qp_wr: i32,
+    pub max_sge: i32,
+    pub max_sge_rd: i32,
+    pub max_cq: i32,
+    pub max_cqe: i32,
+    pub max_mr: i32,
+    pub max_pd: i32,
+    pub max_qp_rd_atom: i32,
+    pub max_ee_rd_atom: i32,
+    pub max_res_rd_atom: i32,
+    pub max_qp_init_rd_atom: i32,
+    pub max_ee_init_rd_atom: i32,
+    pub atomic_cap: IbAtomicCap,
+    pub max_ee: i32,
+    pub max_rdd: i32,
+    pub max_mw: i32,
+    pub max_raw_ipv6_qp: i32,
+    pub max_raw_ethy_qp: i32,
+    pub max_mcast_grp: i32,
+    pub max_mcast_qp_attach: i32,
+    pub max_total_mcast_qp_attach: i32,
+    pub max_ah: i32,
+    pub max_fmr: i32,
+    pub max_map_per_fmr: i32,
+    pub max_srq: i32,
+    pub max_srq_wr: i32,
+    pub max_srq_sge: i32,
+    pub max_pkeys: u16,
+    pub local_ca_ack_delay: u8,
+    pub phys_port_cnt: u8,
+    /// The extended attributes from `ibv_query_device_ex`; `None` if the provider of the device
+    /// does not support it or the query fails.
+    pub ex: Option<IbDeviceAttrEx>,
+}
+
+impl From<&ibv_device_attr> for IbDeviceAttr {
+    fn from(attr: &ibv_device_attr) -> Self {
+        Self {
+            vendor_id: attr.vendor_id,
+            vendor_part_id: attr.vendor_part_id,
+            hw_ver: attr.hw_ver,
+            max_mr_size: attr.max_mr_size,
+            page_size_cap: attr.page_size_cap,
+            device_cap_flags: attr.device_cap_flags,
+            max_qp: attr.max_qp,
+            max_qp_wr: attr.max_qp_wr,
+            max_sge: attr.max_sge,
+            max_sge_rd: attr.max_sge_rd,
+            max_cq: attr.max_cq,
+            max_cqe: attr.max_cqe,
+            max_mr: attr.max_mr,
+            max_pd: attr.max_pd,
+            max_qp_rd_atom: attr.max_qp_rd_atom,
+            max_ee_rd_atom: attr.max_ee_rd_atom,
+            max_res_rd_atom: attr.max_res_rd_atom,
+            max_qp_init_rd_atom: attr.max_qp_init_rd_atom,
+            max_ee_init_rd_atom: attr.max_ee_init_rd_atom,
+            atomic_cap: IbAtomicCap::from(attr.atomic_cap),
+            max_ee: attr.max_ee,
+            max_rdd: attr.max_rdd,
+            max_mw: attr.max_mw,
+            max_raw_ipv6_qp: attr.max_raw_ipv6_qp,
+            max_raw_ethy_qp: attr.max_raw_ethy_qp,
+            max_mcast_grp: attr.max_mcast_grp,
+            max_mcast_qp_attach: attr.max_mcast_qp_attach,
+            max_total_mcast_qp_attach: attr.max_total_mcast_qp_attach,
+            max_ah: attr.max_ah,
+            max_fmr: attr.max_fmr,
+            max_map_per_fmr: attr.max_map_per_fmr,
+            max_srq: attr.max_srq,
+            max_srq_wr: attr.max_srq_wr,
+            max_srq_sge: attr.max_srq_sge,
+            max_pkeys: attr.max_pkeys,
+            local_ca_ack_delay: attr.local_ca_ack_delay,
+            phys_port_cnt: attr.phys_port_cnt,
+            ex: None,
+        }
+    }
+}
+
+/// The extended attributes of a device from `ibv_query_device_ex`.
+#[derive(Clone, Debug, Default)]
+pub struct IbDeviceAttrEx {
+    pub odp_caps: IbOdpCaps,
+    pub completion_timestamp_mask: u64,
+    /// The frequency of the HCA core clock in kHz.
+    pub hca_core_clock: u64,
+    /// The `IBV_DEVICE_*` capabilities, including the ones beyond 32 bits.
+    pub device_cap_flags_ex: u64,
+    pub tso_caps: IbTsoCaps,
+    pub rss_caps: IbRssCaps,
+    pub max_wq_type_rq: u32,
+    pub packet_pacing_caps: IbPacketPacingCaps,
+    /// The `IBV_RAW_PACKET_CAP_*` capabilities.
+    pub raw_packet_caps: u32,
+    pub max_dm_size: u64,
+    pub phys_port_cnt_ex: u32,
+}
+
+impl From<&ibv_device_attr_ex> for IbDeviceAttrEx {
+    fn from(attr: &ibv_device_attr_ex) -> Self {
+        let odp = &attr.odp_caps;
+        let tso = &attr.tso_caps;
+        let rss = &attr.rss_caps;
+        let pacing = &attr.packet_pacing_caps;
+
+        Self {
+            odp_caps: IbOdpCaps {
+                general_caps: odp.general_caps,
+                rc_odp_caps: odp.per_transport_caps.rc_odp_caps,
+                uc_odp_caps: odp.per_transport_caps.uc_odp_caps,
+                ud_odp_caps: odp.per_transport_caps.ud_odp_caps,
+                xrc_odp_caps: attr.xrc_odp_caps,
+            },
+            completion_timestamp_mask: attr.completion_timestamp_mask,
+            hca_core_clock: attr.hca_core_clock,
+            device_cap_flags_ex: attr.device_cap_flags_ex,
+            tso_caps: IbTsoCaps {
+                max_tso: tso.max_tso,
+                supported_qpts: tso.supported_qpts,
+            },
+            rss_caps: IbRssCaps {
+                supported_qpts: rss.supported_qpts,
+                max_rwq_indirection_tables: rss.max_rwq_indirection_tables,
+                max_rwq_indirection_table_size: rss.max_rwq_indirection_table_size,
+                rx_hash_fields_mask: rss.rx_hash_fields_mask,
+                rx_hash_function: rss.rx_hash_function,
+            },
+            max_wq_type_rq: attr.max_wq_type_rq,
+            packet_pacing_caps: IbPacketPacingCaps {
+                qp_rate_limit_min: pacing.qp_rate_limit_min,
+                qp_rate_limit_max: pacing.qp_rate_limit_max,
+                supported_qpts: pacing.supported_qpts,
+            },
+            raw_packet_caps: attr.raw_packet_caps,
+            max_dm_size: attr.max_dm_size,
+            phys_port_cnt_ex: attr.phys_port_cnt_ex,
+        }
+    }
+}
+
+/// The On-Demand Paging capabilities; the per-transport ones are `IBV_ODP_SUPPORT_*` bits.
+#[derive(Clone, Debug, Default)]
+pub struct IbOdpCaps {
+    /// The `IBV_ODP_SUPPORT` and `IBV_ODP_SUPPORT_IMPLICIT` bits.
+    pub general_caps: u64,
+    pub rc_odp_caps: u32,
+    pub uc_odp_caps: u32,
+    pub ud_odp_caps: u32,
+    pub xrc_odp_caps: u32,
+}
+
+impl IbOdpCaps {
+    pub fn is_supported(&self) -> bool {
+        self.general_caps & u64::from(ib::IBV_ODP_SUPPORT) != 0
+    }
+
+    /// Whether the implicit ODP, i.e. registering the whole address space, is supported.
+    pub fn is_implicit_supported(&self) -> bool {
+        self.general_caps & u64::from(ib::IBV_ODP_SUPPORT_IMPLICIT) != 0
+    }
+}
+
+/// The TCP Segmentation Offload capabilities; `supported_qpts` is a bitmap of `1 << qp_type`.
+#[derive(Clone, Debug, Default)]
+pub struct IbTsoCaps {
+    pub max_tso: u32,
+    pub supported_qpts: u32,
+}
+
+/// The Receive Side Scaling capabilities; `supported_qpts` is a bitmap of `1 << qp_type`.
+#[derive(Clone, Debug, Default)]
+pub struct IbRssCaps {
+    pub supported_qpts: u32,
+    pub max_rwq_indirection_tables: u32,
+    pub max_rwq_indirection_table_size: u32,
+    pub rx_hash_fields_mask: u64,
+    pub rx_hash_function: u8,
+}
+
+/// The packet pacing capabilities, i.e. the rate limits of the QPs in kbps; `supported_qpts`
+/// is a bitmap of `1 << qp_type`.
+#[derive(Clone, Debug, Default)]
+pub struct IbPacketPacingCaps {
+    pub qp_rate_limit_min: u32,
+    pub qp_rate_limit_max: u32,
+    pub supported_qpts: u32,
+}
+
 #[derive(Clone)]
 pub enum IbPortLinkType {
     Ethernet,
@@ -171,7 +502,7 @@ impl TryFrom<u8> for IbPortPhysState {
 #[derive(Clone)]
 pub struct IbPort {
     pub port_num: u8,
-    pub guid: Option<String>,
+    pub guid: Option<Guid>,
     pub subnet: Option<String>,
     pub lid: u16,
     pub link_type: IbPortLinkType,
@@ -0,0 +1,118 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Guid, PartitionKey, PartitionQoS, PortConfig, UFMError};
+
+/// An operation of a batch, e.g. in JSON:
+///
+/// ```json
+/// [
+///   {"op": "create", "pkey": "0x10", "ipoib": true, "members": [{"guid": "1070fd0300176625"}]},
+///   {"op": "bind", "pkey": "0x11", "members": [{"guid": "0011223344560200", "membership": "limited"}]},
+///   {"op": "unbind", "pkey": "0x12", "guids": ["0011223344560201"]},
+///   {"op": "delete", "pkey": "0x13"}
+/// ]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOp {
+    /// Create the partition with the members, if any.
+    Create {
+        pkey: PartitionKey,
+        #[serde(default)]
+        ipoib: bool,
+        #[serde(default)]
+        qos: PartitionQoS,
+        #[serde(default)]
+        members: Vec<PortConfig>,
+    },
+    Delete {
+        pkey: PartitionKey,
+    },
+    Bind {
+        pkey: PartitionKey,
+        members: Vec<PortConfig>,
+    },
+    Unbind {
+        pkey: PartitionKey,
+        guids: Vec<Guid>,
+    },
+}
+
+impl BatchOp {
+    /// Load the operations from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<BatchOp>, UFMError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            UFMError::InvalidConfig(format!("failed to read '{}': {}", path.display(), e))
+        })?;
+
+        serde_json::from_str(&data)
+            .map_err(|e| UFMError::InvalidConfig(format!("invalid '{}': {}", path.display(), e)))
+    }
+
+    pub fn pkey(&self) -> &PartitionKey {
+        match self {
+            BatchOp::Create { pkey, .. }
+            | BatchOp::Delete { pkey }
+            | BatchOp::Bind { pkey, .. }
+            | BatchOp::Unbind { pkey, .. } => pkey,
+        }
+    }
+}
+
+impl fmt::Display for BatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOp::Create { pkey, members, .. } => {
+                write!(f, "create {} ({} ports)", pkey, members.len())
+            }
+            BatchOp::Delete { pkey } => write!(f, "delete {}", pkey),
+            BatchOp::Bind { pkey, members } => write!(f, "bind {} ({} ports)", pkey, members.len()),
+            BatchOp::Unbind { pkey, guids } => write!(f, "unbind {} ({} ports)", pkey, guids.len()),
+        }
+    }
+}
+
+/// The result of an operation in a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub op: BatchOp,
+    pub result: Result<(), UFMError>,
+}
+
+/// The results of a batch, in the order of its operations.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub results: Vec<BatchResult>,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|r| r.result.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &BatchResult> {
+        self.results.iter().filter(|r| r.result.is_err())
+    }
+
+    /// An error of all the failed operations, if any.
+    pub fn error(&self) -> Option<UFMError> {
+        let failed: Vec<String> = self
+            .failed()
+            .filter_map(|r| Some(format!("{}: {}", r.op, r.result.as_ref().err()?)))
+            .collect();
+        if failed.is_empty() {
+            return None;
+        }
+
+        Some(UFMError::Internal(format!(
+            "{} of {} operations failed: {}",
+            failed.len(),
+            self.results.len(),
+            failed.join("; ")
+        )))
+    }
+}
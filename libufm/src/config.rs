@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    PoolConfig, RetryPolicy, TimeoutConfig, TlsConfig, TlsMode, UFMCert, UFMConfig, UFMError,
+};
+
+/// A builder of `UFMConfig`, which validates the address and the authentication.
+#[derive(Default)]
+pub struct UFMConfigBuilder {
+    conf: UFMConfig,
+}
+
+impl UFMConfig {
+    pub fn builder() -> UFMConfigBuilder {
+        UFMConfigBuilder::default()
+    }
+}
+
+impl UFMConfigBuilder {
+    /// The URL of UFM, e.g. `https://ufm.example.com`.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.conf.address = address.into();
+        self
+    }
+
+    /// Authenticate by the username and password.
+    pub fn basic_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.conf.username = Some(username.into());
+        self.conf.password = Some(password.into());
+        self
+    }
+
+    /// Authenticate by a session of UFM, which is logged in by the username and password.
+    pub fn session_auth(self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        let mut builder = self.basic_auth(username, password);
+        builder.conf.session = true;
+        builder
+    }
+
+    /// Authenticate by the access token of UFM.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.conf.token = Some(token.into());
+        self
+    }
+
+    /// Authenticate by the client certificate.
+    pub fn cert(mut self, cert: UFMCert) -> Self {
+        self.conf.cert = Some(cert);
+        self
+    }
+
+    pub fn tls_mode(mut self, mode: TlsMode) -> Self {
+        self.conf.tls.mode = mode;
+        self
+    }
+
+    /// The server name to verify the certificate of UFM against.
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.conf.tls.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.conf.tls = tls;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.conf.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: TimeoutConfig) -> Self {
+        self.conf.timeout = timeout;
+        self
+    }
+
+    pub fn pool(mut self, pool: PoolConfig) -> Self {
+        self.conf.pool = pool;
+        self
+    }
+
+    pub fn build(self) -> Result<UFMConfig, UFMError> {
+        let conf = self.conf;
+
+        if conf.address.is_empty() {
+            return Err(UFMError::InvalidConfig("UFM address is empty".to_string()));
+        }
+        let addr = Url::parse(&conf.address)
+            .map_err(|_| UFMError::InvalidConfig(format!("invalid UFM url: {}", conf.address)))?;
+        if !matches!(addr.scheme(), "http" | "https") || addr.host_str().is_none() {
+            return Err(UFMError::InvalidConfig(format!(
+                "invalid UFM url: {}",
+                conf.address
+            )));
+        }
+
+        let basic = conf.username.is_some() || conf.password.is_some();
+        let auths = [basic, conf.token.is_some(), conf.cert.is_some()];
+        match auths.iter().filter(|a| **a).count() {
+            0 => {
+                return Err(UFMError::InvalidConfig(
+                    "no authentication of UFM; one of token, username/password and client certificate is required".to_string(),
+                ))
+            }
+            1 => {}
+            _ => {
+                return Err(UFMError::InvalidConfig(
+                    "only one of token, username/password and client certificate can be used"
+                        .to_string(),
+                ))
+            }
+        }
+
+        if basic && conf.username.is_none() {
+            return Err(UFMError::InvalidConfig("username is empty".to_string()));
+        }
+        if basic && conf.password.is_none() {
+            return Err(UFMError::InvalidConfig("password is empty".to_string()));
+        }
+        if conf.session && !basic {
+            return Err(UFMError::InvalidConfig(
+                "the session requires username and password".to_string(),
+            ));
+        }
+
+        Ok(conf)
+    }
+}
+
+/// A named profile of UFM in the config file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Log in a session by the username and password.
+    pub session: bool,
+    /// The CA to verify UFM with; it's also the CA of the client certificate if there's one.
+    pub ca_crt: Option<String>,
+    pub tls_key: Option<String>,
+    pub tls_crt: Option<String>,
+    pub insecure_skip_verify: bool,
+    pub server_name: Option<String>,
+}
+
+impl Profile {
+    /// A builder of `UFMConfig` with the settings of the profile.
+    pub fn builder(&self) -> Result<UFMConfigBuilder, UFMError> {
+        let mut builder = UFMConfig::builder();
+
+        if let Some(address) = &self.address {
+            builder = builder.address(address);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        if self.username.is_some() || self.password.is_some() {
+            builder.conf.username = self.username.clone();
+            builder.conf.password = self.password.clone();
+        }
+        builder.conf.session = self.session;
+
+        let cert = match (&self.ca_crt, &self.tls_key, &self.tls_crt) {
+            (_, None, None) => None,
+            (Some(ca_crt), Some(tls_key), Some(tls_crt)) => Some(UFMCert {
+                ca_crt: ca_crt.to_string(),
+                tls_key: tls_key.to_string(),
+                tls_crt: tls_crt.to_string(),
+            }),
+            _ => {
+                return Err(UFMError::InvalidConfig(
+                    "ca_crt, tls_key and tls_crt are required by the client certificate"
+                        .to_string(),
+                ))
+            }
+        };
+
+        builder = match (self.insecure_skip_verify, &cert, &self.ca_crt) {
+            (true, _, _) => builder.tls_mode(TlsMode::InsecureSkipVerify),
+            (false, None, Some(ca_crt)) => builder.tls_mode(TlsMode::CustomCa(ca_crt.to_string())),
+            _ => builder,
+        };
+        if let Some(cert) = cert {
+            builder = builder.cert(cert);
+        }
+        if let Some(server_name) = &self.server_name {
+            builder = builder.server_name(server_name);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// The config file of named UFM profiles, in TOML or YAML, e.g.
+///
+/// ```toml
+/// default = "lab"
+///
+/// [profiles.lab]
+/// address = "https://ufm-lab"
+/// token = "XlojlA7zgotVegyIEIP5vnw5C7ZYT9"
+///
+/// [profiles.prod]
+/// address = "https://ufm"
+/// ca_crt = "/etc/ufm/ca.crt"
+/// tls_key = "/etc/ufm/client.key"
+/// tls_crt = "/etc/ufm/client.crt"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// The profile to use if none is selected.
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileConfig {
+    /// Load the config file; it's parsed as YAML if its extension is `yaml` or `yml`,
+    /// otherwise as TOML.
+    pub fn load(path: impl AsRef<Path>) -> Result<ProfileConfig, UFMError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            UFMError::InvalidConfig(format!("failed to read '{}': {}", path.display(), e))
+        })?;
+
+        let res = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&data).map_err(|e| e.to_string()),
+            _ => toml::from_str(&data).map_err(|e| e.to_string()),
+        };
+
+        res.map_err(|e| UFMError::InvalidConfig(format!("invalid '{}': {}", path.display(), e)))
+    }
+
+    pub fn from_toml(data: &str) -> Result<ProfileConfig, UFMError> {
+        toml::from_str(data).map_err(|e| UFMError::InvalidConfig(e.to_string()))
+    }
+
+    pub fn from_yaml(data: &str) -> Result<ProfileConfig, UFMError> {
+        serde_yaml::from_str(data).map_err(|e| UFMError::InvalidConfig(e.to_string()))
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Profile, UFMError> {
+        self.profiles
+            .get(name)
+            .ok_or(UFMError::InvalidConfig(format!(
+                "profile '{}' not found",
+                name
+            )))
+    }
+
+    /// The default profile, if there's one.
+    pub fn default_profile(&self) -> Result<Option<&Profile>, UFMError> {
+        match &self.default {
+            None => Ok(None),
+            Some(name) => self.get(name).map(Some),
+        }
+    }
+}
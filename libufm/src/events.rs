@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::UFMError;
+
+/// The severity of the events and alarms, from the lowest to the highest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Minor,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Minor => "Minor",
+            Severity::Critical => "Critical",
+        };
+        f.pad(s)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = UFMError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "minor" => Ok(Severity::Minor),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(UFMError::InvalidConfig(format!("invalid severity: {}", s))),
+        }
+    }
+}
+
+/// An event of UFM, e.g. a link went down.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Event {
+    pub id: u64,
+    pub name: String,
+    pub severity: Severity,
+    /// When the event happened, e.g. `2024-01-01 10:00:00`.
+    pub timestamp: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// The type of the object, e.g. `Port`.
+    #[serde(default, rename = "type")]
+    pub object_type: Option<String>,
+    /// The object of the event, e.g. the name of a port `b83fd203002a1f3a_1`.
+    #[serde(default)]
+    pub object_name: Option<String>,
+    #[serde(default)]
+    pub object_path: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub counter: Option<u64>,
+}
+
+/// An alarm of UFM, which is raised by the events until it's cleared.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Alarm {
+    pub id: u64,
+    pub name: String,
+    pub severity: Severity,
+    /// When the alarm was raised, e.g. `2024-01-01 10:00:00`.
+    pub timestamp: String,
+    /// The type of the object, e.g. `Port`.
+    #[serde(default, rename = "type")]
+    pub object_type: Option<String>,
+    #[serde(default)]
+    pub object_name: Option<String>,
+    #[serde(default)]
+    pub object_path: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub counter: Option<u64>,
+}
+
+/// The filter of the events and alarms; an item matches if it matches all the conditions which are set.
+/// The conditions are sent to UFM in the query, so only the matching items are transferred.
+///
+/// The times are in UFM's format, e.g. `2024-01-01 10:00:00`.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// The lowest severity of the items.
+    pub min_severity: Option<Severity>,
+    /// The items at or after the time.
+    pub since: Option<String>,
+    /// The items before the time.
+    pub until: Option<String>,
+    /// A part of the name or the path of the object, e.g. a port GUID.
+    pub object: Option<String>,
+    /// The items after the ID.
+    pub after_id: Option<u64>,
+    /// The max number of the items, from the lowest IDs.
+    pub limit: Option<usize>,
+}
+
+impl EventFilter {
+    /// The query of the conditions which are set, e.g. `min_severity=Warning&after_id=10`.
+    pub(crate) fn query(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(s) = &self.min_severity {
+            query.append_pair("min_severity", &s.to_string());
+        }
+        if let Some(t) = &self.since {
+            query.append_pair("since", t);
+        }
+        if let Some(t) = &self.until {
+            query.append_pair("until", t);
+        }
+        if let Some(o) = &self.object {
+            query.append_pair("object", o);
+        }
+        if let Some(a) = &self.after_id {
+            query.append_pair("after_id", &a.to_string());
+        }
+        if let Some(l) = &self.limit {
+            query.append_pair("limit", &l.to_string());
+        }
+
+        query.finish()
+    }
+
+    fn matches(
+        &self,
+        id: u64,
+        severity: Severity,
+        timestamp: &str,
+        object_name: &Option<String>,
+        object_path: &Option<String>,
+    ) -> bool {
+        let object = match &self.object {
+            None => true,
+            Some(o) => [object_name, object_path]
+                .into_iter()
+                .flatten()
+                .any(|s| s.to_lowercase().contains(&o.to_lowercase())),
+        };
+
+        object
+            && self.min_severity.is_none_or(|s| severity >= s)
+            && self.since.as_deref().is_none_or(|t| timestamp >= t)
+            && self.until.as_deref().is_none_or(|t| timestamp < t)
+            && self.after_id.is_none_or(|a| id > a)
+    }
+
+    pub fn matches_event(&self, e: &Event) -> bool {
+        self.matches(
+            e.id,
+            e.severity,
+            &e.timestamp,
+            &e.object_name,
+            &e.object_path,
+        )
+    }
+
+    pub fn matches_alarm(&self, a: &Alarm) -> bool {
+        self.matches(
+            a.id,
+            a.severity,
+            &a.timestamp,
+            &a.object_name,
+            &a.object_path,
+        )
+    }
+}
@@ -1,69 +1,468 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use base64::prelude::*;
-use serde::{Deserialize, Serialize};
+use futures_util::{stream, Stream, StreamExt};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 use url::Url;
 
+use self::monitoring::SessionData;
 use self::rest::{RestClient, RestClientConfig, RestError, RestScheme};
-use self::types::{Configuration, PhysicalPort, Port};
+use self::types::{PhysicalPort, UfmLink, VirtualPort};
 
+mod batch;
+mod config;
+mod events;
+mod monitoring;
+mod reconcile;
 mod rest;
+mod snapshot;
+mod topology;
 mod types;
 
-pub use types::PortType;
+pub use batch::{BatchOp, BatchReport, BatchResult};
+pub use config::{Profile, ProfileConfig, UFMConfigBuilder};
+pub use events::{Alarm, Event, EventFilter, Severity};
+pub use monitoring::{Counter, CounterSample, MonitoringSession, PortCounters};
+pub use reconcile::{ApplyReport, PartitionChange, PartitionSpec, Plan};
+pub use rest::{PoolConfig, RetryPolicy, TimeoutConfig, TlsConfig, TlsMode};
+pub use snapshot::{PartitionSnapshot, SnapshotChange};
+pub use topology::{Topology, TopologyNode};
+pub use types::{
+    Configuration, Lid, Link, LinkEnd, LogicalState, PhysicalState, Port, PortFilter, PortType,
+    System, SystemType,
+};
+
+/// The highest service level of InfiniBand.
+const MAX_SERVICE_LEVEL: u8 = 15;
+
+/// The max number of the events of a poll in `Ufm::follow_events`.
+const FOLLOW_EVENTS_LIMIT: usize = 1000;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PartitionQoS {
-    // Default 2k; one of 2k or 4k; the MTU of the services.
-    pub mtu_limit: u16,
+    // Default 2k; one of 2k or 4k, the MTU of the services.
+    pub mtu_limit: IbMtu,
     // Default is 0, value can be range from 0-15
     pub service_level: u8,
-    // Default is 2.5, can be one of the following: 2.5, 10, 30, 5, 20, 40, 60, 80, 120, 14, 56, 112, 168, 25, 100, 200, or 300
-    pub rate_limit: f64,
+    // Default is 2.5 Gb/s.
+    pub rate_limit: IbRate,
 }
 
 impl Default for PartitionQoS {
     fn default() -> Self {
         Self {
-            mtu_limit: 2,
+            mtu_limit: IbMtu::Mtu2048,
             service_level: 0,
-            rate_limit: 2.5_f64,
+            rate_limit: IbRate::Rate2_5,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl PartitionQoS {
+    /// Check the QoS before it's sent to UFM, which rejects the invalid QoS without details.
+    pub fn validate(&self) -> Result<(), UFMError> {
+        if self.service_level > MAX_SERVICE_LEVEL {
+            return Err(UFMError::InvalidQoS(format!(
+                "service level {} is not in 0-{}",
+                self.service_level, MAX_SERVICE_LEVEL
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The MTU of the partitions, which UFM supports 2048 and 4096 bytes only. It's printed and
+/// serialized in bytes, and sent to UFM in KB, i.e. `2` or `4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IbMtu {
+    Mtu2048,
+    Mtu4096,
+}
+
+impl IbMtu {
+    pub const ALL: [IbMtu; 2] = [IbMtu::Mtu2048, IbMtu::Mtu4096];
+
+    pub fn bytes(&self) -> u16 {
+        match self {
+            IbMtu::Mtu2048 => 2048,
+            IbMtu::Mtu4096 => 4096,
+        }
+    }
+
+    /// The MTU in KB as UFM takes it.
+    pub fn kb(&self) -> u16 {
+        self.bytes() / 1024
+    }
+
+    pub fn from_bytes(bytes: u16) -> Option<IbMtu> {
+        IbMtu::ALL.into_iter().find(|m| m.bytes() == bytes)
+    }
+
+    pub fn from_kb(kb: u16) -> Option<IbMtu> {
+        IbMtu::ALL.into_iter().find(|m| m.kb() == kb)
+    }
+
+    /// The MTU of either the bytes, e.g. `2048`, or the KB of UFM, e.g. `2`, which never overlap.
+    fn from_value(value: u64) -> Result<IbMtu, UFMError> {
+        u16::try_from(value)
+            .ok()
+            .and_then(|v| IbMtu::from_bytes(v).or_else(|| IbMtu::from_kb(v)))
+            .ok_or(UFMError::InvalidQoS(format!(
+                "mtu {} is not supported by UFM; one of 2048, 4096",
+                value
+            )))
+    }
+
+    /// Serialize the MTU in KB, for the requests to UFM.
+    fn serialize_kb<S: Serializer>(mtu: &IbMtu, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(mtu.kb())
+    }
+}
+
+impl fmt::Display for IbMtu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.bytes().to_string())
+    }
+}
+
+impl FromStr for IbMtu {
+    type Err = UFMError;
+
+    /// Parse the MTU in bytes, e.g. `4096`, or in KB as UFM takes it, e.g. `4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s
+            .trim()
+            .parse()
+            .map_err(|_| UFMError::InvalidQoS(format!("invalid mtu: {}", s)))?;
+
+        IbMtu::from_value(v)
+    }
+}
+
+impl Serialize for IbMtu {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.bytes())
+    }
+}
+
+/// The MTU is read in either bytes or KB, e.g. from UFM.
+impl<'de> Deserialize<'de> for IbMtu {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IbMtu::from_value(u64::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// The rate limits of UFM, in Gb/s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IbRate {
+    Rate2_5,
+    Rate5,
+    Rate10,
+    Rate14,
+    Rate20,
+    Rate25,
+    Rate30,
+    Rate40,
+    Rate56,
+    Rate60,
+    Rate80,
+    Rate100,
+    Rate112,
+    Rate120,
+    Rate168,
+    Rate200,
+    Rate300,
+}
+
+impl IbRate {
+    pub const ALL: [IbRate; 17] = [
+        IbRate::Rate2_5,
+        IbRate::Rate5,
+        IbRate::Rate10,
+        IbRate::Rate14,
+        IbRate::Rate20,
+        IbRate::Rate25,
+        IbRate::Rate30,
+        IbRate::Rate40,
+        IbRate::Rate56,
+        IbRate::Rate60,
+        IbRate::Rate80,
+        IbRate::Rate100,
+        IbRate::Rate112,
+        IbRate::Rate120,
+        IbRate::Rate168,
+        IbRate::Rate200,
+        IbRate::Rate300,
+    ];
+
+    /// The rate in Gb/s, as UFM takes it.
+    pub fn gbps(&self) -> f64 {
+        match self {
+            IbRate::Rate2_5 => 2.5,
+            IbRate::Rate5 => 5.0,
+            IbRate::Rate10 => 10.0,
+            IbRate::Rate14 => 14.0,
+            IbRate::Rate20 => 20.0,
+            IbRate::Rate25 => 25.0,
+            IbRate::Rate30 => 30.0,
+            IbRate::Rate40 => 40.0,
+            IbRate::Rate56 => 56.0,
+            IbRate::Rate60 => 60.0,
+            IbRate::Rate80 => 80.0,
+            IbRate::Rate100 => 100.0,
+            IbRate::Rate112 => 112.0,
+            IbRate::Rate120 => 120.0,
+            IbRate::Rate168 => 168.0,
+            IbRate::Rate200 => 200.0,
+            IbRate::Rate300 => 300.0,
+        }
+    }
+
+    pub fn from_gbps(gbps: f64) -> Option<IbRate> {
+        IbRate::ALL.into_iter().find(|r| r.gbps() == gbps)
+    }
+
+    fn from_value(gbps: f64) -> Result<IbRate, UFMError> {
+        IbRate::from_gbps(gbps).ok_or_else(|| {
+            let rates: Vec<String> = IbRate::ALL.iter().map(|r| r.to_string()).collect();
+            UFMError::InvalidQoS(format!(
+                "rate limit {} is not one of {}",
+                gbps,
+                rates.join(", ")
+            ))
+        })
+    }
+}
+
+impl fmt::Display for IbRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.gbps().to_string())
+    }
+}
+
+impl FromStr for IbRate {
+    type Err = UFMError;
+
+    /// Parse the rate in Gb/s, e.g. `2.5` or `100`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s
+            .trim()
+            .parse()
+            .map_err(|_| UFMError::InvalidQoS(format!("invalid rate limit: {}", s)))?;
+
+        IbRate::from_value(v)
+    }
+}
+
+impl Serialize for IbRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.gbps())
+    }
+}
+
+impl<'de> Deserialize<'de> for IbRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IbRate::from_value(f64::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum PortMembership {
     Limited,
+    #[default]
     Full,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PortConfig {
     /// The GUID of Port.
-    pub guid: String,
+    pub guid: Guid,
     /// Default false; store the PKey at index 0 of the PKey table of the GUID.
+    #[serde(default)]
     pub index0: bool,
     /// Default is full:
     ///   "full"    - members with full membership can communicate with all hosts (members) within the network/partition
     ///   "limited" - members with limited membership cannot communicate with other members with limited membership.
     ///               However, communication is allowed between every other combination of membership types.
+    #[serde(default)]
     pub membership: PortMembership,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The 64-bit GUID of a port or a node.
+///
+/// It accepts the common notations when parsing, e.g. `0011223344560200`,
+/// `0x0011223344560200`, `0011:2233:4456:0200` or `00:11:22:33:44:56:02:00`.
+/// It is displayed in UFM's notation (`0011223344560200`) by default, and in
+/// the colon-separated notation (`0011:2233:4456:0200`) with `{:#}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Guid(u64);
+
+impl Guid {
+    /// The GUID in UFM's notation, e.g. `0011223344560200`.
+    pub fn to_hex_string(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+
+    /// The GUID in colon-separated notation, e.g. `0011:2233:4456:0200`.
+    pub fn to_colon_string(&self) -> String {
+        format!(
+            "{:04x}:{:04x}:{:04x}:{:04x}",
+            (self.0 >> 48) & 0xffff,
+            (self.0 >> 32) & 0xffff,
+            (self.0 >> 16) & 0xffff,
+            self.0 & 0xffff
+        )
+    }
+}
+
+impl From<u64> for Guid {
+    fn from(v: u64) -> Self {
+        Guid(v)
+    }
+}
+
+impl From<Guid> for u64 {
+    fn from(v: Guid) -> u64 {
+        v.0
+    }
+}
+
+impl FromStr for Guid {
+    type Err = UFMError;
+
+    fn from_str(guid: &str) -> Result<Self, Self::Err> {
+        let invalid = || UFMError::InvalidGuid(guid.to_string());
+
+        let s = guid.trim().to_lowercase();
+        let s = s.strip_prefix(HEX_PRE).unwrap_or(&s);
+
+        let digits = if s.contains(':') {
+            // All groups have the same width, either "xxxx:xxxx:xxxx:xxxx" or "xx:xx:xx:xx:xx:xx:xx:xx".
+            let groups: Vec<&str> = s.split(':').collect();
+            let width = match groups.len() {
+                4 => 4,
+                8 => 2,
+                _ => return Err(invalid()),
+            };
+            if groups.iter().any(|g| g.len() != width) {
+                return Err(invalid());
+            }
+            groups.concat()
+        } else {
+            s.to_string()
+        };
+
+        if digits.is_empty() || digits.len() > 16 || !digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(invalid());
+        }
+
+        u64::from_str_radix(&digits, 16)
+            .map(Guid)
+            .map_err(|_| invalid())
+    }
+}
+
+impl TryFrom<String> for Guid {
+    type Error = UFMError;
+
+    fn try_from(guid: String) -> Result<Self, Self::Error> {
+        Guid::from_str(&guid)
+    }
+}
+
+impl TryFrom<&String> for Guid {
+    type Error = UFMError;
+
+    fn try_from(guid: &String) -> Result<Self, Self::Error> {
+        Guid::from_str(guid)
+    }
+}
+
+impl TryFrom<&str> for Guid {
+    type Error = UFMError;
+
+    fn try_from(guid: &str) -> Result<Self, Self::Error> {
+        Guid::from_str(guid)
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        if f.alternate() {
+            f.pad(&self.to_colon_string())
+        } else {
+            f.pad(&self.to_hex_string())
+        }
+    }
+}
+
+impl Serialize for Guid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Guid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Guid::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// The 15-bit key of a partition, e.g. `0x5`; the default partition is `0x7fff`.
+///
+/// The pkey tables of the ports keep the pkeys as 16-bit entries, whose high bit is the
+/// membership, i.e. `0x8005` is the full membership of `0x5`; see `from_entry` and `to_entry`.
+/// The base `0x0000` is invalid, so are the entries `0x0000` and `0x8000`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PartitionKey(u16);
 
+/// The membership bit of the entries of the pkey tables.
+const PKEY_MEMBERSHIP_BIT: u16 = 0x8000;
+
 impl PartitionKey {
     pub fn is_default(&self) -> bool {
         self.0 == 0x7fff
     }
+
+    /// Whether the entry of a pkey table is invalid, i.e. `0x0000` or `0x8000`.
+    pub fn is_invalid_entry(entry: u16) -> bool {
+        entry & !PKEY_MEMBERSHIP_BIT == 0
+    }
+
+    /// The pkey and the membership of an entry of a pkey table, e.g. `0x8005` is the full
+    /// membership of `0x5`; `None` if the entry is invalid.
+    pub fn from_entry(entry: u16) -> Option<(PartitionKey, PortMembership)> {
+        if PartitionKey::is_invalid_entry(entry) {
+            return None;
+        }
+
+        let membership = match entry & PKEY_MEMBERSHIP_BIT {
+            0 => PortMembership::Limited,
+            _ => PortMembership::Full,
+        };
+
+        Some((PartitionKey(entry & !PKEY_MEMBERSHIP_BIT), membership))
+    }
+
+    /// The entry of the pkey in a pkey table with the membership.
+    pub fn to_entry(&self, membership: &PortMembership) -> u16 {
+        match membership {
+            PortMembership::Full => self.0 | PKEY_MEMBERSHIP_BIT,
+            PortMembership::Limited => self.0,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Partition {
     /// The name of Partition.
     pub name: String,
@@ -76,33 +475,64 @@ pub struct Partition {
     pub qos: PartitionQoS,
 }
 
+/// The membership of a port in a partition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartitionMembership {
+    pub partition: Partition,
+    pub membership: PortMembership,
+    pub index0: bool,
+}
+
+/// An access token of UFM, to authenticate by `UFMConfig::token`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccessToken {
+    pub access_token: String,
+    /// Whether the token was revoked.
+    #[serde(default)]
+    pub revoked: bool,
+    /// When the token was created, e.g. `2023-10-31 10:11:42`.
+    #[serde(default)]
+    pub creation_date: Option<String>,
+}
+
 const HEX_PRE: &str = "0x";
 
 impl TryFrom<u16> for PartitionKey {
     type Error = UFMError;
 
+    /// A pkey without the membership bit, in `0x0001..=0x7fff`.
     fn try_from(pkey: u16) -> Result<Self, Self::Error> {
-        if pkey != (pkey & 0x7fff) {
-            return Err(UFMError::InvalidPKey(pkey.to_string()));
+        if pkey & PKEY_MEMBERSHIP_BIT != 0 || pkey == 0 {
+            return Err(UFMError::InvalidPKey(format!("{HEX_PRE}{:x}", pkey)));
         }
 
         Ok(PartitionKey(pkey))
     }
 }
 
+impl FromStr for PartitionKey {
+    type Err = UFMError;
+
+    /// Parse the pkey in hex with `0x`, e.g. `0x7fff`, or in decimal, e.g. `5`.
+    fn from_str(pkey: &str) -> Result<Self, Self::Err> {
+        let invalid = || UFMError::InvalidPKey(pkey.to_string());
+
+        let p = pkey.trim().to_lowercase();
+        let k = match p.strip_prefix(HEX_PRE) {
+            Some(h) => u16::from_str_radix(h, 16),
+            None => p.parse(),
+        }
+        .map_err(|_| invalid())?;
+
+        PartitionKey::try_from(k).map_err(|_| invalid())
+    }
+}
+
 impl TryFrom<String> for PartitionKey {
     type Error = UFMError;
 
     fn try_from(pkey: String) -> Result<Self, Self::Error> {
-        let pkey = pkey.to_lowercase();
-        let base = if pkey.starts_with(HEX_PRE) { 16 } else { 10 };
-        let p = pkey.trim_start_matches(HEX_PRE);
-        let k = u16::from_str_radix(p, base);
-
-        match k {
-            Ok(v) => Ok(PartitionKey(v)),
-            Err(_e) => Err(UFMError::InvalidPKey(pkey.to_string())),
-        }
+        PartitionKey::from_str(&pkey)
     }
 }
 
@@ -110,7 +540,7 @@ impl TryFrom<&String> for PartitionKey {
     type Error = UFMError;
 
     fn try_from(pkey: &String) -> Result<Self, Self::Error> {
-        PartitionKey::try_from(pkey.to_string())
+        PartitionKey::from_str(pkey)
     }
 }
 
@@ -118,7 +548,32 @@ impl TryFrom<&str> for PartitionKey {
     type Error = UFMError;
 
     fn try_from(pkey: &str) -> Result<Self, Self::Error> {
-        PartitionKey::try_from(pkey.to_string())
+        PartitionKey::from_str(pkey)
+    }
+}
+
+/// The pkey is serialized in UFM's notation, e.g. `"0x5"`, and deserialized from either a
+/// string, e.g. `"0x5"` or `"5"`, or a number, e.g. `5`.
+impl Serialize for PartitionKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PartitionKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Pkey {
+            Number(u16),
+            Text(String),
+        }
+
+        match Pkey::deserialize(deserializer)? {
+            Pkey::Number(n) => PartitionKey::try_from(n),
+            Pkey::Text(s) => PartitionKey::from_str(&s),
+        }
+        .map_err(de::Error::custom)
     }
 }
 
@@ -146,6 +601,15 @@ impl TryFrom<String> for PortMembership {
     }
 }
 
+impl fmt::Display for PortMembership {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortMembership::Full => f.pad("full"),
+            PortMembership::Limited => f.pad("limited"),
+        }
+    }
+}
+
 impl TryFrom<&str> for PortMembership {
     type Error = UFMError;
 
@@ -154,29 +618,136 @@ impl TryFrom<&str> for PortMembership {
     }
 }
 
+/// Group the GUIDs of the ports by their membership and index0, in the order they appear;
+/// UFM takes one membership and index0 for all the GUIDs in a request.
+fn group_ports(ports: Vec<PortConfig>) -> Vec<(PortMembership, bool, Vec<Guid>)> {
+    let mut groups: Vec<(PortMembership, bool, Vec<Guid>)> = Vec::new();
+    for p in ports {
+        match groups
+            .iter_mut()
+            .find(|(m, i, _)| *m == p.membership && *i == p.index0)
+        {
+            Some((_, _, guids)) => guids.push(p.guid),
+            None => groups.push((p.membership, p.index0, vec![p.guid])),
+        }
+    }
+
+    groups
+}
+
+/// A handle to UFM; the clones share the same connections, e.g. to be used by many tasks.
+#[derive(Clone)]
 pub struct Ufm {
-    client: RestClient,
+    client: Arc<RestClient>,
+}
+
+/// The details of a failed request to UFM.
+#[derive(Debug, Clone)]
+pub struct RequestError {
+    /// The HTTP method of the request, e.g. `GET`.
+    pub method: String,
+    /// The path of the request relative to the base path, e.g. `/resources/pkeys/0x5`.
+    pub path: String,
+    /// The HTTP status code replied by UFM; `None` if there's no reply, e.g. timeout.
+    pub status: Option<u16>,
+    /// The `error` field replied by UFM, or the raw body if it's not UFM's error.
+    pub message: String,
+    /// The error JSON replied by UFM, if any.
+    pub body: Option<serde_json::Value>,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.status {
+            Some(status) => write!(
+                f,
+                "{} {} ({}): {}",
+                self.method, self.path, status, self.message
+            ),
+            None => write!(f, "{} {}: {}", self.method, self.path, self.message),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum UFMError {
     #[error("{0}")]
     Internal(String),
-    #[error("'{0}' not found")]
-    NotFound(String),
     #[error("invalid pkey '{0}'")]
     InvalidPKey(String),
+    #[error("invalid guid '{0}'")]
+    InvalidGuid(String),
     #[error("invalid configuration '{0}'")]
     InvalidConfig(String),
+    #[error("invalid qos: {0}")]
+    InvalidQoS(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(RequestError),
+    #[error("forbidden: {0}")]
+    Forbidden(RequestError),
+    #[error("not found: {0}")]
+    NotFound(RequestError),
+    #[error("conflict: {0}")]
+    Conflict(RequestError),
+    #[error("invalid request: {0}")]
+    InvalidRequest(RequestError),
+    #[error("server error: {0}")]
+    ServerError(RequestError),
+    #[error("timeout: {0}")]
+    Timeout(RequestError),
+    #[error("connection failure: {0}")]
+    ConnectionFailure(RequestError),
+}
+
+impl UFMError {
+    /// The details of the failed request, if the error comes from a request to UFM.
+    pub fn request(&self) -> Option<&RequestError> {
+        match self {
+            UFMError::Unauthorized(e)
+            | UFMError::Forbidden(e)
+            | UFMError::NotFound(e)
+            | UFMError::Conflict(e)
+            | UFMError::InvalidRequest(e)
+            | UFMError::ServerError(e)
+            | UFMError::Timeout(e)
+            | UFMError::ConnectionFailure(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code replied by UFM, if any.
+    pub fn status(&self) -> Option<u16> {
+        self.request().and_then(|e| e.status)
+    }
+
+    /// Whether the error is transient, so the same request may succeed later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            UFMError::Timeout(_) | UFMError::ConnectionFailure(_) => true,
+            // 501 Not Implemented will not change by retrying.
+            UFMError::ServerError(e) => e.status != Some(501),
+            // 408 Request Timeout and 429 Too Many Requests.
+            UFMError::InvalidRequest(e) => matches!(e.status, Some(408) | Some(429)),
+            _ => false,
+        }
+    }
 }
 
 impl From<RestError> for UFMError {
     fn from(e: RestError) -> Self {
         match e {
             RestError::Internal(msg) => UFMError::Internal(msg),
-            RestError::NotFound(msg) => UFMError::NotFound(msg),
-            RestError::AuthFailure(msg) => UFMError::InvalidConfig(msg),
             RestError::InvalidConfig(msg) => UFMError::InvalidConfig(msg),
+            RestError::Timeout(e) => UFMError::Timeout(e),
+            RestError::Connection(e) => UFMError::ConnectionFailure(e),
+            RestError::Http(e) => match e.status.unwrap_or_default() {
+                401 => UFMError::Unauthorized(e),
+                403 => UFMError::Forbidden(e),
+                404 => UFMError::NotFound(e),
+                409 => UFMError::Conflict(e),
+                500..=599 => UFMError::ServerError(e),
+                _ => UFMError::InvalidRequest(e),
+            },
         }
     }
 }
@@ -188,12 +759,23 @@ pub struct UFMCert {
     pub tls_crt: String,
 }
 
+#[derive(Default)]
 pub struct UFMConfig {
     pub address: String,
     pub username: Option<String>,
     pub password: Option<String>,
     pub token: Option<String>,
     pub cert: Option<UFMCert>,
+    /// Log in a session by the username and password, instead of sending them in every request.
+    pub session: bool,
+    /// The verification of UFM's certificate for HTTPS.
+    pub tls: TlsConfig,
+    /// The policy to retry the failed requests.
+    pub retry: RetryPolicy,
+    /// The timeouts of the requests.
+    pub timeout: TimeoutConfig,
+    /// The connection pool of the clients.
+    pub pool: PoolConfig,
 }
 
 pub fn connect(conf: UFMConfig) -> Result<Ufm, UFMError> {
@@ -204,17 +786,8 @@ pub fn connect(conf: UFMConfig) -> Result<Ufm, UFMError> {
         addr
     )))?;
 
-    let (base_path, auth_info) = match &conf.token {
-        None if conf.cert.is_some() => {
-            let auth_cert = conf.cert.unwrap().clone();
-            (
-                "/ufmRest".to_string(),
-                format!(
-                    "{}\n{}\n{}",
-                    auth_cert.ca_crt, auth_cert.tls_key, auth_cert.tls_crt
-                ),
-            )
-        }
+    let (base_path, auth_info, cert, session) = match &conf.token {
+        None if conf.cert.is_some() => ("/ufmRest".to_string(), String::new(), conf.cert, None),
         None => {
             let password = conf
                 .password
@@ -225,12 +798,23 @@ pub fn connect(conf: UFMConfig) -> Result<Ufm, UFMError> {
                 .clone()
                 .ok_or(UFMError::InvalidConfig("username is empty".to_string()))?;
 
-            (
-                "/ufmRest".to_string(),
-                BASE64_STANDARD.encode(format!("{}:{}", username, password)),
-            )
+            if conf.session {
+                (
+                    "/ufmRestV2".to_string(),
+                    String::new(),
+                    None,
+                    Some((username, password)),
+                )
+            } else {
+                (
+                    "/ufmRest".to_string(),
+                    BASE64_STANDARD.encode(format!("{}:{}", username, password)),
+                    None,
+                    None,
+                )
+            }
         }
-        Some(t) => ("/ufmRestV3".to_string(), t.to_string()),
+        Some(t) => ("/ufmRestV3".to_string(), t.to_string(), None, None),
     };
 
     let c = RestClient::new(&RestClientConfig {
@@ -239,12 +823,28 @@ pub fn connect(conf: UFMConfig) -> Result<Ufm, UFMError> {
         auth_info,
         base_path,
         scheme: RestScheme::from(addr.scheme().to_string()),
+        cert,
+        session,
+        tls: conf.tls,
+        retry: conf.retry,
+        timeout: conf.timeout,
+        pool: conf.pool,
     })?;
 
-    Ok(Ufm { client: c })
+    Ok(Ufm {
+        client: Arc::new(c),
+    })
 }
 
 impl Ufm {
+    /// A handle sharing the connections of this one, whose requests time out after `timeout`
+    /// as a whole instead, e.g. for the long operations like `list_port` on a large fabric.
+    pub fn with_timeout(&self, timeout: Duration) -> Ufm {
+        Ufm {
+            client: Arc::new(self.client.with_timeout(timeout)),
+        }
+    }
+
     pub async fn get_configuration(&self) -> Result<Configuration, UFMError> {
         let path = String::from("/app/smconf");
         let sm_config = self.client.get(&path).await?;
@@ -252,7 +852,29 @@ impl Ufm {
         Ok(sm_config)
     }
 
+    /// Update the parameters of the SM, e.g. `{"qos": 1}`; only the parameters in
+    /// `get_configuration` can be set, and their values must be of the types of the current ones.
+    pub async fn update_configuration(
+        &self,
+        params: &BTreeMap<String, serde_json::Value>,
+    ) -> Result<(), UFMError> {
+        if params.is_empty() {
+            return Ok(());
+        }
+        self.get_configuration().await?.check_params(params)?;
+
+        let path = String::from("/app/smconf");
+        let data = serde_json::to_string(params)
+            .map_err(|_| UFMError::InvalidConfig("invalid SM parameters".to_string()))?;
+
+        self.client.put(&path, data).await?;
+
+        Ok(())
+    }
+
     pub async fn add_partition(&self, p: Partition) -> Result<(), UFMError> {
+        p.qos.validate()?;
+
         let path = String::from("/resources/pkeys/add");
 
         let membership = PortMembership::Full;
@@ -264,13 +886,14 @@ impl Ufm {
             ip_over_ib: bool,
             membership: PortMembership,
             index0: bool,
-            mtu_limit: u16,
+            #[serde(serialize_with = "IbMtu::serialize_kb")]
+            mtu_limit: IbMtu,
             service_level: u8,
-            rate_limit: f64,
+            rate_limit: IbRate,
         }
 
         let pkey = Pkey {
-            pkey: p.pkey.clone().to_string(),
+            pkey: p.pkey.to_string(),
             ip_over_ib: p.ipoib,
             membership,
             index0,
@@ -287,22 +910,26 @@ impl Ufm {
         Ok(())
     }
 
+    /// Set the partition with exactly the ports; the ports of different memberships or index0
+    /// are added by separate requests after the partition is set. It's not atomic: if adding
+    /// a group of ports fails, the partition is left with the groups before it, and the error
+    /// tells the group which failed.
     pub async fn set_partition(
         &self,
         p: Partition,
         ports: Vec<PortConfig>,
     ) -> Result<(), UFMError> {
-        let path = String::from("/resources/pkeys");
+        p.qos.validate()?;
 
-        let mut membership = PortMembership::Full;
-        let mut index0 = true;
+        let path = String::from("/resources/pkeys");
 
-        let mut guids = Vec::with_capacity(ports.len());
-        for pb in ports {
-            membership = pb.membership.clone();
-            index0 = pb.index0;
-            guids.push(pb.guid.to_string());
-        }
+        // UFM sets the GUIDs with one membership and index0; the others are added afterwards.
+        let mut groups = group_ports(ports).into_iter();
+        let (membership, index0, guids) = match groups.next() {
+            Some(group) => group,
+            // The partition without any ports.
+            None => (PortMembership::Full, true, vec![]),
+        };
 
         #[derive(Serialize, Deserialize, Debug)]
         struct Pkey {
@@ -310,14 +937,15 @@ impl Ufm {
             ip_over_ib: bool,
             membership: PortMembership,
             index0: bool,
-            guids: Vec<String>,
-            mtu_limit: u16,
+            guids: Vec<Guid>,
+            #[serde(serialize_with = "IbMtu::serialize_kb")]
+            mtu_limit: IbMtu,
             service_level: u8,
-            rate_limit: f64,
+            rate_limit: IbRate,
         }
 
         let pkey = Pkey {
-            pkey: p.pkey.clone().to_string(),
+            pkey: p.pkey.to_string(),
             ip_over_ib: p.ipoib,
             membership,
             index0,
@@ -332,18 +960,40 @@ impl Ufm {
 
         self.client.put(&path, data).await?;
 
+        for (membership, index0, guids) in groups {
+            let group = format!(
+                "{} ports with index0={} ({})",
+                membership,
+                index0,
+                guids
+                    .iter()
+                    .map(|g| g.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if let Err(e) = self.add_guids(&p, membership, index0, guids).await {
+                return Err(UFMError::Internal(format!(
+                    "partition {} is set partially, failed to add the {}: {}",
+                    p.pkey, group, e
+                )));
+            }
+        }
+
         Ok(())
     }
 
     pub async fn update_partition_qos(&self, p: Partition) -> Result<(), UFMError> {
+        p.qos.validate()?;
+
         let path = String::from("/resources/pkeys/qos_conf");
 
         #[derive(Serialize, Deserialize, Debug)]
         struct PkeyQoS {
             pkey: String,
-            mtu_limit: u16,
+            #[serde(serialize_with = "IbMtu::serialize_kb")]
+            mtu_limit: IbMtu,
             service_level: u8,
-            rate_limit: f64,
+            rate_limit: IbRate,
         }
 
         let data = serde_json::to_string(&PkeyQoS {
@@ -359,18 +1009,24 @@ impl Ufm {
         Ok(())
     }
 
+    /// Bind the ports to the partition; the ports of different memberships or index0 are
+    /// bound by separate requests.
     pub async fn bind_ports(&self, p: Partition, ports: Vec<PortConfig>) -> Result<(), UFMError> {
-        let path = String::from("/resources/pkeys");
+        for (membership, index0, guids) in group_ports(ports) {
+            self.add_guids(&p, membership, index0, guids).await?;
+        }
 
-        let mut membership = PortMembership::Full;
-        let mut index0 = true;
+        Ok(())
+    }
 
-        let mut guids = Vec::with_capacity(ports.len());
-        for pb in ports {
-            membership = pb.membership.clone();
-            index0 = pb.index0;
-            guids.push(pb.guid.to_string());
-        }
+    async fn add_guids(
+        &self,
+        p: &Partition,
+        membership: PortMembership,
+        index0: bool,
+        guids: Vec<Guid>,
+    ) -> Result<(), UFMError> {
+        let path = String::from("/resources/pkeys");
 
         #[derive(Serialize, Deserialize, Debug)]
         struct Pkey {
@@ -378,11 +1034,11 @@ impl Ufm {
             ip_over_ib: bool,
             membership: PortMembership,
             index0: bool,
-            guids: Vec<String>,
+            guids: Vec<Guid>,
         }
 
         let pkey = Pkey {
-            pkey: p.pkey.clone().to_string(),
+            pkey: p.pkey.to_string(),
             ip_over_ib: p.ipoib,
             membership,
             index0,
@@ -397,21 +1053,17 @@ impl Ufm {
         Ok(())
     }
 
-    pub async fn unbind_ports(
-        &self,
-        pkey: PartitionKey,
-        guids: Vec<String>,
-    ) -> Result<(), UFMError> {
+    pub async fn unbind_ports(&self, pkey: PartitionKey, guids: Vec<Guid>) -> Result<(), UFMError> {
         let path = String::from("/actions/remove_guids_from_pkey");
 
         #[derive(Serialize, Deserialize, Debug)]
         struct Pkey {
             pkey: String,
-            guids: Vec<String>,
+            guids: Vec<Guid>,
         }
 
         let pkey = Pkey {
-            pkey: pkey.clone().to_string(),
+            pkey: pkey.to_string(),
             guids,
         };
 
@@ -476,34 +1128,364 @@ impl Ufm {
         Ok(())
     }
 
-    pub async fn list_port(&self, pkey: PartitionKey) -> Result<Vec<Port>, UFMError> {
-        let mut res = Vec::new();
-        // get GUIDs from pkey
+    /// The current partitions with all of their members, except the default partition.
+    pub async fn list_partition_specs(&self) -> Result<Vec<PartitionSpec>, UFMError> {
+        let mut specs = self.list_partition_members().await?;
+        specs.retain(|s| !s.partition.pkey.is_default());
+
+        Ok(specs)
+    }
+
+    /// The partitions which the port is a member of, with its membership; the default
+    /// partition is included only if UFM lists the port in it.
+    pub async fn partitions_for_guid(
+        &self,
+        guid: Guid,
+    ) -> Result<Vec<PartitionMembership>, UFMError> {
+        let mut res: Vec<PartitionMembership> = self
+            .list_partition_members()
+            .await?
+            .into_iter()
+            .filter_map(|s| {
+                let m = s.members.into_iter().find(|m| m.guid == guid)?;
+                Some(PartitionMembership {
+                    partition: s.partition,
+                    membership: m.membership,
+                    index0: m.index0,
+                })
+            })
+            .collect();
+        res.sort_by_key(|m| m.partition.pkey);
+
+        Ok(res)
+    }
+
+    /// The members of the partition with their membership and index0.
+    pub async fn partition_members(&self, pkey: PartitionKey) -> Result<Vec<PortConfig>, UFMError> {
         #[derive(Serialize, Deserialize, Debug)]
         struct PkeyWithGUIDs {
-            pub partition: String,
-            pub ip_over_ib: bool,
-            pub guids: Vec<PortConfig>,
+            #[serde(default)]
+            guids: Vec<PortConfig>,
         }
 
-        let path = format!("resources/pkeys/{}?guids_data=true", pkey.to_string());
-        let pkeywithguids: PkeyWithGUIDs = self.client.get(&path).await?;
+        let path = format!("/resources/pkeys/{}?guids_data=true", pkey);
+        let pkey: PkeyWithGUIDs = self.client.get(&path).await?;
 
-        // list physical ports
-        let path = String::from("/resources/ports?sys_type=Computer");
-        let physical_ports: Vec<PhysicalPort> = self.client.list(&path).await?;
+        Ok(pkey.guids)
+    }
 
-        // list virtual ports
-        // let path = String::from("/resources/vports");
-        // let virtual_ports: Vec<VirtualPort> = self.client.list(&path).await?;
+    /// A snapshot of all the partitions without their ports, or of the partition of `pkey`
+    /// with its ports; the snapshot of a partition which does not exist is empty.
+    pub async fn partition_snapshot(
+        &self,
+        pkey: Option<PartitionKey>,
+    ) -> Result<PartitionSnapshot, UFMError> {
+        let Some(pkey) = pkey else {
+            return Ok(PartitionSnapshot::new(self.list_partition().await?));
+        };
+
+        let p = match self.get_partition(&pkey.to_string()).await {
+            Ok(p) => p,
+            Err(UFMError::NotFound(_)) => return Ok(PartitionSnapshot::default()),
+            Err(e) => return Err(e),
+        };
+        let ports = self.list_port(pkey).await?;
+
+        let mut snapshot = PartitionSnapshot::new(vec![p]);
+        snapshot.add_ports(pkey, ports);
+
+        Ok(snapshot)
+    }
+
+    /// Watch the partitions as `partition_snapshot`, by taking a snapshot every `interval`
+    /// and yielding the changes since the previous one, starting from `from`. A failed poll
+    /// is yielded as an error, and the polling goes on.
+    pub fn watch_partitions(
+        &self,
+        pkey: Option<PartitionKey>,
+        from: PartitionSnapshot,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<SnapshotChange, UFMError>> + '_ {
+        let init = (from, VecDeque::new());
+
+        stream::unfold(init, move |(mut last, mut pending)| async move {
+            loop {
+                if let Some(c) = pending.pop_front() {
+                    return Some((Ok(c), (last, pending)));
+                }
+
+                tokio::time::sleep(interval).await;
+
+                let snapshot = match self.partition_snapshot(pkey).await {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => return Some((Err(e), (last, pending))),
+                };
+                pending.extend(last.diff(&snapshot));
+                last = snapshot;
+            }
+        })
+    }
+
+    /// All the partitions with all of their members in one request.
+    async fn list_partition_members(&self) -> Result<Vec<PartitionSpec>, UFMError> {
+        #[derive(Serialize, Deserialize, Debug)]
+        struct Pkey {
+            partition: String,
+            ip_over_ib: bool,
+            qos_conf: PartitionQoS,
+            #[serde(default)]
+            guids: Vec<PortConfig>,
+        }
+
+        let path = String::from("/resources/pkeys?qos_conf=true&guids_data=true");
+        let pkeys: HashMap<String, Pkey> = self.client.list(&path).await?;
+
+        let mut specs = Vec::new();
+        for (k, v) in pkeys {
+            specs.push(PartitionSpec {
+                partition: Partition {
+                    name: v.partition,
+                    pkey: PartitionKey::try_from(&k)?,
+                    ipoib: v.ip_over_ib,
+                    qos: v.qos_conf,
+                },
+                members: v.guids,
+            });
+        }
+
+        Ok(specs)
+    }
+
+    /// Plan the changes to make the partitions as desired; see `Plan::new`.
+    pub async fn plan_partitions(
+        &self,
+        desired: &[PartitionSpec],
+        prune: bool,
+    ) -> Result<Plan, UFMError> {
+        let current = self.list_partition_specs().await?;
+
+        Plan::new(desired, &current, prune)
+    }
+
+    pub async fn apply_change(&self, change: &PartitionChange) -> Result<(), UFMError> {
+        match change {
+            PartitionChange::Create(p) => self.add_partition(p.clone()).await,
+            PartitionChange::UpdateQoS { pkey, to, .. } => {
+                let p = Partition {
+                    name: String::new(),
+                    pkey: *pkey,
+                    ipoib: false,
+                    qos: to.clone(),
+                };
+                self.update_partition_qos(p).await
+            }
+            PartitionChange::Bind {
+                partition,
+                membership,
+                index0,
+                guids,
+            } => {
+                let ports = guids
+                    .iter()
+                    .map(|g| PortConfig {
+                        guid: *g,
+                        index0: *index0,
+                        membership: membership.clone(),
+                    })
+                    .collect();
+                self.bind_ports(partition.clone(), ports).await
+            }
+            PartitionChange::Unbind { pkey, guids } => {
+                self.unbind_ports(*pkey, guids.clone()).await
+            }
+            PartitionChange::Delete(pkey) => self.delete_partition(&pkey.to_string()).await,
+        }
+    }
+
+    /// Make the partitions as desired, and report the changes which are applied. It stops at
+    /// the first change which fails, which is in the report with its error, and the changes
+    /// before it are kept; it returns an error only if the changes can't be planned.
+    pub async fn apply_partitions(
+        &self,
+        desired: &[PartitionSpec],
+        prune: bool,
+    ) -> Result<ApplyReport, UFMError> {
+        let plan = self.plan_partitions(desired, prune).await?;
+
+        let mut report = ApplyReport {
+            total: plan.changes.len(),
+            ..ApplyReport::default()
+        };
+        for c in plan.changes {
+            log::info!("Applying {}", c);
+            if let Err(e) = self.apply_change(&c).await {
+                report.failed = Some((c, e));
+                break;
+            }
+            report.applied.push(c);
+        }
+
+        Ok(report)
+    }
+
+    /// Run the operations with at most `concurrency` of them at a time, and report the result
+    /// of each one; the operations on the same pkey run one by one in order, and all the
+    /// operations are run even if some of them fail.
+    pub async fn batch(&self, ops: Vec<BatchOp>, concurrency: usize) -> BatchReport {
+        // The operations by pkey, in the order of their first appearance.
+        let mut groups: Vec<Vec<(usize, BatchOp)>> = vec![];
+        let mut index: HashMap<u16, usize> = HashMap::new();
+        for (i, op) in ops.into_iter().enumerate() {
+            let g = *index.entry(u16::from(*op.pkey())).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[g].push((i, op));
+        }
+
+        let mut results: Vec<(usize, BatchResult)> = stream::iter(groups)
+            .map(|group| async move {
+                let mut results = Vec::with_capacity(group.len());
+                for (i, op) in group {
+                    let result = self.run_batch_op(&op).await;
+                    results.push((i, BatchResult { op, result }));
+                }
+                results
+            })
+            .buffer_unordered(concurrency.max(1))
+            .flat_map(stream::iter)
+            .collect()
+            .await;
+        results.sort_by_key(|(i, _)| *i);
+
+        BatchReport {
+            results: results.into_iter().map(|(_, r)| r).collect(),
+        }
+    }
+
+    /// Create the partitions with their members; see `batch`.
+    pub async fn create_partitions(
+        &self,
+        partitions: Vec<(Partition, Vec<PortConfig>)>,
+        concurrency: usize,
+    ) -> BatchReport {
+        let ops = partitions
+            .into_iter()
+            .map(|(p, members)| BatchOp::Create {
+                pkey: p.pkey,
+                ipoib: p.ipoib,
+                qos: p.qos,
+                members,
+            })
+            .collect();
+
+        self.batch(ops, concurrency).await
+    }
+
+    /// Delete the partitions; see `batch`.
+    pub async fn delete_partitions(
+        &self,
+        pkeys: Vec<PartitionKey>,
+        concurrency: usize,
+    ) -> BatchReport {
+        let ops = pkeys
+            .into_iter()
+            .map(|pkey| BatchOp::Delete { pkey })
+            .collect();
+
+        self.batch(ops, concurrency).await
+    }
+
+    async fn run_batch_op(&self, op: &BatchOp) -> Result<(), UFMError> {
+        match op {
+            BatchOp::Create {
+                pkey,
+                ipoib,
+                qos,
+                members,
+            } => {
+                let p = Partition {
+                    name: String::new(),
+                    pkey: *pkey,
+                    ipoib: *ipoib,
+                    qos: qos.clone(),
+                };
+                match members.is_empty() {
+                    true => self.add_partition(p).await,
+                    false => self.set_partition(p, members.clone()).await,
+                }
+            }
+            BatchOp::Delete { pkey } => self.delete_partition(&pkey.to_string()).await,
+            BatchOp::Bind { pkey, members } => {
+                let p = Partition {
+                    name: String::new(),
+                    pkey: *pkey,
+                    ipoib: false,
+                    qos: PartitionQoS::default(),
+                };
+                self.bind_ports(p, members.clone()).await
+            }
+            BatchOp::Unbind { pkey, guids } => self.unbind_ports(*pkey, guids.clone()).await,
+        }
+    }
+
+    /// Generate an access token for the current user.
+    pub async fn create_token(&self) -> Result<AccessToken, UFMError> {
+        let token = self.client.create("/app/tokens", String::new()).await?;
+
+        Ok(token)
+    }
+
+    /// List the access tokens of the current user.
+    pub async fn list_tokens(&self) -> Result<Vec<AccessToken>, UFMError> {
+        let tokens = self.client.list("/app/tokens").await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn revoke_token(&self, token: &str) -> Result<(), UFMError> {
+        let path = format!("/app/tokens/{}", token);
+        self.client.delete(&path).await?;
+
+        Ok(())
+    }
+
+    pub async fn list_port(&self, pkey: PartitionKey) -> Result<Vec<Port>, UFMError> {
+        let mut res = Vec::new();
+        // get GUIDs from pkey
+        let members = self.partition_members(pkey).await?;
+
+        // list physical ports
+        let physical_ports = self
+            .list_ports(&PortFilter {
+                sys_type: Some(SystemType::Computer),
+                ..PortFilter::default()
+            })
+            .await?;
+
+        // list virtual ports; an older UFM may not have them, so fall back to the physical ports
+        let virtual_ports = match self.list_vports().await {
+            Ok(vports) => vports,
+            Err(e) => {
+                log::warn!(
+                    "Failed to list the virtual ports of partition {}: {}",
+                    pkey,
+                    e
+                );
+                vec![]
+            }
+        };
 
         let mut port_map = HashMap::new();
         for pport in physical_ports {
-            port_map.insert(pport.guid.clone(), Port::from(pport));
+            port_map.insert(pport.guid, pport);
+        }
+        for vport in virtual_ports {
+            port_map.insert(vport.guid, vport);
         }
 
         if !pkey.is_default() {
-            for port_config in pkeywithguids.guids {
+            for port_config in members {
                 let guid = port_config.guid;
                 match port_map.get(&guid) {
                     Some(p) => {
@@ -524,6 +1506,293 @@ impl Ufm {
         Ok(res)
     }
 
+    /// List the physical ports in the fabric which match the filter.
+    pub async fn list_ports(&self, filter: &PortFilter) -> Result<Vec<Port>, UFMError> {
+        // UFM filters the ports by the system and its type.
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(sys_type) = &filter.sys_type {
+            query.append_pair("sys_type", &sys_type.to_string());
+        }
+        if let Some(system) = &filter.system {
+            query.append_pair("system", system);
+        }
+        let query = query.finish();
+
+        let path = match query.is_empty() {
+            true => String::from("/resources/ports"),
+            false => format!("/resources/ports?{}", query),
+        };
+        let physical_ports: Vec<PhysicalPort> = self.client.list(&path).await?;
+
+        Ok(physical_ports
+            .into_iter()
+            .map(Port::from)
+            .filter(|p| filter.matches(p))
+            .collect())
+    }
+
+    /// List the systems in the fabric, e.g. the switches and the hosts.
+    pub async fn list_systems(&self) -> Result<Vec<System>, UFMError> {
+        let path = String::from("/resources/systems");
+        let systems = self.client.list(&path).await?;
+
+        Ok(systems)
+    }
+
+    pub async fn get_system(&self, guid: Guid) -> Result<System, UFMError> {
+        let path = format!("/resources/systems/{}", guid);
+        let system = self.client.get(&path).await?;
+
+        Ok(system)
+    }
+
+    /// List the events which match the filter, in the order of their IDs. The filter is applied
+    /// by UFM, and again to its reply in case it ignores some of the conditions.
+    pub async fn list_events(&self, filter: &EventFilter) -> Result<Vec<Event>, UFMError> {
+        let query = filter.query();
+        let path = match query.is_empty() {
+            true => String::from("/app/events"),
+            false => format!("/app/events?{}", query),
+        };
+        let events: Vec<Event> = self.client.list(&path).await?;
+
+        let mut events: Vec<Event> = events
+            .into_iter()
+            .filter(|e| filter.matches_event(e))
+            .collect();
+        events.sort_by_key(|e| e.id);
+        if let Some(limit) = filter.limit {
+            events.truncate(limit);
+        }
+
+        Ok(events)
+    }
+
+    /// List the alarms which match the filter, in the order of their IDs; see `list_events`.
+    pub async fn list_alarms(&self, filter: &EventFilter) -> Result<Vec<Alarm>, UFMError> {
+        let query = filter.query();
+        let path = match query.is_empty() {
+            true => String::from("/app/alarms"),
+            false => format!("/app/alarms?{}", query),
+        };
+        let alarms: Vec<Alarm> = self.client.list(&path).await?;
+
+        let mut alarms: Vec<Alarm> = alarms
+            .into_iter()
+            .filter(|a| filter.matches_alarm(a))
+            .collect();
+        alarms.sort_by_key(|a| a.id);
+        if let Some(limit) = filter.limit {
+            alarms.truncate(limit);
+        }
+
+        Ok(alarms)
+    }
+
+    /// The ID of the latest event of UFM; `0` if there's no event.
+    async fn last_event_id(&self) -> Result<u64, UFMError> {
+        let path = String::from("/app/events?order=desc&limit=1");
+        let events: Vec<Event> = self.client.list(&path).await?;
+
+        Ok(events.iter().map(|e| e.id).max().unwrap_or_default())
+    }
+
+    /// Follow the events which match the filter, by polling UFM every `interval` for at most
+    /// `FOLLOW_EVENTS_LIMIT` events after the last seen ID, and at once while there're more.
+    /// It starts from `filter.after_id`, or the latest event of UFM when the stream is first
+    /// polled if it's not set, i.e. only the new events are followed. A failed poll is yielded
+    /// as an error, and the polling goes on.
+    pub fn follow_events(
+        &self,
+        filter: EventFilter,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Event, UFMError>> + '_ {
+        struct Follow {
+            last_id: Option<u64>,
+            pending: VecDeque<Event>,
+            /// Whether to wait for the interval before the next poll.
+            wait: bool,
+        }
+
+        let init = Follow {
+            last_id: filter.after_id,
+            pending: VecDeque::new(),
+            wait: false,
+        };
+
+        stream::unfold(init, move |mut st| {
+            let filter = filter.clone();
+            async move {
+                loop {
+                    if let Some(e) = st.pending.pop_front() {
+                        return Some((Ok(e), st));
+                    }
+
+                    if st.wait {
+                        tokio::time::sleep(interval).await;
+                    }
+                    st.wait = true;
+
+                    let after_id = match st.last_id {
+                        Some(id) => id,
+                        None => match self.last_event_id().await {
+                            Ok(id) => *st.last_id.insert(id),
+                            Err(e) => return Some((Err(e), st)),
+                        },
+                    };
+                    let new = EventFilter {
+                        after_id: Some(after_id),
+                        limit: Some(FOLLOW_EVENTS_LIMIT),
+                        ..filter.clone()
+                    };
+                    let events = match self.list_events(&new).await {
+                        Ok(events) => events,
+                        Err(e) => return Some((Err(e), st)),
+                    };
+
+                    // Poll the rest at once if the limit is reached.
+                    st.wait = events.len() < FOLLOW_EVENTS_LIMIT;
+                    if let Some(e) = events.last() {
+                        st.last_id = Some(e.id);
+                    }
+                    st.pending.extend(events);
+                }
+            }
+        })
+    }
+
+    /// Create a monitoring session of the counters of the ports, which samples them every `interval`.
+    pub async fn create_monitoring_session(
+        &self,
+        guids: &[Guid],
+        counters: &[Counter],
+        interval: Duration,
+    ) -> Result<MonitoringSession, UFMError> {
+        // UFM monitors the ports by their names.
+        let filter = PortFilter {
+            guids: Some(guids.iter().cloned().collect()),
+            ..PortFilter::default()
+        };
+        let ports: BTreeMap<String, Guid> = self
+            .list_ports(&filter)
+            .await?
+            .into_iter()
+            .filter_map(|p| Some((p.name?, p.guid)))
+            .collect();
+        if let Some(g) = guids.iter().find(|g| !ports.values().any(|p| p == *g)) {
+            return Err(UFMError::InvalidConfig(format!("port '{}' not found", g)));
+        }
+
+        #[derive(Serialize, Debug)]
+        struct Session<'a> {
+            scope_object: &'a str,
+            monitor_object: &'a str,
+            objects: Vec<&'a String>,
+            attributes: Vec<&'a str>,
+            functions: Vec<&'a str>,
+            interval: u64,
+        }
+
+        let session = Session {
+            scope_object: "port",
+            monitor_object: "port",
+            objects: ports.keys().collect(),
+            attributes: counters.iter().map(|c| c.attribute()).collect(),
+            functions: vec!["RAW"],
+            interval: interval.as_secs().max(1),
+        };
+        let data = serde_json::to_string(&session)
+            .map_err(|_| UFMError::InvalidConfig("invalid monitoring session".to_string()))?;
+
+        // UFM creates the session asynchronously, and replies its location.
+        let path = String::from("/monitoring/session");
+        let location = self.client.create_location(&path, data).await?;
+        let id = location
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or("");
+        if id.is_empty() {
+            return Err(UFMError::Internal(format!(
+                "invalid location of monitoring session: {}",
+                location
+            )));
+        }
+
+        Ok(MonitoringSession {
+            id: id.to_string(),
+            ports,
+            counters: counters.to_vec(),
+        })
+    }
+
+    /// The latest samples of the counters of the ports in the monitoring session.
+    pub async fn monitoring_data(
+        &self,
+        session: &MonitoringSession,
+    ) -> Result<Vec<CounterSample>, UFMError> {
+        let path = format!("/monitoring/session/{}/data", session.id);
+        let data: SessionData = self.client.get(&path).await?;
+
+        let mut samples = vec![];
+        for (name, attrs) in data.ports {
+            let guid = match session.ports.get(&name) {
+                Some(guid) => *guid,
+                None => continue,
+            };
+
+            let mut counters = PortCounters::default();
+            for (attr, value) in attrs {
+                if let Ok(c) = Counter::from_str(&attr) {
+                    counters.set(c, value.0);
+                }
+            }
+
+            samples.push(CounterSample {
+                timestamp: data.timestamp,
+                guid,
+                port_name: name,
+                counters,
+            });
+        }
+
+        Ok(samples)
+    }
+
+    pub async fn delete_monitoring_session(
+        &self,
+        session: &MonitoringSession,
+    ) -> Result<(), UFMError> {
+        let path = format!("/monitoring/session/{}", session.id);
+        self.client.delete(&path).await?;
+
+        Ok(())
+    }
+
+    /// List the links between the ports in the fabric.
+    pub async fn list_links(&self) -> Result<Vec<Link>, UFMError> {
+        let path = String::from("/resources/links");
+        let links: Vec<UfmLink> = self.client.list(&path).await?;
+
+        Ok(links.into_iter().map(Link::from).collect())
+    }
+
+    /// The topology of the fabric, built from the systems and the links.
+    pub async fn topology(&self) -> Result<Topology, UFMError> {
+        let systems = self.list_systems().await?;
+        let links = self.list_links().await?;
+
+        Ok(Topology::new(systems, links))
+    }
+
+    /// List the virtual ports, e.g. the SR-IOV VFs; their `parent_guid` is the GUID of the physical port.
+    pub async fn list_vports(&self) -> Result<Vec<Port>, UFMError> {
+        let path = String::from("/resources/vports");
+        let virtual_ports: Vec<VirtualPort> = self.client.list(&path).await?;
+
+        Ok(virtual_ports.into_iter().map(Port::from).collect())
+    }
+
     pub async fn version(&self) -> Result<String, UFMError> {
         #[derive(Serialize, Deserialize, Debug)]
         struct Version {
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{Guid, UFMError};
+
+/// The counters of the ports which can be monitored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Counter {
+    /// The data transmitted, in units of 4 bytes.
+    XmitData,
+    /// The data received, in units of 4 bytes.
+    RcvData,
+    XmitPackets,
+    RcvPackets,
+    SymbolErrors,
+    LinkDowned,
+    LinkErrorRecovery,
+    RcvErrors,
+    XmitDiscards,
+    /// The ticks during which the port had data to transmit but no credits.
+    XmitWait,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 10] = [
+        Counter::XmitData,
+        Counter::RcvData,
+        Counter::XmitPackets,
+        Counter::RcvPackets,
+        Counter::SymbolErrors,
+        Counter::LinkDowned,
+        Counter::LinkErrorRecovery,
+        Counter::RcvErrors,
+        Counter::XmitDiscards,
+        Counter::XmitWait,
+    ];
+
+    /// The name of the counter in UFM, e.g. `PortXmitDataExtended`.
+    pub fn attribute(&self) -> &'static str {
+        match self {
+            Counter::XmitData => "PortXmitDataExtended",
+            Counter::RcvData => "PortRcvDataExtended",
+            Counter::XmitPackets => "PortXmitPktsExtended",
+            Counter::RcvPackets => "PortRcvPktsExtended",
+            Counter::SymbolErrors => "SymbolErrorCounter",
+            Counter::LinkDowned => "LinkDownedCounter",
+            Counter::LinkErrorRecovery => "LinkErrorRecoveryCounter",
+            Counter::RcvErrors => "PortRcvErrors",
+            Counter::XmitDiscards => "PortXmitDiscards",
+            Counter::XmitWait => "PortXmitWait",
+        }
+    }
+
+    /// Whether the counter is of the data, which is in units of 4 bytes.
+    pub fn is_data(&self) -> bool {
+        matches!(self, Counter::XmitData | Counter::RcvData)
+    }
+}
+
+impl fmt::Display for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Counter::XmitData => "xmit-data",
+            Counter::RcvData => "rcv-data",
+            Counter::XmitPackets => "xmit-packets",
+            Counter::RcvPackets => "rcv-packets",
+            Counter::SymbolErrors => "symbol-errors",
+            Counter::LinkDowned => "link-downed",
+            Counter::LinkErrorRecovery => "link-error-recovery",
+            Counter::RcvErrors => "rcv-errors",
+            Counter::XmitDiscards => "xmit-discards",
+            Counter::XmitWait => "xmit-wait",
+        };
+        f.pad(s)
+    }
+}
+
+impl FromStr for Counter {
+    type Err = UFMError;
+
+    /// Parse the counter by its name, e.g. `xmit-data`, or its name in UFM, e.g. `PortXmitDataExtended`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Counter::ALL
+            .into_iter()
+            .find(|c| c.to_string() == s.to_lowercase() || c.attribute().eq_ignore_ascii_case(s))
+            .ok_or(UFMError::InvalidConfig(format!("invalid counter: {}", s)))
+    }
+}
+
+/// The counters of a port; `None` if the counter is not monitored.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PortCounters {
+    pub xmit_data: Option<u64>,
+    pub rcv_data: Option<u64>,
+    pub xmit_packets: Option<u64>,
+    pub rcv_packets: Option<u64>,
+    pub symbol_errors: Option<u64>,
+    pub link_downed: Option<u64>,
+    pub link_error_recovery: Option<u64>,
+    pub rcv_errors: Option<u64>,
+    pub xmit_discards: Option<u64>,
+    pub xmit_wait: Option<u64>,
+}
+
+impl PortCounters {
+    pub fn get(&self, counter: Counter) -> Option<u64> {
+        *self.field(counter)
+    }
+
+    pub fn set(&mut self, counter: Counter, value: u64) {
+        *self.field_mut(counter) = Some(value);
+    }
+
+    fn field(&self, counter: Counter) -> &Option<u64> {
+        match counter {
+            Counter::XmitData => &self.xmit_data,
+            Counter::RcvData => &self.rcv_data,
+            Counter::XmitPackets => &self.xmit_packets,
+            Counter::RcvPackets => &self.rcv_packets,
+            Counter::SymbolErrors => &self.symbol_errors,
+            Counter::LinkDowned => &self.link_downed,
+            Counter::LinkErrorRecovery => &self.link_error_recovery,
+            Counter::RcvErrors => &self.rcv_errors,
+            Counter::XmitDiscards => &self.xmit_discards,
+            Counter::XmitWait => &self.xmit_wait,
+        }
+    }
+
+    fn field_mut(&mut self, counter: Counter) -> &mut Option<u64> {
+        match counter {
+            Counter::XmitData => &mut self.xmit_data,
+            Counter::RcvData => &mut self.rcv_data,
+            Counter::XmitPackets => &mut self.xmit_packets,
+            Counter::RcvPackets => &mut self.rcv_packets,
+            Counter::SymbolErrors => &mut self.symbol_errors,
+            Counter::LinkDowned => &mut self.link_downed,
+            Counter::LinkErrorRecovery => &mut self.link_error_recovery,
+            Counter::RcvErrors => &mut self.rcv_errors,
+            Counter::XmitDiscards => &mut self.xmit_discards,
+            Counter::XmitWait => &mut self.xmit_wait,
+        }
+    }
+}
+
+/// The counters of a port at a time.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CounterSample {
+    /// The time of the sample, in seconds since the epoch.
+    pub timestamp: f64,
+    /// The GUID of the port.
+    pub guid: Guid,
+    /// The name of the port in UFM, e.g. `b83fd203002a1f3a_1`.
+    pub port_name: String,
+    pub counters: PortCounters,
+}
+
+impl CounterSample {
+    /// The rate of the counter per second since the previous sample of the same port; the data
+    /// counters are in bytes per second. `None` if the counter is not in both samples, or it was
+    /// reset in between.
+    pub fn rate(&self, prev: &CounterSample, counter: Counter) -> Option<f64> {
+        let elapsed = self.timestamp - prev.timestamp;
+        if elapsed <= 0.0 {
+            return None;
+        }
+
+        let delta = self
+            .counters
+            .get(counter)?
+            .checked_sub(prev.counters.get(counter)?)?;
+        let delta = match counter.is_data() {
+            true => delta as f64 * 4.0,
+            false => delta as f64,
+        };
+
+        Some(delta / elapsed)
+    }
+}
+
+/// A monitoring session of UFM, which samples the counters of the ports at an interval
+/// until it's deleted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonitoringSession {
+    pub id: String,
+    /// The ports being monitored, keyed by their names in UFM.
+    pub ports: BTreeMap<String, Guid>,
+    pub counters: Vec<Counter>,
+}
+
+/// The snapshot of a monitoring session in the shape of `/monitoring/session/{id}/data`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionData {
+    pub timestamp: f64,
+    /// The counters by the names of the ports and the attributes.
+    #[serde(rename = "Port", default)]
+    pub ports: BTreeMap<String, BTreeMap<String, CounterValue>>,
+}
+
+/// UFM replies the counters as integers or floats.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct CounterValue(pub u64);
+
+impl<'de> Deserialize<'de> for CounterValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let v = serde_json::Number::deserialize(deserializer)?;
+        match (v.as_u64(), v.as_f64()) {
+            (Some(v), _) => Ok(CounterValue(v)),
+            (None, Some(v)) if v >= 0.0 => Ok(CounterValue(v as u64)),
+            _ => Err(serde::de::Error::custom(format!("invalid counter: {v}"))),
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Guid, Partition, PartitionKey, PartitionQoS, PortConfig, PortMembership, UFMError};
+
+/// The desired state of a partition, i.e. its settings and all of its members.
+#[derive(Debug, Clone)]
+pub struct PartitionSpec {
+    pub partition: Partition,
+    pub members: Vec<PortConfig>,
+}
+
+impl PartitionSpec {
+    /// Load the partitions from a file; it's parsed as YAML if its extension is `yaml` or `yml`,
+    /// otherwise as TOML, e.g.
+    ///
+    /// ```yaml
+    /// partitions:
+    ///   - pkey: "0x5"
+    ///     ipoib: true
+    ///     qos:
+    ///       mtu_limit: 4096
+    ///       service_level: 0
+    ///       rate_limit: 100
+    ///     members:
+    ///       - guid: "1070fd0300176625"
+    ///         membership: full
+    ///         index0: true
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<PartitionSpec>, UFMError> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            UFMError::InvalidConfig(format!("failed to read '{}': {}", path.display(), e))
+        })?;
+
+        let res = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&data).map_err(|e| e.to_string()),
+            _ => toml::from_str(&data).map_err(|e| e.to_string()),
+        };
+        let file: SpecFile = res
+            .map_err(|e| UFMError::InvalidConfig(format!("invalid '{}': {}", path.display(), e)))?;
+
+        Ok(file.into_specs())
+    }
+
+    pub fn from_yaml(data: &str) -> Result<Vec<PartitionSpec>, UFMError> {
+        let file: SpecFile =
+            serde_yaml::from_str(data).map_err(|e| UFMError::InvalidConfig(e.to_string()))?;
+
+        Ok(file.into_specs())
+    }
+
+    pub fn from_toml(data: &str) -> Result<Vec<PartitionSpec>, UFMError> {
+        let file: SpecFile =
+            toml::from_str(data).map_err(|e| UFMError::InvalidConfig(e.to_string()))?;
+
+        Ok(file.into_specs())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SpecFile {
+    #[serde(default)]
+    partitions: Vec<SpecEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SpecEntry {
+    #[serde(default)]
+    name: String,
+    pkey: PartitionKey,
+    #[serde(default)]
+    ipoib: bool,
+    #[serde(default)]
+    qos: PartitionQoS,
+    #[serde(default)]
+    members: Vec<PortConfig>,
+}
+
+impl SpecFile {
+    fn into_specs(self) -> Vec<PartitionSpec> {
+        self.partitions
+            .into_iter()
+            .map(|p| PartitionSpec {
+                partition: Partition {
+                    name: p.name,
+                    pkey: p.pkey,
+                    ipoib: p.ipoib,
+                    qos: p.qos,
+                },
+                members: p.members,
+            })
+            .collect()
+    }
+}
+
+/// A change to make the partitions of UFM as desired; it's serialized as e.g.
+/// `{"delete": "0x7"}` in the output of ufmctl.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionChange {
+    /// Create the partition; its members are bound by the following changes.
+    Create(Partition),
+    #[serde(rename = "update_qos")]
+    UpdateQoS {
+        pkey: PartitionKey,
+        from: PartitionQoS,
+        to: PartitionQoS,
+    },
+    /// Bind the ports to the partition, or change the membership of its members.
+    Bind {
+        partition: Partition,
+        membership: PortMembership,
+        index0: bool,
+        guids: Vec<Guid>,
+    },
+    Unbind {
+        pkey: PartitionKey,
+        guids: Vec<Guid>,
+    },
+    /// Delete the partition which is not declared.
+    Delete(PartitionKey),
+}
+
+impl fmt::Display for PartitionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guids = |guids: &[Guid]| {
+            guids
+                .iter()
+                .map(|g| g.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            PartitionChange::Create(p) => write!(
+                f,
+                "+ partition {}: ipoib={}, mtu_limit={}, service_level={}, rate_limit={}",
+                p.pkey, p.ipoib, p.qos.mtu_limit, p.qos.service_level, p.qos.rate_limit
+            ),
+            PartitionChange::UpdateQoS { pkey, from, to } => {
+                write!(
+                    f,
+                    "~ partition {}: {}",
+                    pkey,
+                    qos_diffs(from, to).join(", ")
+                )
+            }
+            PartitionChange::Bind {
+                partition,
+                membership,
+                index0,
+                guids: g,
+            } => write!(
+                f,
+                "+ bind {} ({}, index0={}): {}",
+                partition.pkey,
+                membership,
+                index0,
+                guids(g)
+            ),
+            PartitionChange::Unbind { pkey, guids: g } => {
+                write!(f, "- unbind {}: {}", pkey, guids(g))
+            }
+            PartitionChange::Delete(pkey) => write!(f, "- partition {}", pkey),
+        }
+    }
+}
+
+/// The differences of the QoS, e.g. `mtu_limit 2048 -> 4096`; empty if they're the same.
+pub(crate) fn qos_diffs(from: &PartitionQoS, to: &PartitionQoS) -> Vec<String> {
+    let mut diffs = vec![];
+    if from.mtu_limit != to.mtu_limit {
+        diffs.push(format!("mtu_limit {} -> {}", from.mtu_limit, to.mtu_limit));
+    }
+    if from.service_level != to.service_level {
+        diffs.push(format!(
+            "service_level {} -> {}",
+            from.service_level, to.service_level
+        ));
+    }
+    if from.rate_limit != to.rate_limit {
+        diffs.push(format!(
+            "rate_limit {} -> {}",
+            from.rate_limit, to.rate_limit
+        ));
+    }
+
+    diffs
+}
+
+/// The changes to make the partitions of UFM as desired, in the order to apply them.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub changes: Vec<PartitionChange>,
+}
+
+impl Plan {
+    /// Plan the changes from the current partitions to the desired ones. The members of the
+    /// desired partitions are exactly the declared ones; the partitions which are not declared
+    /// are deleted only if `prune`. The default partition is never changed.
+    pub fn new(
+        desired: &[PartitionSpec],
+        current: &[PartitionSpec],
+        prune: bool,
+    ) -> Result<Plan, UFMError> {
+        let current: HashMap<u16, &PartitionSpec> = current
+            .iter()
+            .map(|c| (u16::from(c.partition.pkey), c))
+            .collect();
+
+        let mut declared = BTreeMap::new();
+        for d in desired {
+            let pkey = &d.partition.pkey;
+            if pkey.is_default() {
+                return Err(UFMError::InvalidConfig(format!(
+                    "the default partition {} can not be declared",
+                    pkey
+                )));
+            }
+            if declared.insert(u16::from(*pkey), d).is_some() {
+                return Err(UFMError::InvalidConfig(format!(
+                    "partition {} is declared more than once",
+                    pkey
+                )));
+            }
+
+            let mut guids = HashSet::new();
+            if let Some(m) = d.members.iter().find(|m| !guids.insert(m.guid)) {
+                return Err(UFMError::InvalidConfig(format!(
+                    "port {} is declared more than once in partition {}",
+                    m.guid, pkey
+                )));
+            }
+        }
+
+        let mut changes = vec![];
+        for (k, d) in &declared {
+            let cur = current.get(k);
+            let pkey = &d.partition.pkey;
+
+            let members: HashMap<Guid, &PortConfig> = match cur {
+                None => {
+                    changes.push(PartitionChange::Create(d.partition.clone()));
+                    HashMap::new()
+                }
+                Some(c) => {
+                    if c.partition.ipoib != d.partition.ipoib {
+                        return Err(UFMError::InvalidConfig(format!(
+                            "the ipoib of partition {} can not be changed in place",
+                            pkey
+                        )));
+                    }
+                    let (from, to) = (&c.partition.qos, &d.partition.qos);
+                    if !qos_diffs(from, to).is_empty() {
+                        changes.push(PartitionChange::UpdateQoS {
+                            pkey: *pkey,
+                            from: from.clone(),
+                            to: to.clone(),
+                        });
+                    }
+                    c.members.iter().map(|m| (m.guid, m)).collect()
+                }
+            };
+
+            // UFM binds the ports of the same membership and index0 at once.
+            let mut binds: BTreeMap<(PortMembership, bool), Vec<Guid>> = BTreeMap::new();
+            for m in &d.members {
+                let changed = members
+                    .get(&m.guid)
+                    .is_none_or(|c| c.membership != m.membership || c.index0 != m.index0);
+                if changed {
+                    binds
+                        .entry((m.membership.clone(), m.index0))
+                        .or_default()
+                        .push(m.guid);
+                }
+            }
+            for ((membership, index0), guids) in binds {
+                changes.push(PartitionChange::Bind {
+                    partition: d.partition.clone(),
+                    membership,
+                    index0,
+                    guids,
+                });
+            }
+
+            let mut unbinds: Vec<Guid> = members
+                .keys()
+                .filter(|g| !d.members.iter().any(|m| m.guid == **g))
+                .cloned()
+                .collect();
+            unbinds.sort();
+            if !unbinds.is_empty() {
+                changes.push(PartitionChange::Unbind {
+                    pkey: *pkey,
+                    guids: unbinds,
+                });
+            }
+        }
+
+        if prune {
+            let mut undeclared: Vec<&PartitionSpec> = current
+                .iter()
+                .filter(|(k, c)| !declared.contains_key(k) && !c.partition.pkey.is_default())
+                .map(|(_, c)| *c)
+                .collect();
+            undeclared.sort_by_key(|c| u16::from(c.partition.pkey));
+            for c in undeclared {
+                changes.push(PartitionChange::Delete(c.partition.pkey));
+            }
+        }
+
+        Ok(Plan { changes })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.changes {
+            writeln!(f, "{}", c)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of applying a plan; see `Ufm::apply_partitions`.
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    /// The changes which were applied, in order.
+    pub applied: Vec<PartitionChange>,
+    /// The change which failed with its error, if any; the changes after it were not applied.
+    pub failed: Option<(PartitionChange, UFMError)>,
+    /// The number of the changes in the plan.
+    pub total: usize,
+}
+
+impl ApplyReport {
+    /// Whether there was nothing to change.
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+}
@@ -1,20 +1,31 @@
+use std::collections::hash_map::RandomState;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
 
+use hyper::body::{Bytes, HttpBody};
 use hyper::client::HttpConnector;
-use hyper::header::{AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+use hyper::header::{
+    HeaderMap, AUTHORIZATION, CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE, USER_AGENT,
+};
 use hyper::http::StatusCode;
-use hyper::{Body, Client, Method, Uri};
+use hyper::{Body, Client, Method, Request, Uri};
 use hyper_rustls::HttpsConnector;
 use hyper_timeout::TimeoutConnector;
 use thiserror::Error;
+use tokio::sync::Mutex;
 use tokio_rustls::rustls;
-use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier};
-use tokio_rustls::rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerName,
+};
 
-use crate::UFMCert;
+use crate::{RequestError, UFMCert};
 
 struct NoCertificateVerification;
 
@@ -34,25 +45,103 @@ impl ServerCertVerifier for NoCertificateVerification {
 
 const REST_TIME_OUT: Duration = Duration::from_secs(10);
 
+/// How to verify the certificate of UFM.
+#[derive(Clone, Debug, Default)]
+pub enum TlsMode {
+    /// Verify with the well-known roots of webpki; the CA of the client certificate is used
+    /// instead if there's one.
+    #[default]
+    WebPkiRoots,
+    /// Verify with the CA bundle in the PEM file.
+    CustomCa(String),
+    /// Do not verify the certificate of UFM; it's insecure and only for testing.
+    InsecureSkipVerify,
+}
+
+/// The TLS configuration of HTTPS connections to UFM.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    pub mode: TlsMode,
+    /// The server name to send by SNI and to verify the certificate against; the host of
+    /// the UFM address is used by default.
+    pub server_name: Option<String>,
+}
+
+type HttpClient = hyper::Client<TimeoutConnector<HttpConnector>>;
+type HttpsClient = hyper::Client<TimeoutConnector<HttpsConnector<HttpConnector>>>;
+
 #[derive(Error, Debug)]
 pub enum RestError {
     #[error("{0}")]
     Internal(String),
-    #[error("'{0}' not found")]
-    NotFound(String),
-    #[error("failed to auth '{0}'")]
-    AuthFailure(String),
     #[error("invalid configuration '{0}'")]
     InvalidConfig(String),
+    /// UFM replied with an error status.
+    #[error("{0}")]
+    Http(RequestError),
+    #[error("{0}")]
+    Timeout(RequestError),
+    #[error("{0}")]
+    Connection(RequestError),
 }
 
-impl From<hyper::Error> for RestError {
-    fn from(value: hyper::Error) -> Self {
-        if value.is_user() {
-            return RestError::AuthFailure(value.message().to_string());
+impl RestError {
+    fn from_hyper(method: &Method, path: &str, e: hyper::Error) -> Self {
+        let req_err = RequestError {
+            method: method.to_string(),
+            path: format!("/{}", path.trim_matches('/')),
+            status: None,
+            message: e.to_string(),
+            body: None,
+        };
+
+        // The timeout of hyper-timeout is reported as an io::Error in the sources.
+        let mut source = std::error::Error::source(&e);
+        while let Some(s) = source {
+            if let Some(io_err) = s.downcast_ref::<io::Error>() {
+                if io_err.kind() == io::ErrorKind::TimedOut {
+                    return RestError::Timeout(req_err);
+                }
+            }
+            source = s.source();
+        }
+
+        if e.is_timeout() {
+            return RestError::Timeout(req_err);
+        }
+
+        if e.is_connect()
+            || e.is_incomplete_message()
+            || e.is_canceled()
+            || e.is_closed()
+            || e.is_body_write_aborted()
+        {
+            return RestError::Connection(req_err);
         }
 
-        RestError::Internal(value.message().to_string())
+        RestError::Internal(format!("rest request failure: {}", req_err))
+    }
+
+    fn from_status(method: &Method, path: &str, status: StatusCode, data: &str) -> Self {
+        // UFM replies errors as `{"error": "..."}`, otherwise keep the raw body.
+        let body = serde_json::from_str::<serde_json::Value>(data).ok();
+        let message = body
+            .as_ref()
+            .and_then(|b| b.get("error"))
+            .and_then(|e| e.as_str())
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| match data.trim() {
+                "" => status.canonical_reason().unwrap_or_default().to_string(),
+                d => d.to_string(),
+            });
+
+        RestError::Http(RequestError {
+            method: method.to_string(),
+            path: format!("/{}", path.trim_matches('/')),
+            status: Some(status.as_u16()),
+            message,
+            body,
+        })
     }
 }
 
@@ -81,216 +170,259 @@ impl Display for RestScheme {
     }
 }
 
+/// The policy to retry the failed requests with exponential backoff.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The max attempts of a request including the first one; 1 disables retry.
+    pub max_attempts: u32,
+    /// The delay before the first retry; it's doubled for each of the following retries.
+    pub base_delay: Duration,
+    /// The upper bound of the delay between two attempts.
+    pub max_delay: Duration,
+    /// The ratio of the delay to randomize, from 0.0 (no jitter) to 1.0 (full jitter).
+    pub jitter: f64,
+    /// The HTTP status codes to retry.
+    pub retryable_status: Vec<u16>,
+    /// Retry when failed to connect to UFM or the connection is broken.
+    pub retry_connection_errors: bool,
+    /// Retry when the request timed out.
+    pub retry_timeouts: bool,
+    /// Retry POST requests; they're not retried by default as they're not idempotent.
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            retryable_status: vec![408, 429, 500, 502, 503, 504],
+            retry_connection_errors: true,
+            retry_timeouts: true,
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    fn should_retry(&self, method: &Method, attempt: u32, err: &RestError) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        let retryable_method = match *method {
+            Method::GET | Method::PUT | Method::DELETE => true,
+            Method::POST => self.retry_post,
+            _ => false,
+        };
+        if !retryable_method {
+            return false;
+        }
+
+        match err {
+            RestError::Http(e) => e
+                .status
+                .map(|s| self.retryable_status.contains(&s))
+                .unwrap_or(false),
+            RestError::Connection(_) => self.retry_connection_errors,
+            RestError::Timeout(_) => self.retry_timeouts,
+            _ => false,
+        }
+    }
+
+    /// The delay before the next attempt, after `attempt` attempts failed.
+    fn delay(&self, attempt: u32) -> Duration {
+        let exp = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(exp).min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+
+        // A random number in [0, 1) without pulling a RNG for it.
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - jitter * random)
+    }
+}
+
+/// The timeouts of the requests to UFM; `None` means no timeout.
+#[derive(Clone, Debug)]
+pub struct TimeoutConfig {
+    /// The timeout to establish the connection.
+    pub connect: Option<Duration>,
+    /// The timeout of waiting for the response, and each chunk of its body.
+    pub read: Option<Duration>,
+    /// The timeout of each write to the connection.
+    pub write: Option<Duration>,
+    /// The timeout of a whole request, including reading the response body; each retry has its own.
+    pub request: Option<Duration>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect: Some(REST_TIME_OUT),
+            read: Some(REST_TIME_OUT),
+            write: Some(REST_TIME_OUT),
+            request: None,
+        }
+    }
+}
+
+/// The connection pool of the clients to UFM.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// How long an idle connection is kept in the pool; `None` keeps it forever.
+    pub idle_timeout: Option<Duration>,
+    /// The max idle connections kept for each host.
+    pub max_idle_per_host: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(90)),
+            max_idle_per_host: usize::MAX,
+        }
+    }
+}
+
 pub struct RestClientConfig {
     pub address: String,
     pub port: Option<u16>,
     pub scheme: RestScheme,
     pub auth_info: String,
     pub base_path: String,
+    pub cert: Option<UFMCert>,
+    /// The username and password to log in a session of UFM; the requests are
+    /// authenticated by the session cookie instead of `auth_info`.
+    pub session: Option<(String, String)>,
+    pub tls: TlsConfig,
+    pub retry: RetryPolicy,
+    pub timeout: TimeoutConfig,
+    pub pool: PoolConfig,
+}
+
+/// A session of UFM, shared by the clients to the same UFM.
+#[derive(Clone)]
+struct Session {
+    login_url: String,
+    /// The form of the credentials to log in.
+    credentials: String,
+    /// The session cookie; `None` before logged in.
+    cookie: Arc<Mutex<Option<String>>>,
+}
+
+/// A successful response of UFM.
+struct RestResponse {
+    body: String,
+    /// The `Location` header, e.g. of the resource which is created.
+    location: Option<String>,
 }
 
 pub struct RestClient {
     base_url: String,
     auth_info: String,
+    session: Option<Session>,
     scheme: RestScheme,
-    http_client: hyper::Client<TimeoutConnector<HttpConnector>>,
-    https_client: hyper::Client<TimeoutConnector<HttpsConnector<HttpConnector>>>,
+    retry: RetryPolicy,
+    timeout: TimeoutConfig,
+    /// The clients share their connection pools with their clones.
+    http_client: HttpClient,
+    https_client: HttpsClient,
 }
 
 impl RestClient {
     pub fn new(conf: &RestClientConfig) -> Result<RestClient, RestError> {
-        let mut auth_info = conf.auth_info.clone().trim().to_string();
-        let mut auto_cert: Option<UFMCert> = None;
-
-        if auth_info.chars().filter(|c| *c == '\n').count() == 2 {
-            let mut v = auth_info.split('\n');
-            auto_cert = Some(UFMCert {
-                ca_crt: v.next().unwrap_or("").to_string(),
-                tls_key: v.next().unwrap_or("").to_string(),
-                tls_crt: v.next().unwrap_or("").to_string(),
-            });
-            auth_info = "".to_string();
-        } else {
-            auth_info = format!("Basic {}", conf.auth_info.clone().trim());
-        }
+        // The client certificate or the session cookie authenticates the requests instead of the header.
+        let auth_info = match (&conf.cert, &conf.session) {
+            (None, None) => format!("Basic {}", conf.auth_info.clone().trim()),
+            _ => "".to_string(),
+        };
 
-        let base_url = match &conf.port {
-            None => format!(
-                "{}://{}/{}",
-                conf.scheme,
-                conf.address,
-                conf.base_path.trim_matches('/')
-            ),
-            Some(p) => format!(
-                "{}://{}:{}/{}",
-                conf.scheme,
-                conf.address,
-                p,
-                conf.base_path.trim_matches('/')
-            ),
+        let origin = match &conf.port {
+            None => format!("{}://{}", conf.scheme, conf.address),
+            Some(p) => format!("{}://{}:{}", conf.scheme, conf.address, p),
         };
+        let base_url = format!("{}/{}", origin, conf.base_path.trim_matches('/'));
+
+        let session = conf.session.as_ref().map(|(username, password)| Session {
+            login_url: format!("{}/dologin", origin),
+            credentials: url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("httpd_username", username)
+                .append_pair("httpd_password", password)
+                .finish(),
+            cookie: Arc::new(Mutex::new(None)),
+        });
 
         let _ = base_url
             .parse::<Uri>()
             .map_err(|_| RestError::InvalidConfig("invalid rest address".to_string()))?;
 
-        let mut http_connector = TimeoutConnector::new(HttpConnector::new());
-        http_connector.set_connect_timeout(Some(REST_TIME_OUT));
-        http_connector.set_read_timeout(Some(REST_TIME_OUT));
-        http_connector.set_write_timeout(Some(REST_TIME_OUT));
+        let config = build_tls_config(conf.cert.as_ref(), &conf.tls)?;
 
-        let config = if let Some(auto_cert) = &auto_cert {
-            // Get CA root
-            let mut roots = RootCertStore::empty();
-            let fd = match std::fs::File::open(auto_cert.ca_crt.clone()) {
-                Ok(fd) => fd,
-                Err(_) => {
-                    return Err(RestError::NotFound(format!(
-                        "Root CA file not found at '{}'",
-                        auto_cert.ca_crt.clone()
-                    )));
-                }
-            };
-            let mut buf = std::io::BufReader::new(&fd);
-            match rustls_pemfile::certs(&mut buf) {
-                Ok(certs) => roots.add_parsable_certificates(&certs),
-                Err(_) => {
-                    return Err(RestError::NotFound(format!(
-                        "Root CA file not found at '{}'",
-                        auto_cert.tls_crt.clone()
-                    )));
-                }
-            };
-
-            // Get client certificate
-            let certs = {
-                let fd = match std::fs::File::open(auto_cert.tls_crt.clone()) {
-                    Ok(fd) => fd,
-                    Err(_) => {
-                        return Err(RestError::NotFound(format!(
-                            "Client Cert file not found at '{}'",
-                            auto_cert.tls_crt.clone()
-                        )));
-                    }
-                };
-                let mut buf = std::io::BufReader::new(&fd);
-                match rustls_pemfile::certs(&mut buf) {
-                    Ok(certs) => certs.into_iter().map(Certificate).collect::<Vec<_>>(),
-                    Err(_) => {
-                        return Err(RestError::NotFound(format!(
-                            "Client Cert file not found at '{}'",
-                            auto_cert.tls_crt.clone()
-                        )));
-                    }
-                }
-            };
-
-            // Get client private key
-            let key = {
-                let fd = match std::fs::File::open(auto_cert.tls_key.clone()) {
-                    Ok(fd) => fd,
-                    Err(_) => {
-                        return Err(RestError::NotFound(format!(
-                            "Client Private Key file not found at '{}'",
-                            auto_cert.tls_key.clone()
-                        )));
-                    }
-                };
-                let mut buf = std::io::BufReader::new(&fd);
-                use rustls_pemfile::Item;
-                match rustls_pemfile::read_one(&mut buf) {
-                    Ok(Some(item)) => match item {
-                        Item::RSAKey(rsa_key) => Some(PrivateKey(rsa_key)),
-                        Item::PKCS8Key(pkcs8_key) => Some(PrivateKey(pkcs8_key)),
-                        Item::ECKey(ec_key) => Some(PrivateKey(ec_key)),
-                        Item::X509Certificate(_) => {
-                            return Err(RestError::NotFound(format!(
-                                "Expected Client Private Key but certificate is found '{}'",
-                                auto_cert.tls_key.clone()
-                            )));
-                        }
-                        Item::Crl(_) => {
-                            return Err(RestError::NotFound(format!("Expected Client Private Key but certificate revocation list is found '{}'", auto_cert.tls_key)));
-                        }
-                        _ => {
-                            return Err(RestError::NotFound(format!(
-                                "Client Private Key is corrupted '{}'",
-                                auto_cert.tls_key.clone()
-                            )));
-                        }
-                    },
-                    _ => {
-                        return Err(RestError::NotFound(format!(
-                            "Client Private Key file not found at '{}'",
-                            auto_cert.tls_key.clone()
-                        )));
-                    }
-                }
-            };
-
-            let build_no_client_auth_config = || {
-                ClientConfig::builder()
-                    .with_safe_defaults()
-                    .with_root_certificates(roots.clone())
-                    .with_no_client_auth()
-            };
-
-            if !certs.is_empty() && key.is_some() {
-                if let Ok(config) = ClientConfig::builder()
-                    .with_safe_defaults()
-                    .with_root_certificates(roots.clone())
-                    .with_client_auth_cert(certs, key.unwrap())
-                {
-                    // Use TLS flow with client authentication
-                    config
-                } else {
-                    // Client creation failure
-                    build_no_client_auth_config()
-                }
-            } else {
-                // Unable to use client cert/key pair
-                build_no_client_auth_config()
-            }
-        } else {
-            ClientConfig::builder()
-                .with_safe_defaults()
-                .with_custom_certificate_verifier(std::sync::Arc::new(NoCertificateVerification))
-                .with_no_client_auth()
-        };
-
-        let mut https_connector = TimeoutConnector::new(
-            hyper_rustls::HttpsConnectorBuilder::new()
-                .with_tls_config(config)
-                .https_or_http()
-                .enable_http1()
-                .enable_http2()
-                .build(),
-        );
-        https_connector.set_connect_timeout(Some(REST_TIME_OUT));
-        https_connector.set_read_timeout(Some(REST_TIME_OUT));
-        https_connector.set_write_timeout(Some(REST_TIME_OUT));
+        let (http_client, https_client) =
+            build_clients(&config, &conf.tls, &conf.timeout, &conf.pool);
 
         Ok(Self {
             base_url,
             auth_info,
+            session,
             scheme: conf.scheme.clone(),
-            // TODO(k82cn): Add timout for the clients.
-            http_client: Client::builder().build::<_, hyper::Body>(http_connector),
-            https_client: Client::builder().build::<_, hyper::Body>(https_connector),
+            retry: conf.retry.clone(),
+            timeout: conf.timeout.clone(),
+            http_client,
+            https_client,
         })
     }
 
+    /// A client sharing the connections of this one, whose requests time out after `timeout`
+    /// as a whole instead of by the read and request timeouts.
+    pub fn with_timeout(&self, timeout: Duration) -> RestClient {
+        Self {
+            base_url: self.base_url.clone(),
+            auth_info: self.auth_info.clone(),
+            session: self.session.clone(),
+            scheme: self.scheme.clone(),
+            retry: self.retry.clone(),
+            timeout: TimeoutConfig {
+                read: None,
+                request: Some(timeout),
+                ..self.timeout.clone()
+            },
+            http_client: self.http_client.clone(),
+            https_client: self.https_client.clone(),
+        }
+    }
+
     pub async fn get<'a, T: serde::de::DeserializeOwned>(
         &'a self,
         path: &'a str,
     ) -> Result<T, RestError> {
-        let resp = self.execute_request(Method::GET, path, None).await?;
+        let resp = self.execute_request(Method::GET, path, None).await?.body;
+        // UFM replies an empty object for the resource not found.
         if resp.eq("{}") {
-            return Err(RestError::NotFound(path.to_string()));
+            return Err(RestError::from_status(
+                &Method::GET,
+                path,
+                StatusCode::NOT_FOUND,
+                "",
+            ));
         }
 
         let data = serde_json::from_str(&resp)
-            .map_err(|_| RestError::InvalidConfig("invalid response".to_string()))?;
+            .map_err(|e| RestError::Internal(format!("invalid response of '{path}': {e}")))?;
 
         Ok(data)
     }
@@ -299,13 +431,39 @@ impl RestClient {
         &'a self,
         path: &'a str,
     ) -> Result<T, RestError> {
-        let resp = self.execute_request(Method::GET, path, None).await?;
+        let resp = self.execute_request(Method::GET, path, None).await?.body;
+        let data = serde_json::from_str(&resp)
+            .map_err(|e| RestError::Internal(format!("invalid response of '{path}': {e}")))?;
+
+        Ok(data)
+    }
+
+    /// POST the data, and parse the created resource from the response.
+    pub async fn create<'a, T: serde::de::DeserializeOwned>(
+        &'a self,
+        path: &'a str,
+        data: String,
+    ) -> Result<T, RestError> {
+        let resp = self
+            .execute_request(Method::POST, path, Some(data))
+            .await?
+            .body;
         let data = serde_json::from_str(&resp)
-            .map_err(|_| RestError::InvalidConfig("invalid response".to_string()))?;
+            .map_err(|e| RestError::Internal(format!("invalid response of '{path}': {e}")))?;
 
         Ok(data)
     }
 
+    /// POST the data, and return the location of the created resource, e.g. the resources
+    /// which UFM creates asynchronously with `202 Accepted`.
+    pub async fn create_location(&self, path: &str, data: String) -> Result<String, RestError> {
+        let resp = self.execute_request(Method::POST, path, Some(data)).await?;
+
+        resp.location.ok_or(RestError::Internal(format!(
+            "no location in the response of '{path}'"
+        )))
+    }
+
     pub async fn post(&self, path: &str, data: String) -> Result<(), RestError> {
         self.execute_request(Method::POST, path, Some(data)).await?;
 
@@ -329,7 +487,107 @@ impl RestClient {
         method: Method,
         path: &str,
         data: Option<String>,
-    ) -> Result<String, RestError> {
+    ) -> Result<RestResponse, RestError> {
+        let mut attempt = 1;
+        loop {
+            match self.send_request(&method, path, data.clone()).await {
+                Err(e) if self.retry.should_retry(&method, attempt, &e) => {
+                    let delay = self.retry.delay(attempt);
+                    log::warn!(
+                        "Attempt {attempt} of {method} {path} failed: {e}; retry in {delay:?}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn send_request(
+        &self,
+        method: &Method,
+        path: &str,
+        data: Option<String>,
+    ) -> Result<RestResponse, RestError> {
+        let session = match &self.session {
+            None => return self.send_once(method, path, data, None).await,
+            Some(session) => session,
+        };
+
+        let cookie = self.login(session, None).await?;
+        match self
+            .send_once(method, path, data.clone(), Some(&cookie))
+            .await
+        {
+            Err(RestError::Http(e)) if e.status == Some(StatusCode::UNAUTHORIZED.as_u16()) => {
+                log::info!("The session of UFM expired; log in again");
+                let cookie = self.login(session, Some(cookie)).await?;
+                self.send_once(method, path, data, Some(&cookie)).await
+            }
+            res => res,
+        }
+    }
+
+    /// The cookie of the session; log in if there's no session yet, or the session is `expired`.
+    async fn login(&self, session: &Session, expired: Option<String>) -> Result<String, RestError> {
+        let mut cookie = session.cookie.lock().await;
+        if let Some(c) = cookie.as_ref() {
+            // Another request may have logged in already.
+            if Some(c) != expired.as_ref() {
+                return Ok(c.to_string());
+            }
+        }
+
+        let path = "/dologin";
+        log::debug!("Method: POST, URL: {}", session.login_url);
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(&session.login_url)
+            .header(USER_AGENT, env!("CARGO_PKG_NAME"))
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(session.credentials.clone()))
+            .map_err(|_| RestError::InvalidConfig("invalid login request".to_string()))?;
+
+        let (status, headers, chunk) = self.send(&Method::POST, path, req).await?;
+        if !status.is_success() && !status.is_redirection() {
+            let data = String::from_utf8_lossy(&chunk).to_string();
+            return Err(RestError::from_status(&Method::POST, path, status, &data));
+        }
+
+        // Keep the name and value of the cookies, without the attributes.
+        let cookies = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| v.split(';').next())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            return Err(RestError::Http(RequestError {
+                method: Method::POST.to_string(),
+                path: path.to_string(),
+                status: Some(StatusCode::UNAUTHORIZED.as_u16()),
+                message: "no session cookie in the response of login".to_string(),
+                body: None,
+            }));
+        }
+
+        let c = cookies.join("; ");
+        *cookie = Some(c.clone());
+
+        Ok(c)
+    }
+
+    async fn send_once(
+        &self,
+        method: &Method,
+        path: &str,
+        data: Option<String>,
+        cookie: Option<&str>,
+    ) -> Result<RestResponse, RestError> {
         let url = format!("{}/{}", self.base_url, path.trim_matches('/'));
         let uri = url
             .parse::<Uri>()
@@ -338,39 +596,248 @@ impl RestClient {
         let body = data.unwrap_or_default();
         log::debug!("Method: {method}, URL: {url}, Body: {body}");
 
-        let req = hyper::Request::builder()
-            .method(method)
+        let mut builder = Request::builder()
+            .method(method.clone())
             .uri(uri)
             .header(USER_AGENT, env!("CARGO_PKG_NAME"))
-            .header(CONTENT_TYPE, "application/json")
-            .header(AUTHORIZATION, self.auth_info.to_string())
+            .header(CONTENT_TYPE, "application/json");
+        if !self.auth_info.is_empty() {
+            builder = builder.header(AUTHORIZATION, self.auth_info.to_string());
+        }
+        if let Some(cookie) = cookie {
+            builder = builder.header(COOKIE, cookie);
+        }
+
+        let req = builder
             .body(Body::from(body))
             .map_err(|_| RestError::InvalidConfig("invalid rest request".to_string()))?;
 
-        let body = match &self.scheme {
-            RestScheme::Http => {
-                self.http_client
-                    .request(req)
+        let (status, headers, chunk) = self.send(method, path, req).await?;
+        let data = String::from_utf8_lossy(&chunk).to_string();
+
+        match status {
+            StatusCode::OK | StatusCode::CREATED | StatusCode::ACCEPTED => Ok(RestResponse {
+                body: data,
+                location: headers
+                    .get(LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_string()),
+            }),
+            _ => Err(RestError::from_status(method, path, status, &data)),
+        }
+    }
+
+    /// Send the request and read the whole response, within the read timeout of the response
+    /// and each chunk of its body, and the request timeout of all, if any.
+    async fn send(
+        &self,
+        method: &Method,
+        path: &str,
+        req: Request<Body>,
+    ) -> Result<(StatusCode, HeaderMap, Bytes), RestError> {
+        let read = self.timeout.read;
+        let send = async {
+            let resp = within(read, method, path, "read", async {
+                match &self.scheme {
+                    RestScheme::Http => self.http_client.request(req).await,
+                    RestScheme::Https => self.https_client.request(req).await,
+                }
+                .map_err(|e| RestError::from_hyper(method, path, e))
+            })
+            .await?;
+
+            let status = resp.status();
+            let headers = resp.headers().clone();
+
+            let mut body = resp.into_body();
+            let mut data = vec![];
+            while let Some(chunk) = within(read, method, path, "read", async {
+                body.data()
                     .await
-                    .map_err(|e: hyper::Error| {
-                        RestError::Internal(format!("rest request failure: {:?}", e))
-                    })?
+                    .transpose()
+                    .map_err(|e| RestError::from_hyper(method, path, e))
+            })
+            .await?
+            {
+                data.extend_from_slice(&chunk);
             }
-            RestScheme::Https => self
-                .https_client
-                .request(req)
-                .await
-                .map_err(|e| RestError::Internal(format!("rest request failure: {:?}", e)))?,
+
+            Ok::<_, RestError>((status, headers, Bytes::from(data)))
         };
 
-        let status = body.status();
-        let chunk = hyper::body::to_bytes(body.into_body()).await?;
-        let data = String::from_utf8(chunk.to_vec()).unwrap();
+        within(self.timeout.request, method, path, "request", send).await
+    }
+}
 
-        match status {
-            StatusCode::OK => Ok(data),
-            StatusCode::CREATED => Ok(data),
-            _ => Err(RestError::Internal(data)),
+/// Run the future of the request within the timeout, if any.
+async fn within<T>(
+    timeout: Option<Duration>,
+    method: &Method,
+    path: &str,
+    what: &str,
+    fut: impl Future<Output = Result<T, RestError>>,
+) -> Result<T, RestError> {
+    let t = match timeout {
+        None => return fut.await,
+        Some(t) => t,
+    };
+
+    tokio::time::timeout(t, fut).await.map_err(|_| {
+        RestError::Timeout(RequestError {
+            method: method.to_string(),
+            path: format!("/{}", path.trim_matches('/')),
+            status: None,
+            message: format!("{what} timed out after {t:?}"),
+            body: None,
+        })
+    })?
+}
+
+fn build_clients(
+    tls_config: &ClientConfig,
+    tls: &TlsConfig,
+    timeout: &TimeoutConfig,
+    pool: &PoolConfig,
+) -> (HttpClient, HttpsClient) {
+    let mut http_connector = TimeoutConnector::new(HttpConnector::new());
+    http_connector.set_connect_timeout(timeout.connect);
+    http_connector.set_write_timeout(timeout.write);
+
+    let builder = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config.clone())
+        .https_or_http();
+    let builder = match &tls.server_name {
+        Some(name) => builder.with_server_name(name.to_string()),
+        None => builder,
+    };
+    let mut https_connector = TimeoutConnector::new(builder.enable_http1().enable_http2().build());
+    https_connector.set_connect_timeout(timeout.connect);
+    https_connector.set_write_timeout(timeout.write);
+
+    let mut builder = Client::builder();
+    builder
+        .pool_idle_timeout(pool.idle_timeout)
+        .pool_max_idle_per_host(pool.max_idle_per_host);
+
+    (
+        builder.build::<_, hyper::Body>(http_connector),
+        builder.build::<_, hyper::Body>(https_connector),
+    )
+}
+
+fn build_tls_config(cert: Option<&UFMCert>, tls: &TlsConfig) -> Result<ClientConfig, RestError> {
+    let builder = ClientConfig::builder().with_safe_defaults();
+
+    let verifier: Arc<dyn ServerCertVerifier> = match (&tls.mode, cert) {
+        (TlsMode::InsecureSkipVerify, _) => {
+            log::warn!("The certificate of UFM is not verified");
+            Arc::new(NoCertificateVerification)
         }
+        (TlsMode::CustomCa(ca_crt), _) => Arc::new(WebPkiVerifier::new(load_roots(ca_crt)?, None)),
+        (TlsMode::WebPkiRoots, Some(cert)) => {
+            Arc::new(WebPkiVerifier::new(load_roots(&cert.ca_crt)?, None))
+        }
+        (TlsMode::WebPkiRoots, None) => {
+            let mut roots = RootCertStore::empty();
+            roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject.as_ref(),
+                    ta.subject_public_key_info.as_ref(),
+                    ta.name_constraints.as_ref().map(|nc| nc.as_ref()),
+                )
+            }));
+            Arc::new(WebPkiVerifier::new(roots, None))
+        }
+    };
+    let builder = builder.with_custom_certificate_verifier(verifier);
+
+    let cert = match cert {
+        Some(cert) => cert,
+        None => return Ok(builder.with_no_client_auth()),
+    };
+
+    let certs = load_certs(&cert.tls_crt)?;
+    let key = load_key(&cert.tls_key)?;
+
+    match builder.with_client_auth_cert(certs, key) {
+        // Use TLS flow with client authentication
+        Ok(config) => Ok(config),
+        Err(e) => Err(RestError::InvalidConfig(format!(
+            "invalid Client Cert '{}' or Private Key '{}': {e}",
+            cert.tls_crt, cert.tls_key
+        ))),
+    }
+}
+
+fn load_roots(ca_crt: &str) -> Result<RootCertStore, RestError> {
+    let mut roots = RootCertStore::empty();
+    let fd = std::fs::File::open(ca_crt)
+        .map_err(|_| RestError::InvalidConfig(format!("Root CA file not found at '{}'", ca_crt)))?;
+    let mut buf = std::io::BufReader::new(&fd);
+    let certs = rustls_pemfile::certs(&mut buf)
+        .map_err(|_| RestError::InvalidConfig(format!("Root CA file is corrupted '{}'", ca_crt)))?;
+
+    let (added, _) = roots.add_parsable_certificates(&certs);
+    if added == 0 {
+        return Err(RestError::InvalidConfig(format!(
+            "No Root CA found in '{}'",
+            ca_crt
+        )));
+    }
+
+    Ok(roots)
+}
+
+fn load_certs(tls_crt: &str) -> Result<Vec<Certificate>, RestError> {
+    let fd = std::fs::File::open(tls_crt).map_err(|_| {
+        RestError::InvalidConfig(format!("Client Cert file not found at '{}'", tls_crt))
+    })?;
+    let mut buf = std::io::BufReader::new(&fd);
+    let certs = rustls_pemfile::certs(&mut buf).map_err(|_| {
+        RestError::InvalidConfig(format!("Client Cert file is corrupted '{}'", tls_crt))
+    })?;
+
+    if certs.is_empty() {
+        return Err(RestError::InvalidConfig(format!(
+            "No Client Cert found in '{}'",
+            tls_crt
+        )));
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(tls_key: &str) -> Result<PrivateKey, RestError> {
+    let fd = std::fs::File::open(tls_key).map_err(|_| {
+        RestError::InvalidConfig(format!(
+            "Client Private Key file not found at '{}'",
+            tls_key
+        ))
+    })?;
+    let mut buf = std::io::BufReader::new(&fd);
+
+    use rustls_pemfile::Item;
+    match rustls_pemfile::read_one(&mut buf) {
+        Ok(Some(item)) => match item {
+            Item::RSAKey(rsa_key) => Ok(PrivateKey(rsa_key)),
+            Item::PKCS8Key(pkcs8_key) => Ok(PrivateKey(pkcs8_key)),
+            Item::ECKey(ec_key) => Ok(PrivateKey(ec_key)),
+            Item::X509Certificate(_) => Err(RestError::InvalidConfig(format!(
+                "Expected Client Private Key but certificate is found '{}'",
+                tls_key
+            ))),
+            Item::Crl(_) => Err(RestError::InvalidConfig(format!(
+                "Expected Client Private Key but certificate revocation list is found '{}'",
+                tls_key
+            ))),
+            _ => Err(RestError::InvalidConfig(format!(
+                "Client Private Key is corrupted '{}'",
+                tls_key
+            ))),
+        },
+        _ => Err(RestError::InvalidConfig(format!(
+            "Client Private Key file not found at '{}'",
+            tls_key
+        ))),
     }
 }
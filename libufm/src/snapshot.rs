@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::reconcile::qos_diffs;
+use crate::{Guid, Lid, LogicalState, Partition, PartitionKey, PartitionQoS, Port};
+
+/// The partitions at a time, and the ports of some of them; see `Ufm::partition_snapshot`.
+#[derive(Debug, Clone, Default)]
+pub struct PartitionSnapshot {
+    pub partitions: BTreeMap<PartitionKey, Partition>,
+    /// The ports of the partitions whose ports were listed, by pkey and GUID.
+    pub ports: BTreeMap<PartitionKey, BTreeMap<Guid, Port>>,
+}
+
+impl PartitionSnapshot {
+    pub fn new(partitions: Vec<Partition>) -> Self {
+        Self {
+            partitions: partitions.into_iter().map(|p| (p.pkey, p)).collect(),
+            ports: BTreeMap::new(),
+        }
+    }
+
+    /// Add the ports of the partition, so that its members are compared by `diff`.
+    pub fn add_ports(&mut self, pkey: PartitionKey, ports: Vec<Port>) {
+        self.ports
+            .insert(pkey, ports.into_iter().map(|p| (p.guid, p)).collect());
+    }
+
+    /// The changes from this snapshot to the newer one, ordered by pkey. The members are
+    /// compared only for the partitions whose ports are in both snapshots.
+    pub fn diff(&self, newer: &PartitionSnapshot) -> Vec<SnapshotChange> {
+        let mut changes = vec![];
+
+        for (pkey, p) in &self.partitions {
+            if !newer.partitions.contains_key(pkey) {
+                changes.push(SnapshotChange::PartitionRemoved(p.clone()));
+            }
+        }
+        for (pkey, p) in &newer.partitions {
+            match self.partitions.get(pkey) {
+                None => changes.push(SnapshotChange::PartitionAdded(p.clone())),
+                Some(old) if !qos_diffs(&old.qos, &p.qos).is_empty() => {
+                    changes.push(SnapshotChange::QoSChanged {
+                        pkey: *pkey,
+                        from: old.qos.clone(),
+                        to: p.qos.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (pkey, ports) in &newer.ports {
+            let Some(old) = self.ports.get(pkey) else {
+                continue;
+            };
+
+            for (guid, port) in old {
+                if !ports.contains_key(guid) {
+                    changes.push(SnapshotChange::MemberLeft {
+                        pkey: *pkey,
+                        port: port.clone(),
+                    });
+                }
+            }
+            for (guid, port) in ports {
+                let Some(prev) = old.get(guid) else {
+                    changes.push(SnapshotChange::MemberJoined {
+                        pkey: *pkey,
+                        port: port.clone(),
+                    });
+                    continue;
+                };
+
+                if prev.lid != port.lid {
+                    changes.push(SnapshotChange::LidChanged {
+                        pkey: *pkey,
+                        guid: *guid,
+                        from: prev.lid,
+                        to: port.lid,
+                    });
+                }
+                if prev.logical_state != port.logical_state {
+                    changes.push(SnapshotChange::StateChanged {
+                        pkey: *pkey,
+                        guid: *guid,
+                        from: prev.logical_state,
+                        to: port.logical_state,
+                    });
+                }
+            }
+        }
+
+        changes.sort_by_key(|c| c.pkey());
+        changes
+    }
+}
+
+/// A change between two snapshots of the partitions.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotChange {
+    PartitionAdded(Partition),
+    PartitionRemoved(Partition),
+    #[serde(rename = "qos_changed")]
+    QoSChanged {
+        pkey: PartitionKey,
+        from: PartitionQoS,
+        to: PartitionQoS,
+    },
+    MemberJoined {
+        pkey: PartitionKey,
+        port: Port,
+    },
+    MemberLeft {
+        pkey: PartitionKey,
+        port: Port,
+    },
+    LidChanged {
+        pkey: PartitionKey,
+        guid: Guid,
+        from: Option<Lid>,
+        to: Option<Lid>,
+    },
+    /// The logical state of the member changed, e.g. from `Active` to `Down`.
+    StateChanged {
+        pkey: PartitionKey,
+        guid: Guid,
+        from: Option<LogicalState>,
+        to: Option<LogicalState>,
+    },
+}
+
+impl SnapshotChange {
+    pub fn pkey(&self) -> PartitionKey {
+        match self {
+            SnapshotChange::PartitionAdded(p) | SnapshotChange::PartitionRemoved(p) => p.pkey,
+            SnapshotChange::QoSChanged { pkey, .. }
+            | SnapshotChange::MemberJoined { pkey, .. }
+            | SnapshotChange::MemberLeft { pkey, .. }
+            | SnapshotChange::LidChanged { pkey, .. }
+            | SnapshotChange::StateChanged { pkey, .. } => *pkey,
+        }
+    }
+}
+
+impl fmt::Display for SnapshotChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let partition = |p: &Partition| match p.name.is_empty() {
+            true => p.pkey.to_string(),
+            false => format!("{} ({})", p.pkey, p.name),
+        };
+        let lid = |l: &Option<Lid>| l.map_or("-".to_string(), |l| l.to_string());
+        let state = |s: &Option<LogicalState>| s.map_or("Unknown".to_string(), |s| s.to_string());
+
+        match self {
+            SnapshotChange::PartitionAdded(p) => write!(f, "+ partition {}", partition(p)),
+            SnapshotChange::PartitionRemoved(p) => write!(f, "- partition {}", partition(p)),
+            SnapshotChange::QoSChanged { pkey, from, to } => {
+                write!(
+                    f,
+                    "~ partition {}: {}",
+                    pkey,
+                    qos_diffs(from, to).join(", ")
+                )
+            }
+            SnapshotChange::MemberJoined { pkey, port } => {
+                write!(
+                    f,
+                    "+ member {}: {} ({})",
+                    pkey,
+                    port.guid,
+                    state(&port.logical_state)
+                )
+            }
+            SnapshotChange::MemberLeft { pkey, port } => {
+                write!(f, "- member {}: {}", pkey, port.guid)
+            }
+            SnapshotChange::LidChanged {
+                pkey,
+                guid,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ member {}: {} lid {} -> {}",
+                pkey,
+                guid,
+                lid(from),
+                lid(to)
+            ),
+            SnapshotChange::StateChanged {
+                pkey,
+                guid,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ member {}: {} state {} -> {}",
+                pkey,
+                guid,
+                state(from),
+                state(to)
+            ),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Guid, Link, System, UFMError};
+
+/// A node of the topology, i.e. a system in the fabric.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TopologyNode {
+    pub guid: Guid,
+    /// The name of the system; `None` if it's not one of the systems of UFM.
+    pub name: Option<String>,
+    /// The type of the system, e.g. `switch` or `host`.
+    pub system_type: Option<String>,
+}
+
+/// The graph of the systems and the links between them.
+///
+/// The nodes and the links are kept in order, and each link goes from the lower
+/// end to the higher one, so the exports of the same cabling are the same.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    pub nodes: Vec<TopologyNode>,
+    pub links: Vec<Link>,
+}
+
+impl Topology {
+    pub fn new(systems: Vec<System>, links: Vec<Link>) -> Topology {
+        let mut nodes: BTreeMap<Guid, TopologyNode> = systems
+            .into_iter()
+            .map(|s| {
+                let node = TopologyNode {
+                    guid: s.guid,
+                    name: Some(s.system_name),
+                    system_type: Some(s.system_type),
+                };
+                (s.guid, node)
+            })
+            .collect();
+
+        let mut links: Vec<Link> = links
+            .into_iter()
+            .map(|mut l| {
+                if l.destination < l.source {
+                    std::mem::swap(&mut l.source, &mut l.destination);
+                }
+                l
+            })
+            .collect();
+        links.sort_by(|a, b| (&a.source, &a.destination).cmp(&(&b.source, &b.destination)));
+
+        // The systems which are not reported by UFM, e.g. the ones being replaced.
+        for l in &links {
+            for guid in [l.source.system_guid, l.destination.system_guid] {
+                nodes.entry(guid).or_insert(TopologyNode {
+                    guid,
+                    name: None,
+                    system_type: None,
+                });
+            }
+        }
+
+        Topology {
+            nodes: nodes.into_values().collect(),
+            links,
+        }
+    }
+
+    /// Export the topology as an undirected graph of Graphviz DOT.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph fabric {\n");
+
+        for n in &self.nodes {
+            let shape = match n.system_type.as_deref() {
+                Some("switch") => "box",
+                _ => "ellipse",
+            };
+            let label = n.name.clone().unwrap_or(n.guid.to_string());
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\", shape={}];",
+                n.guid,
+                escape(&label),
+                shape
+            );
+        }
+
+        for l in &self.links {
+            let label = [l.speed.as_deref(), l.width.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -- \"{}\" [taillabel=\"{} ({})\", headlabel=\"{} ({})\", label=\"{}\"];",
+                l.source.system_guid,
+                l.destination.system_guid,
+                l.source.port_number,
+                l.source.port_guid,
+                l.destination.port_number,
+                l.destination.port_guid,
+                escape(&label)
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> Result<String, UFMError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| UFMError::Internal(format!("failed to export topology: {e}")))
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
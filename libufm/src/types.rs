@@ -1,4 +1,12 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{Guid, UFMError};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
@@ -7,38 +15,350 @@ pub enum PortType {
     Virtual,
 }
 
+/// The type of the systems in the fabric.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemType {
+    Switch,
+    Computer,
+    Gateway,
+}
+
+impl fmt::Display for SystemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SystemType::Switch => "Switch",
+            SystemType::Computer => "Computer",
+            SystemType::Gateway => "Gateway",
+        };
+        f.pad(s)
+    }
+}
+
+impl FromStr for SystemType {
+    type Err = UFMError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "switch" => Ok(SystemType::Switch),
+            // The type of the computers is `host` in the systems of UFM.
+            "computer" | "host" => Ok(SystemType::Computer),
+            "gateway" => Ok(SystemType::Gateway),
+            _ => Err(UFMError::InvalidConfig(format!(
+                "invalid system type: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// The LID of a port; the unassigned LIDs, i.e. `0` and `0xffff`, are `None` in `Port`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lid(u16);
+
+impl Lid {
+    /// The LID, or `None` if it's unassigned or out of range.
+    pub fn new(lid: i64) -> Option<Lid> {
+        match u16::try_from(lid) {
+            Ok(0) | Ok(0xffff) | Err(_) => None,
+            Ok(lid) => Some(Lid(lid)),
+        }
+    }
+}
+
+impl From<Lid> for u16 {
+    fn from(lid: Lid) -> Self {
+        lid.0
+    }
+}
+
+impl fmt::Display for Lid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Lid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let lid = i64::deserialize(deserializer)?;
+        Lid::new(lid).ok_or(de::Error::custom(format!("invalid lid: {lid}")))
+    }
+}
+
+/// UFM replies `0` or `65535` for the LID of a port without a LID.
+fn unassigned_as_none<'de, D>(deserializer: D) -> Result<Option<Lid>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Lid::new(i64::deserialize(deserializer)?))
+}
+
+/// The logical state of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogicalState {
+    Down,
+    Init,
+    Armed,
+    Active,
+}
+
+impl LogicalState {
+    pub const ALL: [LogicalState; 4] = [
+        LogicalState::Down,
+        LogicalState::Init,
+        LogicalState::Armed,
+        LogicalState::Active,
+    ];
+}
+
+impl fmt::Display for LogicalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            LogicalState::Down => "Down",
+            LogicalState::Init => "Init",
+            LogicalState::Armed => "Armed",
+            LogicalState::Active => "Active",
+        };
+        f.pad(s)
+    }
+}
+
+impl FromStr for LogicalState {
+    type Err = UFMError;
+
+    /// Parse the state case-insensitively, e.g. `Active` or `ACTIVE`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_state(s).as_str() {
+            "initialize" => Some(LogicalState::Init),
+            n => LogicalState::ALL
+                .into_iter()
+                .find(|l| normalize_state(&l.to_string()) == n),
+        }
+        .ok_or(UFMError::InvalidConfig(format!(
+            "invalid logical state: {}",
+            s
+        )))
+    }
+}
+
+/// The physical state of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicalState {
+    Sleep,
+    Polling,
+    Disabled,
+    PortConfigurationTraining,
+    LinkUp,
+    LinkErrorRecovery,
+    PhyTest,
+}
+
+impl PhysicalState {
+    pub const ALL: [PhysicalState; 7] = [
+        PhysicalState::Sleep,
+        PhysicalState::Polling,
+        PhysicalState::Disabled,
+        PhysicalState::PortConfigurationTraining,
+        PhysicalState::LinkUp,
+        PhysicalState::LinkErrorRecovery,
+        PhysicalState::PhyTest,
+    ];
+}
+
+impl fmt::Display for PhysicalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PhysicalState::Sleep => "Sleep",
+            PhysicalState::Polling => "Polling",
+            PhysicalState::Disabled => "Disabled",
+            PhysicalState::PortConfigurationTraining => "PortConfigurationTraining",
+            PhysicalState::LinkUp => "LinkUp",
+            PhysicalState::LinkErrorRecovery => "LinkErrorRecovery",
+            PhysicalState::PhyTest => "PhyTest",
+        };
+        f.pad(s)
+    }
+}
+
+impl FromStr for PhysicalState {
+    type Err = UFMError;
+
+    /// Parse the state case-insensitively, ignoring the spaces, e.g. `LinkUp` or `Link Up`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let n = normalize_state(s);
+        PhysicalState::ALL
+            .into_iter()
+            .find(|p| normalize_state(&p.to_string()) == n)
+            .ok_or(UFMError::InvalidConfig(format!(
+                "invalid physical state: {}",
+                s
+            )))
+    }
+}
+
+fn normalize_state(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// The states are in UFM's strings, e.g. `Active` or `LinkUp`.
+macro_rules! state_serde {
+    ($state:ty) => {
+        impl Serialize for $state {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $state {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+state_serde!(LogicalState);
+state_serde!(PhysicalState);
+
+/// UFM replies the states which are not known, e.g. `N/A`, for the ports without a state.
+fn unknown_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|s| s.parse().ok()))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub struct Port {
-    pub guid: String,
+    pub guid: Guid,
     pub name: Option<String>,
     pub system_id: String,
-    pub lid: i32,
+    /// The LID of the port; `None` if it's unassigned, or the port is not in UFM.
+    pub lid: Option<Lid>,
     pub system_name: String,
-    pub logical_state: String,
-    pub parent_guid: Option<String>,
+    /// The logical state of the port; `None` if it's unknown, e.g. the port is not in UFM.
+    pub logical_state: Option<LogicalState>,
+    pub parent_guid: Option<Guid>,
     pub port_type: Option<PortType>,
+    pub physical_state: Option<PhysicalState>,
+    /// The active speed of the link, e.g. `HDR`.
+    pub active_speed: Option<String>,
+    /// The active width of the link, e.g. `4x`.
+    pub active_width: Option<String>,
+    pub mtu: Option<u32>,
+    /// The GUID of the node at the other end of the link.
+    pub peer_guid: Option<Guid>,
+    pub peer_node_name: Option<String>,
+    /// The port at the other end of the link, e.g. `b83fd203002a1f3a_1`.
+    pub peer_port: Option<String>,
+    pub firmware_version: Option<String>,
+    /// The number of the port on the front panel of its system.
+    pub external_number: Option<u32>,
 }
 
 impl Default for Port {
     fn default() -> Self {
         Self {
-            guid: "".to_string(),
+            guid: Guid::default(),
             name: None,
             system_id: "".to_string(),
-            lid: 65535,
+            lid: None,
             system_name: "".to_string(),
-            logical_state: "Unknown".to_string(),
+            logical_state: None,
             parent_guid: None,
             port_type: None,
+            physical_state: None,
+            active_speed: None,
+            active_width: None,
+            mtu: None,
+            peer_guid: None,
+            peer_node_name: None,
+            peer_port: None,
+            firmware_version: None,
+            external_number: None,
         }
     }
 }
 
+impl Port {
+    /// The data rate of the link in Gb/s, i.e. the speed of a lane by the number of lanes.
+    pub fn rate(&self) -> Option<f64> {
+        let lane = match self.active_speed.as_deref()?.to_uppercase().as_str() {
+            "SDR" => 2.5,
+            "DDR" => 5.0,
+            "QDR" | "FDR10" => 10.0,
+            "FDR" => 14.0,
+            "EDR" => 25.0,
+            "HDR" => 50.0,
+            "NDR" => 100.0,
+            "XDR" => 200.0,
+            _ => return None,
+        };
+        let lanes: f64 = self
+            .active_width
+            .as_deref()?
+            .trim_end_matches(['x', 'X'])
+            .parse()
+            .ok()?;
+
+        Some(lane * lanes)
+    }
+}
+
+/// The filter of `Ufm::list_ports`; a port matches if it matches all the conditions which are set.
+#[derive(Debug, Clone, Default)]
+pub struct PortFilter {
+    /// The ID or the name of the system of the ports.
+    pub system: Option<String>,
+    pub sys_type: Option<SystemType>,
+    /// Whether the logical state of the ports is `Active`.
+    pub active: Option<bool>,
+    /// The active speed of the ports, e.g. `HDR`.
+    pub speed: Option<String>,
+    /// The active width of the ports, e.g. `4x`.
+    pub width: Option<String>,
+    pub guids: Option<HashSet<Guid>>,
+}
+
+impl PortFilter {
+    pub fn matches(&self, port: &Port) -> bool {
+        let eq = |expected: &Option<String>, actual: &Option<String>| match (expected, actual) {
+            (None, _) => true,
+            (Some(e), Some(a)) => e.eq_ignore_ascii_case(a),
+            (Some(_), None) => false,
+        };
+
+        let system = match &self.system {
+            None => true,
+            Some(s) => port.system_id.eq_ignore_ascii_case(s) || port.system_name == *s,
+        };
+        let active = match self.active {
+            None => true,
+            Some(a) => (port.logical_state == Some(LogicalState::Active)) == a,
+        };
+        let guids = match &self.guids {
+            None => true,
+            Some(guids) => guids.contains(&port.guid),
+        };
+
+        system
+            && active
+            && guids
+            && eq(&self.speed, &port.active_speed)
+            && eq(&self.width, &port.active_width)
+    }
+}
+
 impl From<PhysicalPort> for Port {
     fn from(physicalport: PhysicalPort) -> Self {
         Port {
-            guid: physicalport.guid.clone(),
+            guid: physicalport.guid,
             name: Some(physicalport.name),
             system_id: physicalport.system_id,
             lid: physicalport.lid,
@@ -46,6 +366,15 @@ impl From<PhysicalPort> for Port {
             logical_state: physicalport.logical_state,
             parent_guid: None,
             port_type: Some(PortType::Physical),
+            physical_state: physicalport.physical_state,
+            active_speed: physicalport.active_speed,
+            active_width: physicalport.active_width,
+            mtu: physicalport.mtu,
+            peer_guid: physicalport.peer_guid,
+            peer_node_name: physicalport.peer_node_name,
+            peer_port: physicalport.peer_port_dname,
+            firmware_version: physicalport.firmware_version,
+            external_number: physicalport.external_number,
         }
     }
 }
@@ -53,7 +382,7 @@ impl From<PhysicalPort> for Port {
 impl From<VirtualPort> for Port {
     fn from(virtualport: VirtualPort) -> Self {
         Port {
-            guid: virtualport.virtual_port_guid.clone(),
+            guid: virtualport.virtual_port_guid,
             name: None,
             system_id: virtualport.system_guid,
             lid: virtualport.virtual_port_lid,
@@ -61,32 +390,166 @@ impl From<VirtualPort> for Port {
             logical_state: virtualport.virtual_port_state,
             parent_guid: Some(virtualport.port_guid),
             port_type: Some(PortType::Virtual),
+            ..Port::default()
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PhysicalPort {
-    pub guid: String,
+    pub guid: Guid,
     pub name: String,
     #[serde(rename = "systemID")]
     pub system_id: String,
-    pub lid: i32,
+    #[serde(deserialize_with = "unassigned_as_none")]
+    pub lid: Option<Lid>,
     pub system_name: String,
-    pub logical_state: String,
+    #[serde(deserialize_with = "unknown_as_none")]
+    pub logical_state: Option<LogicalState>,
+    #[serde(default, deserialize_with = "unknown_as_none")]
+    pub physical_state: Option<PhysicalState>,
+    #[serde(default)]
+    pub active_speed: Option<String>,
+    #[serde(default)]
+    pub active_width: Option<String>,
+    #[serde(default)]
+    pub mtu: Option<u32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub peer_guid: Option<Guid>,
+    #[serde(default)]
+    pub peer_node_name: Option<String>,
+    #[serde(default)]
+    pub peer_port_dname: Option<String>,
+    #[serde(default, alias = "fw_version")]
+    pub firmware_version: Option<String>,
+    #[serde(default)]
+    pub external_number: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct VirtualPort {
-    pub virtual_port_guid: String,
+    pub virtual_port_guid: Guid,
     pub system_guid: String,
-    pub virtual_port_lid: i32,
+    #[serde(deserialize_with = "unassigned_as_none")]
+    pub virtual_port_lid: Option<Lid>,
+    pub system_name: String,
+    #[serde(deserialize_with = "unknown_as_none")]
+    pub virtual_port_state: Option<LogicalState>,
+    pub port_guid: Guid,
+}
+
+/// A system in the fabric, e.g. a switch or a host.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct System {
+    #[serde(rename = "system_guid")]
+    pub guid: Guid,
     pub system_name: String,
-    pub virtual_port_state: String,
-    pub port_guid: String,
+    /// The type reported by UFM, e.g. `switch`, `host` or `gateway`; see `sys_type`.
+    #[serde(rename = "type")]
+    pub system_type: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub vendor: Option<String>,
+    #[serde(default, alias = "fw_version")]
+    pub firmware_version: Option<String>,
+    #[serde(default)]
+    pub ip: Option<String>,
+    /// The state of the system, e.g. `active`.
+    #[serde(default)]
+    pub state: Option<String>,
+    /// The names of the ports of the system.
+    #[serde(default)]
+    pub ports: Vec<String>,
+}
+
+impl System {
+    /// The type of the system, if it's one of the known types.
+    pub fn sys_type(&self) -> Option<SystemType> {
+        self.system_type.parse().ok()
+    }
 }
 
+/// An end of a link, i.e. a port of a system.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LinkEnd {
+    /// The GUID of the system of the port.
+    pub system_guid: Guid,
+    pub port_number: u32,
+    /// The GUID of the port, which is the system GUID of a switch but not of a host.
+    pub port_guid: Guid,
+    /// The name of the port, e.g. `b83fd203002a1f3a_1`.
+    pub port_name: Option<String>,
+    /// The description of the node, e.g. `hpc-cloud01 mlx5_0`.
+    pub node_description: Option<String>,
+}
+
+/// A link between two ports in the fabric.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Link {
+    pub source: LinkEnd,
+    pub destination: LinkEnd,
+    /// The speed of the link, e.g. `HDR`.
+    pub speed: Option<String>,
+    /// The width of the link, e.g. `4x`.
+    pub width: Option<String>,
+    /// The state of the link, e.g. `Active`.
+    pub state: Option<String>,
+}
+
+impl From<UfmLink> for Link {
+    fn from(link: UfmLink) -> Self {
+        Link {
+            source: LinkEnd {
+                system_guid: link.source_guid,
+                port_number: link.source_port,
+                port_guid: link.source_port_guid,
+                port_name: link.source_port_dname,
+                node_description: link.source_port_node_description,
+            },
+            destination: LinkEnd {
+                system_guid: link.destination_guid,
+                port_number: link.destination_port,
+                port_guid: link.destination_port_guid,
+                port_name: link.destination_port_dname,
+                node_description: link.destination_port_node_description,
+            },
+            speed: link.speed,
+            width: link.width,
+            state: link.state,
+        }
+    }
+}
+
+/// A link in the shape of `/resources/links`.
 #[derive(Serialize, Deserialize, Debug)]
+pub struct UfmLink {
+    pub source_guid: Guid,
+    pub source_port: u32,
+    pub source_port_guid: Guid,
+    #[serde(default)]
+    pub source_port_dname: Option<String>,
+    #[serde(default)]
+    pub source_port_node_description: Option<String>,
+    pub destination_guid: Guid,
+    pub destination_port: u32,
+    pub destination_port_guid: Guid,
+    #[serde(default)]
+    pub destination_port_dname: Option<String>,
+    #[serde(default)]
+    pub destination_port_node_description: Option<String>,
+    #[serde(default, alias = "active_speed")]
+    pub speed: Option<String>,
+    #[serde(default, alias = "active_width")]
+    pub width: Option<String>,
+    #[serde(default, alias = "logical_state")]
+    pub state: Option<String>,
+}
+
+/// The configuration of the SM of UFM.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Configuration {
     pub subnet_prefix: String,
     pub m_key: String,
@@ -95,4 +558,95 @@ pub struct Configuration {
     pub sa_key: String,
     pub log_file: String,
     pub qos: i32,
+    /// The other parameters of the SM by their keys, e.g. `sm_priority`.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+impl Configuration {
+    /// The keys of the parameters which are masked by `masked`.
+    pub const SECRETS: [&'static str; 3] = ["m_key", "sm_key", "sa_key"];
+
+    /// The configuration with the keys of the SM replaced by `********`, e.g. to print it.
+    pub fn masked(&self) -> Configuration {
+        let mask = "********".to_string();
+        Configuration {
+            m_key: mask.clone(),
+            sm_key: mask.clone(),
+            sa_key: mask,
+            ..self.clone()
+        }
+    }
+
+    /// All the parameters by their keys, including the fields.
+    pub fn params(&self) -> BTreeMap<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(params)) => params.into_iter().collect(),
+            _ => BTreeMap::new(),
+        }
+    }
+
+    /// Parse the value of the parameter as the type of its current value, e.g. `1` as a number
+    /// for `qos`; the parameters which are not in the configuration can not be set.
+    pub fn parse_param(&self, key: &str, value: &str) -> Result<Value, UFMError> {
+        let invalid =
+            || UFMError::InvalidConfig(format!("invalid value of SM parameter {}: {}", key, value));
+
+        match self.params().get(key) {
+            None => Err(UFMError::InvalidConfig(format!(
+                "unknown SM parameter: {}",
+                key
+            ))),
+            Some(Value::Bool(_)) => value.parse().map(Value::Bool).map_err(|_| invalid()),
+            Some(Value::Number(_)) => match serde_json::from_str(value) {
+                Ok(Value::Number(n)) => Ok(Value::Number(n)),
+                _ => Err(invalid()),
+            },
+            Some(Value::String(_)) => Ok(Value::String(value.to_string())),
+            Some(_) => Err(UFMError::InvalidConfig(format!(
+                "SM parameter {} can not be set",
+                key
+            ))),
+        }
+    }
+
+    /// Check that the parameters are in the configuration, and their values are of the types
+    /// of the current ones.
+    pub(crate) fn check_params(&self, params: &BTreeMap<String, Value>) -> Result<(), UFMError> {
+        let current = self.params();
+        for (key, value) in params {
+            let settable = match (current.get(key), value) {
+                (None, _) => {
+                    return Err(UFMError::InvalidConfig(format!(
+                        "unknown SM parameter: {}",
+                        key
+                    )))
+                }
+                (Some(Value::Bool(_)), Value::Bool(_))
+                | (Some(Value::Number(_)), Value::Number(_))
+                | (Some(Value::String(_)), Value::String(_)) => true,
+                _ => false,
+            };
+            if !settable {
+                return Err(UFMError::InvalidConfig(format!(
+                    "invalid value of SM parameter {}: {}",
+                    key, value
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// UFM replies an empty string for the GUID of a port without a peer.
+fn empty_as_none<'de, D>(deserializer: D) -> Result<Option<Guid>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(s) if s.is_empty() => Ok(None),
+        Some(s) => s.parse().map(Some).map_err(serde::de::Error::custom),
+    }
 }
@@ -0,0 +1,218 @@
+use libufm::{Profile, ProfileConfig, TlsMode, UFMCert, UFMConfig, UFMError};
+
+const PROFILES_TOML: &str = r#"
+default = "lab"
+
+[profiles.lab]
+address = "https://ufm-lab"
+token = "XlojlA7zgotVegyIEIP5vnw5C7ZYT9"
+
+[profiles.staging]
+address = "https://10.0.0.2"
+username = "admin"
+password = "123456"
+ca_crt = "/etc/ufm/staging-ca.crt"
+server_name = "ufm-staging.example.com"
+
+[profiles.prod]
+address = "https://ufm"
+ca_crt = "/etc/ufm/ca.crt"
+tls_key = "/etc/ufm/client.key"
+tls_crt = "/etc/ufm/client.crt"
+"#;
+
+const PROFILES_YAML: &str = r#"
+default: lab
+profiles:
+  lab:
+    address: https://ufm-lab
+    token: XlojlA7zgotVegyIEIP5vnw5C7ZYT9
+  staging:
+    address: https://10.0.0.2
+    username: admin
+    password: "123456"
+    ca_crt: /etc/ufm/staging-ca.crt
+    server_name: ufm-staging.example.com
+  prod:
+    address: https://ufm
+    ca_crt: /etc/ufm/ca.crt
+    tls_key: /etc/ufm/client.key
+    tls_crt: /etc/ufm/client.crt
+"#;
+
+fn assert_invalid_config<T>(res: Result<T, UFMError>, msg: &str) {
+    match res {
+        Err(UFMError::InvalidConfig(m)) => assert!(m.contains(msg), "{m}"),
+        Err(e) => panic!("unexpected error: {e:?}"),
+        Ok(_) => panic!("expected an invalid configuration"),
+    }
+}
+
+#[test]
+fn test_builder() {
+    let conf = UFMConfig::builder()
+        .address("https://ufm")
+        .basic_auth("admin", "123456")
+        .server_name("ufm.example.com")
+        .build()
+        .unwrap();
+
+    assert_eq!(conf.address, "https://ufm");
+    assert_eq!(conf.username.as_deref(), Some("admin"));
+    assert_eq!(conf.password.as_deref(), Some("123456"));
+    assert_eq!(conf.token, None);
+    assert_eq!(conf.tls.server_name.as_deref(), Some("ufm.example.com"));
+    assert!(matches!(conf.tls.mode, TlsMode::WebPkiRoots));
+}
+
+#[test]
+fn test_builder_validation() {
+    assert_invalid_config(
+        UFMConfig::builder().token("t").build(),
+        "UFM address is empty",
+    );
+    assert_invalid_config(
+        UFMConfig::builder().address("ufm").token("t").build(),
+        "invalid UFM url",
+    );
+    assert_invalid_config(
+        UFMConfig::builder().address("ftp://ufm").token("t").build(),
+        "invalid UFM url",
+    );
+    assert_invalid_config(
+        UFMConfig::builder().address("https://ufm").build(),
+        "no authentication",
+    );
+    assert_invalid_config(
+        UFMConfig::builder()
+            .address("https://ufm")
+            .token("t")
+            .basic_auth("admin", "123456")
+            .build(),
+        "only one of",
+    );
+    assert_invalid_config(
+        UFMConfig::builder()
+            .address("https://ufm")
+            .token("t")
+            .cert(UFMCert {
+                ca_crt: "ca.crt".to_string(),
+                tls_key: "tls.key".to_string(),
+                tls_crt: "tls.crt".to_string(),
+            })
+            .build(),
+        "only one of",
+    );
+
+    let mut builder = UFMConfig::builder().address("https://ufm").token("t");
+    builder = builder.session_auth("admin", "123456");
+    assert_invalid_config(builder.build(), "only one of");
+
+    let conf = UFMConfig::builder()
+        .address("https://ufm")
+        .session_auth("admin", "123456")
+        .build()
+        .unwrap();
+    assert!(conf.session);
+}
+
+fn assert_profiles(conf: &ProfileConfig) {
+    assert_eq!(conf.default.as_deref(), Some("lab"));
+    assert_eq!(conf.profiles.len(), 3);
+
+    let lab = conf.default_profile().unwrap().unwrap().builder().unwrap();
+    let lab = lab.build().unwrap();
+    assert_eq!(lab.address, "https://ufm-lab");
+    assert_eq!(lab.token.as_deref(), Some("XlojlA7zgotVegyIEIP5vnw5C7ZYT9"));
+    assert!(matches!(lab.tls.mode, TlsMode::WebPkiRoots));
+
+    let staging = conf.get("staging").unwrap().builder().unwrap();
+    let staging = staging.build().unwrap();
+    assert_eq!(staging.username.as_deref(), Some("admin"));
+    assert_eq!(staging.password.as_deref(), Some("123456"));
+    assert!(staging.cert.is_none());
+    assert!(matches!(&staging.tls.mode, TlsMode::CustomCa(ca) if ca == "/etc/ufm/staging-ca.crt"));
+    assert_eq!(
+        staging.tls.server_name.as_deref(),
+        Some("ufm-staging.example.com")
+    );
+
+    let prod = conf.get("prod").unwrap().builder().unwrap();
+    let prod = prod.build().unwrap();
+    let cert = prod.cert.unwrap();
+    assert_eq!(cert.ca_crt, "/etc/ufm/ca.crt");
+    assert_eq!(cert.tls_key, "/etc/ufm/client.key");
+    assert_eq!(cert.tls_crt, "/etc/ufm/client.crt");
+    assert!(matches!(prod.tls.mode, TlsMode::WebPkiRoots));
+
+    assert_invalid_config(conf.get("dev"), "profile 'dev' not found");
+}
+
+#[test]
+fn test_profile_config() {
+    let toml = ProfileConfig::from_toml(PROFILES_TOML).unwrap();
+    assert_profiles(&toml);
+
+    let yaml = ProfileConfig::from_yaml(PROFILES_YAML).unwrap();
+    assert_eq!(toml, yaml);
+}
+
+#[test]
+fn test_profile_config_load() {
+    let dir = std::env::temp_dir().join(format!("libufm-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("config.toml");
+    std::fs::write(&path, PROFILES_TOML).unwrap();
+    assert_profiles(&ProfileConfig::load(&path).unwrap());
+
+    let path = dir.join("config.yaml");
+    std::fs::write(&path, PROFILES_YAML).unwrap();
+    assert_profiles(&ProfileConfig::load(&path).unwrap());
+
+    // The YAML is not a valid TOML.
+    let path = dir.join("config.conf");
+    std::fs::write(&path, PROFILES_YAML).unwrap();
+    assert_invalid_config(ProfileConfig::load(&path), "config.conf");
+
+    assert_invalid_config(
+        ProfileConfig::load(dir.join("nonexistent.toml")),
+        "failed to read",
+    );
+}
+
+#[test]
+fn test_profile_validation() {
+    assert_invalid_config(
+        ProfileConfig::from_toml("[profiles.lab]\naddress = \"https://ufm\"\nport = 443\n"),
+        "unknown field",
+    );
+
+    let conf = ProfileConfig::from_toml("default = \"dev\"\n").unwrap();
+    assert_invalid_config(conf.default_profile(), "profile 'dev' not found");
+
+    // The client certificate needs all of the CA, key and certificate.
+    let profile = Profile {
+        address: Some("https://ufm".to_string()),
+        tls_crt: Some("/etc/ufm/client.crt".to_string()),
+        ..Default::default()
+    };
+    assert_invalid_config(profile.builder(), "ca_crt, tls_key and tls_crt");
+
+    let profile = Profile {
+        address: Some("https://ufm".to_string()),
+        username: Some("admin".to_string()),
+        ..Default::default()
+    };
+    assert_invalid_config(profile.builder().unwrap().build(), "password is empty");
+
+    let profile = Profile {
+        address: Some("https://ufm".to_string()),
+        token: Some("t".to_string()),
+        ca_crt: Some("/etc/ufm/ca.crt".to_string()),
+        insecure_skip_verify: true,
+        ..Default::default()
+    };
+    let conf = profile.builder().unwrap().build().unwrap();
+    assert!(matches!(conf.tls.mode, TlsMode::InsecureSkipVerify));
+}
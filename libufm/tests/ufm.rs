@@ -0,0 +1,2062 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use hyper::{Method, StatusCode};
+use serde_json::json;
+
+use libufm::{
+    BatchOp, Counter, CounterSample, EventFilter, Guid, IbMtu, IbRate, Lid, LogicalState,
+    Partition, PartitionChange, PartitionKey, PartitionQoS, PartitionSnapshot, PartitionSpec,
+    PhysicalState, Plan, PoolConfig, Port, PortConfig, PortCounters, PortFilter, PortMembership,
+    PortType, RetryPolicy, Severity, SystemType, TimeoutConfig, TlsConfig, TlsMode, UFMCert,
+    UFMConfig, UFMError,
+};
+use ufmmock::{MockMember, MockPartition, MockUfm};
+
+const GUID_PF: &str = "1070fd0300176625";
+const GUID_VF: &str = "0011223344560200";
+const GUID_UNKNOWN: &str = "0011223344560201";
+
+fn config(mock: &MockUfm) -> UFMConfig {
+    UFMConfig {
+        address: mock.address(),
+        username: Some("admin".to_string()),
+        password: Some("123456".to_string()),
+        token: None,
+        cert: None,
+        retry: fast_retry(),
+        ..Default::default()
+    }
+}
+
+/// Retry without waiting too long in tests.
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy {
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        ..RetryPolicy::default()
+    }
+}
+
+/// Write `content` into a file under the temporary directory, and return its path.
+fn temp_file(name: &str, content: &str) -> String {
+    let path = std::env::temp_dir().join(format!("libufm-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+fn guid(s: &str) -> Guid {
+    Guid::try_from(s).unwrap()
+}
+
+fn partition(pkey: &str) -> Partition {
+    Partition {
+        name: "".to_string(),
+        pkey: PartitionKey::try_from(pkey).unwrap(),
+        ipoib: true,
+        qos: PartitionQoS {
+            mtu_limit: IbMtu::Mtu2048,
+            service_level: 3,
+            rate_limit: IbRate::Rate100,
+        },
+    }
+}
+
+fn member(membership: &str, index0: bool) -> MockMember {
+    MockMember {
+        membership: membership.to_string(),
+        index0,
+    }
+}
+
+async fn start() -> MockUfm {
+    let mock = MockUfm::start().await.unwrap();
+
+    mock.add_port(
+        "Computer",
+        json!({
+            "guid": GUID_PF,
+            "name": "1070fd0300176625_2",
+            "systemID": "1070fd0300176624",
+            "lid": 4,
+            "system_name": "hpc-cloud01",
+            "logical_state": "Active",
+        }),
+    );
+    mock.add_port(
+        "Switch",
+        json!({
+            "guid": "b83fd203002a1f3a",
+            "name": "b83fd203002a1f3a_1",
+            "systemID": "b83fd203002a1f3a",
+            "lid": 1,
+            "system_name": "switch01",
+            "logical_state": "Active",
+        }),
+    );
+    mock.add_vport(json!({
+        "virtual_port_guid": GUID_VF,
+        "system_guid": "1070fd0300176624",
+        "virtual_port_lid": 7,
+        "system_name": "hpc-cloud01",
+        "virtual_port_state": "Active",
+        "port_guid": GUID_PF,
+    }));
+
+    let mut p = MockPartition::new("api_pkey_0x5");
+    p.members.insert(GUID_PF.to_string(), member("full", true));
+    p.members
+        .insert(GUID_UNKNOWN.to_string(), member("limited", false));
+    mock.add_partition(0x5, p);
+
+    mock
+}
+
+#[tokio::test]
+async fn test_version() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::GET);
+    assert_eq!(req.path, "/app/ufm_version");
+    assert_eq!(req.authorization.unwrap(), "Basic YWRtaW46MTIzNDU2");
+}
+
+#[tokio::test]
+async fn test_get_configuration() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let conf = ufm.get_configuration().await.unwrap();
+    assert_eq!(conf.subnet_prefix, "0xfe80000000000000");
+    assert_eq!(conf.sm_key, "0x0000000000000001");
+    assert_eq!(conf.log_file, "/var/log/opensm.log");
+    assert!(!conf.m_key_per_port);
+    assert_eq!(conf.qos, 0);
+    assert_eq!(conf.other["sweep_interval"], json!(10));
+    assert!(!conf.other.contains_key("qos"));
+
+    let masked = conf.masked();
+    assert_eq!(masked.sm_key, "********");
+    assert_eq!(masked.sa_key, "********");
+    assert_eq!(masked.m_key, "********");
+    assert_eq!(masked.subnet_prefix, conf.subnet_prefix);
+}
+
+#[tokio::test]
+async fn test_update_configuration() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let conf = ufm.get_configuration().await.unwrap();
+    assert_eq!(conf.parse_param("qos", "1").unwrap(), json!(1));
+    assert_eq!(
+        conf.parse_param("m_key_per_port", "true").unwrap(),
+        json!(true)
+    );
+    assert_eq!(
+        conf.parse_param("log_file", "/tmp/sm.log").unwrap(),
+        json!("/tmp/sm.log")
+    );
+    assert!(matches!(
+        conf.parse_param("qos", "on"),
+        Err(UFMError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        conf.parse_param("no_such_key", "1"),
+        Err(UFMError::InvalidConfig(_))
+    ));
+
+    let params = BTreeMap::from([
+        ("sweep_interval".to_string(), json!(30)),
+        ("m_key_per_port".to_string(), json!(true)),
+    ]);
+    ufm.update_configuration(&params).await.unwrap();
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::PUT);
+    assert_eq!(req.path, "/app/smconf");
+
+    let conf = ufm.get_configuration().await.unwrap();
+    assert_eq!(conf.other["sweep_interval"], json!(30));
+    assert!(conf.m_key_per_port);
+
+    // The parameters are checked before sending.
+    let params = BTreeMap::from([("sweep_interval".to_string(), json!("fast"))]);
+    assert!(matches!(
+        ufm.update_configuration(&params).await,
+        Err(UFMError::InvalidConfig(_))
+    ));
+    assert_eq!(mock.last_request().unwrap().method, Method::GET);
+}
+
+#[tokio::test]
+async fn test_list_partition() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let mut ps = ufm.list_partition().await.unwrap();
+    ps.sort_by_key(|p| u16::from(p.pkey));
+
+    assert_eq!(ps.len(), 2);
+    assert_eq!(ps[0].name, "api_pkey_0x5");
+    assert_eq!(ps[0].pkey.to_string(), "0x5");
+    assert_eq!(ps[1].name, "management");
+    assert!(ps[1].pkey.is_default());
+    assert!(ps[1].ipoib);
+}
+
+#[tokio::test]
+async fn test_get_partition() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let p = ufm.get_partition("0x5").await.unwrap();
+    assert_eq!(p.name, "api_pkey_0x5");
+    assert_eq!(p.pkey.to_string(), "0x5");
+    assert!(!p.ipoib);
+    assert_eq!(p.qos.mtu_limit, IbMtu::Mtu2048);
+    assert_eq!(p.qos.rate_limit, IbRate::Rate2_5);
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.path, "/resources/pkeys/0x5");
+    assert_eq!(req.query.unwrap(), "qos_conf=true");
+
+    let err = ufm.get_partition("0x6").await.unwrap_err();
+    assert!(matches!(err, UFMError::NotFound(_)), "{err:?}");
+
+    let err = ufm.get_partition("pkey").await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidPKey(_)), "{err:?}");
+
+    let err = ufm.get_partition("0xffff").await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidPKey(_)), "{err:?}");
+}
+
+#[test]
+fn test_partition_key() {
+    let k: PartitionKey = "0x7FFF".parse().unwrap();
+    assert!(k.is_default());
+    assert_eq!(k, PartitionKey::try_from(0x7fff).unwrap());
+    assert_eq!(PartitionKey::try_from("5").unwrap().to_string(), "0x5");
+    assert!(PartitionKey::try_from("0x5").unwrap() < k);
+
+    for invalid in ["0x0", "0", "0x8000", "0xffff", "65536", "0x", "-1", "pkey"] {
+        let err = invalid.parse::<PartitionKey>().unwrap_err();
+        assert!(
+            matches!(err, UFMError::InvalidPKey(_)),
+            "{invalid}: {err:?}"
+        );
+    }
+    assert!(PartitionKey::try_from(0x8005).is_err());
+    assert!(PartitionKey::try_from(0).is_err());
+
+    let (k, m) = PartitionKey::from_entry(0x8005).unwrap();
+    assert_eq!(k.to_string(), "0x5");
+    assert_eq!(m, PortMembership::Full);
+    assert_eq!(k.to_entry(&m), 0x8005);
+    let (k, m) = PartitionKey::from_entry(0x0005).unwrap();
+    assert_eq!(m, PortMembership::Limited);
+    assert_eq!(k.to_entry(&m), 0x0005);
+    assert!(PartitionKey::is_invalid_entry(0x0000));
+    assert!(PartitionKey::is_invalid_entry(0x8000));
+    assert!(PartitionKey::from_entry(0x8000).is_none());
+
+    assert_eq!(serde_json::to_value(k).unwrap(), json!("0x5"));
+    let k: PartitionKey = serde_json::from_value(json!("0x5")).unwrap();
+    assert_eq!(k, serde_json::from_value(json!(5)).unwrap());
+    assert!(serde_json::from_value::<PartitionKey>(json!("0xffff")).is_err());
+    assert!(serde_json::from_value::<PartitionKey>(json!(0x8000)).is_err());
+}
+
+#[tokio::test]
+async fn test_add_partition() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    ufm.add_partition(partition("0x6")).await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::POST);
+    assert_eq!(req.path, "/resources/pkeys/add");
+    assert_eq!(
+        req.body,
+        json!({
+            "pkey": "0x6",
+            "ip_over_ib": true,
+            "membership": "full",
+            "index0": true,
+            "mtu_limit": 2,
+            "service_level": 3,
+            "rate_limit": 100.0,
+        })
+    );
+
+    let p = mock.partition(0x6).unwrap();
+    assert_eq!(p.name, "api_pkey_0x6");
+    assert!(p.ip_over_ib);
+    assert_eq!(p.mtu_limit, 2);
+    assert!(p.members.is_empty());
+}
+
+#[tokio::test]
+async fn test_set_partition() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let ports = vec![
+        PortConfig {
+            guid: guid("0011:2233:4456:0300"),
+            index0: false,
+            membership: PortMembership::Limited,
+        },
+        PortConfig {
+            guid: guid(GUID_PF),
+            index0: false,
+            membership: PortMembership::Limited,
+        },
+    ];
+    ufm.set_partition(partition("0x5"), ports).await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::PUT);
+    assert_eq!(req.path, "/resources/pkeys");
+    assert_eq!(
+        req.body,
+        json!({
+            "pkey": "0x5",
+            "ip_over_ib": true,
+            "membership": "limited",
+            "index0": false,
+            "guids": ["0011223344560300", GUID_PF],
+            "mtu_limit": 2,
+            "service_level": 3,
+            "rate_limit": 100.0,
+        })
+    );
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.members.len(), 2);
+    assert_eq!(p.members[GUID_PF], member("limited", false));
+    assert_eq!(p.service_level, 3);
+}
+
+#[tokio::test]
+async fn test_set_partition_mixed_membership() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let port = |g: &str, membership, index0| PortConfig {
+        guid: guid(g),
+        index0,
+        membership,
+    };
+    let ports = vec![
+        port("0011223344560300", PortMembership::Full, true),
+        port(GUID_PF, PortMembership::Limited, false),
+        port("0011223344560301", PortMembership::Full, true),
+    ];
+    mock.clear_requests();
+    ufm.set_partition(partition("0x6"), ports).await.unwrap();
+
+    let reqs = mock.requests();
+    assert_eq!(reqs.len(), 2);
+    assert_eq!(reqs[0].method, Method::PUT);
+    assert_eq!(
+        reqs[0].body["guids"],
+        json!(["0011223344560300", "0011223344560301"])
+    );
+    assert_eq!(reqs[1].method, Method::POST);
+    assert_eq!(reqs[1].body["guids"], json!([GUID_PF]));
+    assert_eq!(reqs[1].body["membership"], "limited");
+
+    let p = mock.partition(0x6).unwrap();
+    assert_eq!(p.members.len(), 3);
+    assert_eq!(p.members["0011223344560300"], member("full", true));
+    assert_eq!(p.members["0011223344560301"], member("full", true));
+    assert_eq!(p.members[GUID_PF], member("limited", false));
+}
+
+#[tokio::test]
+async fn test_set_partition_partially() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+    mock.fail_requests(Method::POST, "/resources/pkeys", StatusCode::BAD_REQUEST);
+
+    let ports = vec![
+        PortConfig {
+            guid: guid("0011223344560300"),
+            index0: true,
+            membership: PortMembership::Full,
+        },
+        PortConfig {
+            guid: guid(GUID_PF),
+            index0: false,
+            membership: PortMembership::Limited,
+        },
+    ];
+    let err = ufm
+        .set_partition(partition("0x6"), ports)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("failed to add the limited ports with index0=false (1070fd0300176625)"),
+        "{err}"
+    );
+
+    // The partition is left with the first group of ports.
+    let p = mock.partition(0x6).unwrap();
+    assert_eq!(p.members.len(), 1);
+    assert_eq!(p.members["0011223344560300"], member("full", true));
+}
+
+#[tokio::test]
+async fn test_update_partition_qos() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    ufm.update_partition_qos(partition("0x5")).await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::PUT);
+    assert_eq!(req.path, "/resources/pkeys/qos_conf");
+    assert_eq!(
+        req.body,
+        json!({
+            "pkey": "0x5",
+            "mtu_limit": 2,
+            "service_level": 3,
+            "rate_limit": 100.0,
+        })
+    );
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.mtu_limit, 2);
+    assert_eq!(p.service_level, 3);
+    assert_eq!(p.rate_limit, 100.0);
+    assert_eq!(p.members.len(), 2);
+
+    assert!(ufm.update_partition_qos(partition("0x6")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_invalid_partition_qos() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let mut p = partition("0x6");
+    p.qos.service_level = 16;
+    let err = ufm.add_partition(p.clone()).await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidQoS(_)), "{err:?}");
+    let err = ufm.set_partition(p.clone(), vec![]).await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidQoS(_)), "{err:?}");
+    let err = ufm.update_partition_qos(p).await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidQoS(_)), "{err:?}");
+
+    // The invalid QoS is never sent to UFM.
+    assert!(mock.last_request().is_none());
+    assert!(mock.partition(0x6).is_none());
+}
+
+#[test]
+fn test_partition_qos() {
+    assert_eq!("4096".parse::<IbMtu>().unwrap(), IbMtu::Mtu4096);
+    assert_eq!("2048".parse::<IbMtu>().unwrap(), IbMtu::Mtu2048);
+    assert_eq!("4".parse::<IbMtu>().unwrap(), IbMtu::Mtu4096);
+    assert_eq!(IbMtu::Mtu2048.kb(), 2);
+    assert_eq!(IbMtu::Mtu4096.bytes(), 4096);
+    assert_eq!(IbMtu::Mtu4096.to_string(), "4096");
+    assert!(matches!(
+        "1024".parse::<IbMtu>(),
+        Err(UFMError::InvalidQoS(_))
+    ));
+    assert!("0".parse::<IbMtu>().is_err());
+
+    assert_eq!("2.5".parse::<IbRate>().unwrap(), IbRate::Rate2_5);
+    assert_eq!("100".parse::<IbRate>().unwrap(), IbRate::Rate100);
+    assert_eq!(IbRate::Rate2_5.to_string(), "2.5");
+    assert_eq!(IbRate::Rate300.gbps(), 300.0);
+    assert!(matches!(
+        "50".parse::<IbRate>(),
+        Err(UFMError::InvalidQoS(_))
+    ));
+
+    let qos = PartitionQoS::default();
+    assert_eq!(
+        serde_json::to_value(&qos).unwrap(),
+        json!({"mtu_limit": 2048, "service_level": 0, "rate_limit": 2.5})
+    );
+    let qos: PartitionQoS =
+        serde_json::from_value(json!({"mtu_limit": 4, "service_level": 15, "rate_limit": 200}))
+            .unwrap();
+    assert_eq!(qos.mtu_limit, IbMtu::Mtu4096);
+    assert_eq!(qos.rate_limit, IbRate::Rate200);
+    assert!(qos.validate().is_ok());
+    let qos: PartitionQoS =
+        serde_json::from_value(json!({"mtu_limit": 2048, "service_level": 0, "rate_limit": 2.5}))
+            .unwrap();
+    assert_eq!(qos.mtu_limit, IbMtu::Mtu2048);
+    assert!(serde_json::from_value::<PartitionQoS>(
+        json!({"mtu_limit": 1024, "service_level": 0, "rate_limit": 2.5})
+    )
+    .is_err());
+}
+
+#[test]
+fn test_port_state() {
+    assert_eq!(
+        "ACTIVE".parse::<LogicalState>().unwrap(),
+        LogicalState::Active
+    );
+    assert_eq!("init".parse::<LogicalState>().unwrap(), LogicalState::Init);
+    assert_eq!(LogicalState::Armed.to_string(), "Armed");
+    assert!("Unknown".parse::<LogicalState>().is_err());
+
+    assert_eq!(
+        "Link Up".parse::<PhysicalState>().unwrap(),
+        PhysicalState::LinkUp
+    );
+    assert_eq!(
+        "port_configuration_training"
+            .parse::<PhysicalState>()
+            .unwrap(),
+        PhysicalState::PortConfigurationTraining
+    );
+    assert_eq!(
+        PhysicalState::LinkErrorRecovery.to_string(),
+        "LinkErrorRecovery"
+    );
+    assert!("Up".parse::<PhysicalState>().is_err());
+
+    assert!(Lid::new(0).is_none());
+    assert!(Lid::new(0xffff).is_none());
+    assert!(Lid::new(-1).is_none());
+    assert_eq!(Lid::new(4).map(u16::from), Some(4));
+    assert_eq!(serde_json::to_value(Lid::new(4)).unwrap(), json!(4));
+    assert!(serde_json::from_value::<Lid>(json!(65535)).is_err());
+
+    assert_eq!(
+        serde_json::to_value(LogicalState::Active).unwrap(),
+        json!("Active")
+    );
+    assert_eq!(
+        serde_json::from_value::<PhysicalState>(json!("LinkUp")).unwrap(),
+        PhysicalState::LinkUp
+    );
+}
+
+#[tokio::test]
+async fn test_unassigned_lid() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    mock.update_port(GUID_PF, json!({"lid": 0, "logical_state": "N/A"}));
+    let ports = ufm.list_ports(&PortFilter::default()).await.unwrap();
+    let port = ports.iter().find(|p| p.guid == guid(GUID_PF)).unwrap();
+    assert!(port.lid.is_none());
+    assert!(port.logical_state.is_none());
+}
+
+#[tokio::test]
+async fn test_bind_ports() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let ports = vec![PortConfig {
+        guid: guid(GUID_VF),
+        index0: true,
+        membership: PortMembership::Full,
+    }];
+    ufm.bind_ports(partition("0x5"), ports).await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::POST);
+    assert_eq!(req.path, "/resources/pkeys");
+    assert_eq!(
+        req.body,
+        json!({
+            "pkey": "0x5",
+            "ip_over_ib": true,
+            "membership": "full",
+            "index0": true,
+            "guids": [GUID_VF],
+        })
+    );
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.members.len(), 3);
+    assert_eq!(p.members[GUID_VF], member("full", true));
+}
+
+#[tokio::test]
+async fn test_bind_ports_mixed_membership() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    // The storage targets are full members, and the compute clients are limited ones.
+    let ports = vec![
+        PortConfig {
+            guid: guid(GUID_VF),
+            index0: false,
+            membership: PortMembership::Limited,
+        },
+        PortConfig {
+            guid: guid("0011223344560300"),
+            index0: true,
+            membership: PortMembership::Full,
+        },
+    ];
+    mock.clear_requests();
+    ufm.bind_ports(partition("0x5"), ports).await.unwrap();
+
+    let reqs = mock.requests();
+    assert_eq!(reqs.len(), 2);
+    assert!(reqs.iter().all(|r| r.method == Method::POST));
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.members[GUID_VF], member("limited", false));
+    assert_eq!(p.members["0011223344560300"], member("full", true));
+    assert_eq!(p.members[GUID_PF], member("full", true));
+}
+
+#[tokio::test]
+async fn test_unbind_ports() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+    ufm.unbind_ports(pkey, vec![guid(GUID_UNKNOWN)])
+        .await
+        .unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::POST);
+    assert_eq!(req.path, "/actions/remove_guids_from_pkey");
+    assert_eq!(req.body, json!({ "pkey": "0x5", "guids": [GUID_UNKNOWN] }));
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.members.len(), 1);
+    assert!(p.members.contains_key(GUID_PF));
+}
+
+#[tokio::test]
+async fn test_delete_partition() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    ufm.delete_partition("0x5").await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::DELETE);
+    assert_eq!(req.path, "/resources/pkeys/0x5");
+    assert!(mock.partition(0x5).is_none());
+
+    assert!(ufm.delete_partition("0x5").await.is_err());
+}
+
+#[tokio::test]
+async fn test_list_port() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let mut p = mock.partition(0x5).unwrap();
+    p.members.insert(GUID_VF.to_string(), member("full", false));
+    mock.add_partition(0x5, p);
+
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+    let mut ports = ufm.list_port(pkey).await.unwrap();
+    ports.sort_by_key(|p| p.guid);
+
+    assert_eq!(ports.len(), 3);
+
+    assert_eq!(ports[0].guid, guid(GUID_VF));
+    assert_eq!(ports[0].lid, Lid::new(7));
+    assert_eq!(ports[0].parent_guid, Some(guid(GUID_PF)));
+    assert_eq!(ports[0].system_id, "1070fd0300176624");
+    assert_eq!(ports[0].system_name, "hpc-cloud01");
+    assert!(matches!(ports[0].port_type, Some(PortType::Virtual)));
+
+    assert_eq!(ports[1].guid, guid(GUID_UNKNOWN));
+    assert!(ports[1].lid.is_none());
+    assert!(ports[1].logical_state.is_none());
+    assert!(ports[1].port_type.is_none());
+
+    assert_eq!(ports[2].guid, guid(GUID_PF));
+    assert_eq!(ports[2].lid, Lid::new(4));
+    assert_eq!(ports[2].logical_state, Some(LogicalState::Active));
+    assert_eq!(ports[2].system_name, "hpc-cloud01");
+    assert!(matches!(ports[2].port_type, Some(PortType::Physical)));
+
+    // The default partition has all the ports of computers, and the virtual ports.
+    let pkey = PartitionKey::try_from("0x7fff").unwrap();
+    let mut ports = ufm.list_port(pkey).await.unwrap();
+    ports.sort_by_key(|p| p.guid);
+    assert_eq!(ports.len(), 2);
+    assert_eq!(ports[0].guid, guid(GUID_VF));
+    assert_eq!(ports[1].guid, guid(GUID_PF));
+
+    // The physical ports are still listed without the virtual ports, e.g. of an older UFM.
+    mock.fail_requests(Method::GET, "/resources/vports", StatusCode::NOT_FOUND);
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+    let mut ports = ufm.list_port(pkey).await.unwrap();
+    ports.sort_by_key(|p| p.guid);
+    assert_eq!(ports.len(), 3);
+    assert!(ports[0].port_type.is_none());
+    assert!(matches!(ports[2].port_type, Some(PortType::Physical)));
+}
+
+#[tokio::test]
+async fn test_partition_members() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let mut p = MockPartition::new("api_pkey_0x6");
+    p.members
+        .insert(GUID_PF.to_string(), member("limited", false));
+    mock.add_partition(0x6, p);
+
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+    let mut members = ufm.partition_members(pkey).await.unwrap();
+    members.sort_by_key(|m| m.guid);
+    assert_eq!(members.len(), 2);
+    assert_eq!(members[0].guid, guid(GUID_UNKNOWN));
+    assert_eq!(members[0].membership, PortMembership::Limited);
+    assert!(!members[0].index0);
+    assert_eq!(members[1].guid, guid(GUID_PF));
+    assert_eq!(members[1].membership, PortMembership::Full);
+    assert!(members[1].index0);
+
+    let ms = ufm.partitions_for_guid(guid(GUID_PF)).await.unwrap();
+    assert_eq!(ms.len(), 2);
+    assert_eq!(ms[0].partition.pkey.to_string(), "0x5");
+    assert_eq!(ms[0].membership, PortMembership::Full);
+    assert!(ms[0].index0);
+    assert_eq!(ms[1].partition.name, "api_pkey_0x6");
+    assert_eq!(ms[1].membership, PortMembership::Limited);
+    assert!(!ms[1].index0);
+
+    // All the partitions are got at once.
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.path, "/resources/pkeys");
+    assert_eq!(req.query.unwrap(), "qos_conf=true&guids_data=true");
+
+    assert!(ufm
+        .partitions_for_guid(guid(GUID_VF))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_partition_snapshot() {
+    let port = |g: &str, lid: i64, state: &str| Port {
+        guid: guid(g),
+        lid: Lid::new(lid),
+        logical_state: state.parse().ok(),
+        ..Port::default()
+    };
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+
+    let mut old = PartitionSnapshot::new(vec![partition("0x5"), partition("0x7")]);
+    old.add_ports(
+        pkey,
+        vec![
+            port(GUID_PF, 4, "Active"),
+            port(GUID_VF, 7, "Active"),
+            port(GUID_UNKNOWN, 65535, "Unknown"),
+        ],
+    );
+
+    let mut p5 = partition("0x5");
+    p5.qos.mtu_limit = IbMtu::Mtu4096;
+    let mut new = PartitionSnapshot::new(vec![p5, partition("0x6")]);
+    new.add_ports(
+        pkey,
+        vec![
+            port(GUID_PF, 9, "Active"),
+            port(GUID_VF, 7, "Down"),
+            port("b83fd203002a1f3a", 1, "Active"),
+        ],
+    );
+
+    let changes: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        changes,
+        [
+            "~ partition 0x5: mtu_limit 2048 -> 4096",
+            "- member 0x5: 0011223344560201",
+            "~ member 0x5: 0011223344560200 state Active -> Down",
+            "~ member 0x5: 1070fd0300176625 lid 4 -> 9",
+            "+ member 0x5: b83fd203002a1f3a (Active)",
+            "+ partition 0x6",
+            "- partition 0x7",
+        ]
+    );
+
+    assert!(new.diff(&new).is_empty());
+    // The members are not compared without the ports of both snapshots.
+    let changes = PartitionSnapshot::new(vec![partition("0x5")]).diff(&old);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].to_string(), "+ partition 0x7");
+}
+
+#[tokio::test]
+async fn test_watch_partitions() {
+    let mock = start().await;
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy::none(),
+        ..config(&mock)
+    })
+    .unwrap();
+
+    let pkey = PartitionKey::try_from("0x5").unwrap();
+    let snapshot = ufm.partition_snapshot(Some(pkey)).await.unwrap();
+    assert_eq!(snapshot.partitions.len(), 1);
+    assert_eq!(snapshot.ports[&pkey].len(), 2);
+
+    let changes = ufm.watch_partitions(Some(pkey), snapshot, Duration::from_millis(10));
+    tokio::pin!(changes);
+
+    mock.update_port(GUID_PF, json!({"logical_state": "Down", "lid": 9}));
+    let mut p = mock.partition(0x5).unwrap();
+    p.members.insert(GUID_VF.to_string(), member("full", false));
+    mock.add_partition(0x5, p);
+
+    let mut got = vec![];
+    for _ in 0..3 {
+        got.push(changes.next().await.unwrap().unwrap().to_string());
+    }
+    got.sort();
+    assert_eq!(
+        got,
+        [
+            "+ member 0x5: 0011223344560200 (Active)",
+            "~ member 0x5: 1070fd0300176625 lid 4 -> 9",
+            "~ member 0x5: 1070fd0300176625 state Active -> Down",
+        ]
+    );
+
+    // A failed poll is reported, and the polling goes on.
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    let err = changes.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, UFMError::ServerError(_)), "{err:?}");
+
+    ufm.delete_partition("0x5").await.unwrap();
+    let c = changes.next().await.unwrap().unwrap();
+    assert_eq!(c.to_string(), "- partition 0x5 (api_pkey_0x5)");
+
+    // All the partitions are watched without their ports.
+    let snapshot = ufm.partition_snapshot(None).await.unwrap();
+    assert!(snapshot.ports.is_empty());
+    let changes = ufm.watch_partitions(None, snapshot, Duration::from_millis(10));
+    tokio::pin!(changes);
+    ufm.add_partition(partition("0x6")).await.unwrap();
+    let c = changes.next().await.unwrap().unwrap();
+    assert_eq!(c.to_string(), "+ partition 0x6 (api_pkey_0x6)");
+}
+
+#[tokio::test]
+async fn test_list_ports() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    for (port, state, width) in [(2, "Active", "4x"), (3, "Active", "1x"), (4, "Down", "")] {
+        mock.add_port(
+            "Switch",
+            json!({
+                "guid": "b83fd203002a1f3a",
+                "name": format!("b83fd203002a1f3a_{port}"),
+                "systemID": "b83fd203002a1f3a",
+                "lid": 1,
+                "system_name": "switch01",
+                "logical_state": state,
+                "physical_state": if state == "Active" { "LinkUp" } else { "Polling" },
+                "active_speed": if state == "Active" { "HDR" } else { "" },
+                "active_width": width,
+                "mtu": 4096,
+                "peer_guid": if state == "Active" { GUID_PF } else { "" },
+                "peer_node_name": "hpc-cloud01 mlx5_0",
+                "peer_port_dname": "1070fd0300176625_2",
+                "fw_version": "27.2010.1202",
+                "external_number": port,
+            }),
+        );
+    }
+
+    let ports = ufm.list_ports(&PortFilter::default()).await.unwrap();
+    assert_eq!(ports.len(), 5);
+    assert_eq!(mock.last_request().unwrap().query, None);
+
+    let ports = ufm
+        .list_ports(&PortFilter {
+            sys_type: Some(SystemType::Switch),
+            system: Some("switch01".to_string()),
+            ..PortFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ports.len(), 4);
+    assert_eq!(
+        mock.last_request().unwrap().query.as_deref(),
+        Some("sys_type=Switch&system=switch01")
+    );
+
+    let p = &ports[1];
+    assert_eq!(p.name.as_deref(), Some("b83fd203002a1f3a_2"));
+    assert_eq!(p.physical_state, Some(PhysicalState::LinkUp));
+    assert_eq!(p.active_speed.as_deref(), Some("HDR"));
+    assert_eq!(p.active_width.as_deref(), Some("4x"));
+    assert_eq!(p.rate(), Some(200.0));
+    assert_eq!(p.mtu, Some(4096));
+    assert_eq!(p.peer_guid, Some(guid(GUID_PF)));
+    assert_eq!(p.peer_node_name.as_deref(), Some("hpc-cloud01 mlx5_0"));
+    assert_eq!(p.peer_port.as_deref(), Some("1070fd0300176625_2"));
+    assert_eq!(p.firmware_version.as_deref(), Some("27.2010.1202"));
+    assert_eq!(p.external_number, Some(2));
+
+    // The ports which are down.
+    let ports = ufm
+        .list_ports(&PortFilter {
+            active: Some(false),
+            ..PortFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ports.len(), 1);
+    assert_eq!(ports[0].name.as_deref(), Some("b83fd203002a1f3a_4"));
+    assert_eq!(ports[0].peer_guid, None);
+    assert_eq!(ports[0].rate(), None);
+
+    // The ports at a degraded width.
+    let ports = ufm
+        .list_ports(&PortFilter {
+            active: Some(true),
+            width: Some("1X".to_string()),
+            ..PortFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ports.len(), 1);
+    assert_eq!(ports[0].name.as_deref(), Some("b83fd203002a1f3a_3"));
+    assert_eq!(ports[0].rate(), Some(50.0));
+
+    let ports = ufm
+        .list_ports(&PortFilter {
+            guids: Some([guid(GUID_PF)].into()),
+            ..PortFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(ports.len(), 1);
+    assert_eq!(ports[0].system_name, "hpc-cloud01");
+    assert_eq!(ports[0].mtu, None);
+
+    assert!("gateway".parse::<SystemType>().is_ok());
+    assert!("router".parse::<SystemType>().is_err());
+}
+
+fn add_systems(mock: &MockUfm) {
+    mock.add_system(json!({
+        "system_guid": "b83fd203002a1f3a",
+        "system_name": "switch01",
+        "type": "switch",
+        "description": "MQM8700",
+        "model": "MQM8700",
+        "vendor": "Mellanox",
+        "fw_version": "27.2010.1202",
+        "ip": "10.0.0.5",
+        "state": "active",
+        "ports": ["b83fd203002a1f3a_1", "b83fd203002a1f3a_2"],
+        "severity": "Info",
+    }));
+    mock.add_system(json!({
+        "system_guid": "1070fd0300176624",
+        "system_name": "hpc-cloud01",
+        "type": "host",
+        "ports": ["1070fd0300176625_2"],
+    }));
+}
+
+#[tokio::test]
+async fn test_list_systems() {
+    let mock = start().await;
+    add_systems(&mock);
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let systems = ufm.list_systems().await.unwrap();
+    assert_eq!(systems.len(), 2);
+
+    let s = &systems[0];
+    assert_eq!(s.guid, guid("b83fd203002a1f3a"));
+    assert_eq!(s.system_name, "switch01");
+    assert_eq!(s.sys_type(), Some(SystemType::Switch));
+    assert_eq!(s.model.as_deref(), Some("MQM8700"));
+    assert_eq!(s.vendor.as_deref(), Some("Mellanox"));
+    assert_eq!(s.firmware_version.as_deref(), Some("27.2010.1202"));
+    assert_eq!(s.ip.as_deref(), Some("10.0.0.5"));
+    assert_eq!(s.state.as_deref(), Some("active"));
+    assert_eq!(s.ports.len(), 2);
+
+    let s = &systems[1];
+    assert_eq!(s.system_type, "host");
+    assert_eq!(s.sys_type(), Some(SystemType::Computer));
+    assert_eq!(s.model, None);
+}
+
+#[tokio::test]
+async fn test_get_system() {
+    let mock = start().await;
+    add_systems(&mock);
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let s = ufm.get_system(guid("1070fd0300176624")).await.unwrap();
+    assert_eq!(s.system_name, "hpc-cloud01");
+    assert_eq!(s.ports, ["1070fd0300176625_2"]);
+    assert_eq!(
+        mock.last_request().unwrap().path,
+        "/resources/systems/1070fd0300176624"
+    );
+
+    let err = ufm.get_system(guid(GUID_UNKNOWN)).await.unwrap_err();
+    assert!(matches!(err, UFMError::NotFound(_)), "{err:?}");
+}
+
+fn add_links(mock: &MockUfm) {
+    // Reported from the host to the switch.
+    mock.add_link(json!({
+        "source_guid": "0x1070fd0300176624",
+        "source_port": 2,
+        "source_port_guid": "1070fd0300176625",
+        "source_port_dname": "1070fd0300176625_2",
+        "source_port_node_description": "hpc-cloud01 mlx5_0",
+        "destination_guid": "0xb83fd203002a1f3a",
+        "destination_port": 1,
+        "destination_port_guid": "b83fd203002a1f3a",
+        "destination_port_dname": "b83fd203002a1f3a_1",
+        "destination_port_node_description": "switch01",
+        "active_speed": "HDR",
+        "width": "4x",
+        "state": "Active",
+    }));
+    // A switch which is not one of the systems.
+    mock.add_link(json!({
+        "source_guid": "b83fd203002a1f3a",
+        "source_port": 40,
+        "source_port_guid": "b83fd203002a1f3a",
+        "destination_guid": "0c42a10300d7a3c0",
+        "destination_port": 1,
+        "destination_port_guid": "0c42a10300d7a3c1",
+    }));
+}
+
+#[tokio::test]
+async fn test_list_links() {
+    let mock = start().await;
+    add_links(&mock);
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let links = ufm.list_links().await.unwrap();
+    assert_eq!(links.len(), 2);
+
+    let l = &links[0];
+    assert_eq!(l.source.system_guid, guid("1070fd0300176624"));
+    assert_eq!(l.source.port_number, 2);
+    assert_eq!(l.source.port_guid, guid("1070fd0300176625"));
+    assert_eq!(l.source.port_name.as_deref(), Some("1070fd0300176625_2"));
+    assert_eq!(l.destination.system_guid, guid("b83fd203002a1f3a"));
+    assert_eq!(l.destination.port_number, 1);
+    assert_eq!(l.speed.as_deref(), Some("HDR"));
+    assert_eq!(l.width.as_deref(), Some("4x"));
+    assert_eq!(l.state.as_deref(), Some("Active"));
+
+    let l = &links[1];
+    assert_eq!(l.destination.port_name, None);
+    assert_eq!(l.speed, None);
+}
+
+#[tokio::test]
+async fn test_topology() {
+    let mock = start().await;
+    add_systems(&mock);
+    add_links(&mock);
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let topo = ufm.topology().await.unwrap();
+    let nodes: Vec<(Guid, Option<&str>)> = topo
+        .nodes
+        .iter()
+        .map(|n| (n.guid, n.name.as_deref()))
+        .collect();
+    assert_eq!(
+        nodes,
+        [
+            (guid("0c42a10300d7a3c0"), None),
+            (guid("1070fd0300176624"), Some("hpc-cloud01")),
+            (guid("b83fd203002a1f3a"), Some("switch01")),
+        ]
+    );
+
+    // The links go from the lower end to the higher one.
+    assert_eq!(topo.links.len(), 2);
+    assert_eq!(topo.links[0].source.system_guid, guid("0c42a10300d7a3c0"));
+    assert_eq!(topo.links[0].destination.port_number, 40);
+    assert_eq!(topo.links[1].source.system_guid, guid("1070fd0300176624"));
+
+    assert_eq!(
+        topo.to_dot(),
+        r#"graph fabric {
+    "0c42a10300d7a3c0" [label="0c42a10300d7a3c0", shape=ellipse];
+    "1070fd0300176624" [label="hpc-cloud01", shape=ellipse];
+    "b83fd203002a1f3a" [label="switch01", shape=box];
+    "0c42a10300d7a3c0" -- "b83fd203002a1f3a" [taillabel="1 (0c42a10300d7a3c1)", headlabel="40 (b83fd203002a1f3a)", label=""];
+    "1070fd0300176624" -- "b83fd203002a1f3a" [taillabel="2 (1070fd0300176625)", headlabel="1 (b83fd203002a1f3a)", label="HDR 4x"];
+}
+"#
+    );
+
+    let json = topo.to_json().unwrap();
+    let parsed: libufm::Topology = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, topo);
+
+    // The same cabling reported in another order is the same topology.
+    let mut links = topo.links.clone();
+    links.reverse();
+    let l = &mut links[0];
+    std::mem::swap(&mut l.source, &mut l.destination);
+    let systems = ufm.list_systems().await.unwrap();
+    assert_eq!(libufm::Topology::new(systems, links), topo);
+}
+
+fn event(id: u64, severity: &str, timestamp: &str, object: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": "Link is down",
+        "severity": severity,
+        "timestamp": timestamp,
+        "category": "Fabric Topology",
+        "type": "Port",
+        "object_name": object,
+        "object_path": format!("default / Switch: switch01 / {object}"),
+        "description": format!("Link went down: {object}"),
+        "counter": 1,
+    })
+}
+
+#[tokio::test]
+async fn test_list_events() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    mock.add_event(event(
+        3,
+        "Critical",
+        "2024-01-01 10:02:00",
+        "b83fd203002a1f3a_1",
+    ));
+    mock.add_event(event(
+        1,
+        "Info",
+        "2024-01-01 10:00:00",
+        "b83fd203002a1f3a_1",
+    ));
+    mock.add_event(event(
+        2,
+        "Warning",
+        "2024-01-01 10:01:00",
+        "1070fd0300176625_2",
+    ));
+
+    let events = ufm.list_events(&EventFilter::default()).await.unwrap();
+    let ids: Vec<u64> = events.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+    assert_eq!(events[0].severity, Severity::Info);
+    assert_eq!(events[0].object_type.as_deref(), Some("Port"));
+    assert_eq!(events[0].category.as_deref(), Some("Fabric Topology"));
+
+    let filter = EventFilter {
+        min_severity: Some(Severity::Warning),
+        ..EventFilter::default()
+    };
+    let events = ufm.list_events(&filter).await.unwrap();
+    let ids: Vec<u64> = events.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2, 3]);
+    // The filter is applied by UFM.
+    assert_eq!(
+        mock.last_request().unwrap().query.as_deref(),
+        Some("min_severity=Warning")
+    );
+
+    let filter = EventFilter {
+        after_id: Some(1),
+        limit: Some(1),
+        ..EventFilter::default()
+    };
+    let events = ufm.list_events(&filter).await.unwrap();
+    let ids: Vec<u64> = events.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2]);
+
+    let filter = EventFilter {
+        since: Some("2024-01-01 10:01:00".to_string()),
+        until: Some("2024-01-01 10:02:00".to_string()),
+        ..EventFilter::default()
+    };
+    let events = ufm.list_events(&filter).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id, 2);
+
+    let filter = EventFilter {
+        object: Some("B83FD203002A1F3A".to_string()),
+        after_id: Some(1),
+        ..EventFilter::default()
+    };
+    let events = ufm.list_events(&filter).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id, 3);
+
+    mock.add_alarm(json!({
+        "id": 7,
+        "name": "Link is down",
+        "severity": "Minor",
+        "timestamp": "2024-01-01 10:02:00",
+        "type": "Port",
+        "object_name": "b83fd203002a1f3a_1",
+        "reason": "Link went down",
+    }));
+    let alarms = ufm.list_alarms(&EventFilter::default()).await.unwrap();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].severity, Severity::Minor);
+    assert_eq!(alarms[0].reason.as_deref(), Some("Link went down"));
+
+    let filter = EventFilter {
+        min_severity: Some(Severity::Critical),
+        ..EventFilter::default()
+    };
+    assert!(ufm.list_alarms(&filter).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_follow_events() {
+    let mock = start().await;
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy::none(),
+        ..config(&mock)
+    })
+    .unwrap();
+
+    mock.add_event(event(
+        1,
+        "Info",
+        "2024-01-01 10:00:00",
+        "b83fd203002a1f3a_1",
+    ));
+    mock.add_event(event(
+        2,
+        "Critical",
+        "2024-01-01 10:01:00",
+        "b83fd203002a1f3a_1",
+    ));
+
+    let filter = EventFilter {
+        object: Some("b83fd203002a1f3a".to_string()),
+        ..EventFilter::default()
+    };
+    let events = ufm.follow_events(filter, Duration::from_millis(10));
+    tokio::pin!(events);
+
+    // It starts from the latest event, so the existing ones are not replayed.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), events.next())
+            .await
+            .is_err()
+    );
+    let req = &mock.requests()[0];
+    assert_eq!(req.query.as_deref(), Some("order=desc&limit=1"));
+
+    // A failed poll is reported, and the polling goes on.
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    let err = events.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, UFMError::ServerError(_)), "{err:?}");
+
+    // The events of the other objects are skipped.
+    mock.add_event(event(
+        3,
+        "Info",
+        "2024-01-01 10:02:00",
+        "1070fd0300176625_2",
+    ));
+    mock.add_event(event(
+        4,
+        "Info",
+        "2024-01-01 10:03:00",
+        "b83fd203002a1f3a_1",
+    ));
+    assert_eq!(events.next().await.unwrap().unwrap().id, 4);
+
+    // Only the new events of the object are polled, at most 1000 at a time.
+    let reqs = mock.requests();
+    assert!(reqs.iter().all(|r| r.path == "/app/events"));
+    let query = reqs.last().unwrap().query.clone().unwrap();
+    assert!(
+        query.starts_with("object=b83fd203002a1f3a&after_id="),
+        "{query}"
+    );
+    assert!(query.ends_with("&limit=1000"), "{query}");
+
+    let filter = EventFilter {
+        after_id: Some(3),
+        ..EventFilter::default()
+    };
+    let events = ufm.follow_events(filter, Duration::from_millis(10));
+    tokio::pin!(events);
+    assert_eq!(events.next().await.unwrap().unwrap().id, 4);
+}
+
+#[tokio::test]
+async fn test_list_vports() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let vports = ufm.list_vports().await.unwrap();
+    assert_eq!(vports.len(), 1);
+    assert_eq!(vports[0].guid, guid(GUID_VF));
+    assert_eq!(vports[0].parent_guid, Some(guid(GUID_PF)));
+    assert_eq!(vports[0].lid, Lid::new(7));
+    assert_eq!(vports[0].logical_state, Some(LogicalState::Active));
+    assert!(vports[0].name.is_none());
+    assert_eq!(mock.last_request().unwrap().path, "/resources/vports");
+}
+
+#[tokio::test]
+async fn test_connect_with_token() {
+    let mock = start().await;
+    let ufm = libufm::connect(UFMConfig {
+        address: mock.address(),
+        username: None,
+        password: None,
+        token: Some("XlojlA7zgotVegyIEIP5vnw5C7ZYT9".to_string()),
+        cert: None,
+        ..Default::default()
+    })
+    .unwrap();
+
+    ufm.version().await.unwrap();
+
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.path, "/app/ufm_version");
+    assert_eq!(
+        req.authorization.unwrap(),
+        "Basic XlojlA7zgotVegyIEIP5vnw5C7ZYT9"
+    );
+}
+
+#[tokio::test]
+async fn test_connect_without_credential() {
+    let err = libufm::connect(UFMConfig {
+        address: "http://127.0.0.1".to_string(),
+        username: None,
+        password: None,
+        token: None,
+        cert: None,
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+
+    assert!(matches!(err, UFMError::InvalidConfig(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn test_error_conflict() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let err = ufm.add_partition(partition("0x5")).await.unwrap_err();
+    assert!(matches!(err, UFMError::Conflict(_)), "{err:?}");
+    assert_eq!(err.status(), Some(409));
+    assert!(!err.is_retryable());
+
+    let req = err.request().unwrap();
+    assert_eq!(req.method, "POST");
+    assert_eq!(req.path, "/resources/pkeys/add");
+    assert_eq!(req.message, "Pkey 0x5 already exists");
+    assert_eq!(
+        req.body,
+        Some(json!({ "error": "Pkey 0x5 already exists" }))
+    );
+}
+
+#[tokio::test]
+async fn test_error_not_found() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let err = ufm.delete_partition("0x6").await.unwrap_err();
+    assert!(matches!(err, UFMError::NotFound(_)), "{err:?}");
+    assert_eq!(err.request().unwrap().method, "DELETE");
+    assert_eq!(err.request().unwrap().path, "/resources/pkeys/0x6");
+    assert!(!err.is_retryable());
+
+    // UFM replies an empty object for unknown pkeys.
+    let err = ufm.get_partition("0x6").await.unwrap_err();
+    assert!(matches!(err, UFMError::NotFound(_)), "{err:?}");
+    assert_eq!(err.status(), Some(404));
+}
+
+#[tokio::test]
+async fn test_error_status() {
+    let mock = start().await;
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy::none(),
+        ..config(&mock)
+    })
+    .unwrap();
+
+    mock.fail_next(StatusCode::UNAUTHORIZED, 1);
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Unauthorized(_)), "{err:?}");
+    assert!(!err.is_retryable());
+
+    mock.fail_next(StatusCode::FORBIDDEN, 1);
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Forbidden(_)), "{err:?}");
+    assert!(!err.is_retryable());
+
+    mock.fail_next(StatusCode::BAD_REQUEST, 1);
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::InvalidRequest(_)), "{err:?}");
+    assert!(!err.is_retryable());
+
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::ServerError(_)), "{err:?}");
+    assert_eq!(err.request().unwrap().message, "injected failure");
+    assert!(err.is_retryable());
+
+    assert!(ufm.version().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_error_connection() {
+    // Get a local port that nobody listens on.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let ufm = libufm::connect(UFMConfig {
+        address: format!("http://{addr}"),
+        username: Some("admin".to_string()),
+        password: Some("123456".to_string()),
+        token: None,
+        cert: None,
+        retry: fast_retry(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::ConnectionFailure(_)), "{err:?}");
+    assert_eq!(err.status(), None);
+    assert!(err.is_retryable());
+}
+
+#[tokio::test]
+async fn test_retry() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    // GET is retried until it succeeds.
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 2);
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+    assert_eq!(mock.requests().len(), 3);
+
+    // GET gives up after max attempts.
+    mock.clear_requests();
+    mock.fail_next(StatusCode::BAD_GATEWAY, 3);
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::ServerError(_)), "{err:?}");
+    assert_eq!(mock.requests().len(), 3);
+
+    // The status not in the policy is not retried.
+    mock.clear_requests();
+    mock.fail_next(StatusCode::NOT_IMPLEMENTED, 1);
+    assert!(ufm.version().await.is_err());
+    assert_eq!(mock.requests().len(), 1);
+
+    // PUT is retried with the same body.
+    mock.clear_requests();
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    ufm.update_partition_qos(partition("0x5")).await.unwrap();
+    let reqs = mock.requests();
+    assert_eq!(reqs.len(), 2);
+    assert_eq!(reqs[0].body, reqs[1].body);
+    assert_eq!(mock.partition(0x5).unwrap().service_level, 3);
+}
+
+#[tokio::test]
+async fn test_retry_post() {
+    let mock = start().await;
+
+    // POST is not retried by default.
+    let ufm = libufm::connect(config(&mock)).unwrap();
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    assert!(ufm.add_partition(partition("0x6")).await.is_err());
+    assert_eq!(mock.requests().len(), 1);
+    assert!(mock.partition(0x6).is_none());
+
+    // POST is retried if enabled.
+    mock.clear_requests();
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy {
+            retry_post: true,
+            ..fast_retry()
+        },
+        ..config(&mock)
+    })
+    .unwrap();
+    mock.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+    ufm.add_partition(partition("0x6")).await.unwrap();
+    assert_eq!(mock.requests().len(), 2);
+    assert!(mock.partition(0x6).is_some());
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    let mock = start().await;
+    mock.set_delay(Duration::from_millis(300));
+
+    // The whole request timed out.
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy::none(),
+        timeout: TimeoutConfig {
+            request: Some(Duration::from_millis(50)),
+            ..TimeoutConfig::default()
+        },
+        ..config(&mock)
+    })
+    .unwrap();
+
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Timeout(_)), "{err:?}");
+    assert!(err.is_retryable());
+
+    // Override the timeout of a long operation.
+    let long = ufm.with_timeout(Duration::from_secs(5));
+    assert_eq!(long.version().await.unwrap(), ufmmock::UFM_VERSION);
+    let short = long.with_timeout(Duration::from_millis(50));
+    let err = short.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Timeout(_)), "{err:?}");
+
+    // Waiting for the response timed out.
+    let ufm = libufm::connect(UFMConfig {
+        retry: RetryPolicy::none(),
+        timeout: TimeoutConfig {
+            read: Some(Duration::from_millis(50)),
+            ..TimeoutConfig::default()
+        },
+        pool: PoolConfig {
+            idle_timeout: Some(Duration::from_secs(1)),
+            max_idle_per_host: 1,
+        },
+        ..config(&mock)
+    })
+    .unwrap();
+
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Timeout(_)), "{err:?}");
+
+    // The override replaces the read timeout, on the same connections.
+    let long = ufm.with_timeout(Duration::from_secs(5));
+    assert_eq!(long.version().await.unwrap(), ufmmock::UFM_VERSION);
+}
+
+#[tokio::test]
+async fn test_tls_verify() {
+    let mock = MockUfm::start_tls("ufm.example.com").await.unwrap();
+    let ca_crt = temp_file("verify-ca.crt", &mock.ca_crt().unwrap());
+
+    let tls_config = |mode: TlsMode, server_name: Option<&str>| UFMConfig {
+        retry: RetryPolicy::none(),
+        tls: TlsConfig {
+            mode,
+            server_name: server_name.map(str::to_string),
+        },
+        ..config(&mock)
+    };
+
+    // The mock CA is not one of the well-known roots.
+    let ufm = libufm::connect(tls_config(TlsMode::WebPkiRoots, None)).unwrap();
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::ConnectionFailure(_)), "{err:?}");
+
+    // The certificate is for ufm.example.com rather than 127.0.0.1.
+    let ufm = libufm::connect(tls_config(TlsMode::CustomCa(ca_crt.clone()), None)).unwrap();
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::ConnectionFailure(_)), "{err:?}");
+
+    let ufm = libufm::connect(tls_config(
+        TlsMode::CustomCa(ca_crt.clone()),
+        Some("ufm.example.com"),
+    ))
+    .unwrap();
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+
+    let ufm = libufm::connect(tls_config(TlsMode::InsecureSkipVerify, None)).unwrap();
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+    assert!(!mock.last_request().unwrap().client_cert);
+
+    // A missing CA file is a configuration error.
+    let res = libufm::connect(tls_config(
+        TlsMode::CustomCa("/nonexistent/ca.crt".to_string()),
+        None,
+    ));
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+}
+
+#[tokio::test]
+async fn test_tls_client_cert() {
+    let mock = MockUfm::start_tls("ufm.example.com").await.unwrap();
+    let (tls_crt, tls_key) = mock.issue_client_cert().unwrap();
+
+    let ufm = libufm::connect(UFMConfig {
+        username: None,
+        password: None,
+        cert: Some(UFMCert {
+            ca_crt: temp_file("client-ca.crt", &mock.ca_crt().unwrap()),
+            tls_key: temp_file("client-tls.key", &tls_key),
+            tls_crt: temp_file("client-tls.crt", &tls_crt),
+        }),
+        tls: TlsConfig {
+            server_name: Some("ufm.example.com".to_string()),
+            ..TlsConfig::default()
+        },
+        ..config(&mock)
+    })
+    .unwrap();
+
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+
+    let req = mock.last_request().unwrap();
+    assert!(req.client_cert);
+    assert_eq!(req.authorization, None);
+}
+
+#[tokio::test]
+async fn test_session() {
+    let mock = start().await;
+
+    let ufm = libufm::connect(UFMConfig {
+        session: true,
+        ..config(&mock)
+    })
+    .unwrap();
+
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+    let ps = ufm.list_partition().await.unwrap();
+    assert_eq!(ps.len(), 2);
+
+    // Log in once, then send the session cookie rather than the credentials.
+    let reqs = mock.requests();
+    let logins = reqs.iter().filter(|r| r.path == "/dologin").count();
+    assert_eq!(logins, 1);
+    assert_eq!(reqs[0].method, Method::POST);
+    let req = reqs.last().unwrap();
+    assert_eq!(req.authorization, None);
+    let cookie = req.cookie.clone().unwrap();
+    assert!(cookie.contains("sessionid="), "{cookie}");
+
+    // Log in again once the session expired.
+    mock.expire_sessions();
+    mock.clear_requests();
+    assert_eq!(ufm.version().await.unwrap(), ufmmock::UFM_VERSION);
+
+    let reqs = mock.requests();
+    let paths: Vec<&str> = reqs.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, ["/app/ufm_version", "/dologin", "/app/ufm_version"]);
+    assert_eq!(reqs[1].cookie, None);
+    assert_ne!(reqs[2].cookie.clone().unwrap(), cookie);
+}
+
+#[tokio::test]
+async fn test_session_login_failed() {
+    let mock = start().await;
+
+    let ufm = libufm::connect(UFMConfig {
+        password: Some("wrong".to_string()),
+        session: true,
+        ..config(&mock)
+    })
+    .unwrap();
+
+    let err = ufm.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Unauthorized(_)), "{err:?}");
+    assert_eq!(err.request().unwrap().path, "/dologin");
+}
+
+#[tokio::test]
+async fn test_tokens() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let token = ufm.create_token().await.unwrap();
+    assert!(!token.access_token.is_empty());
+    assert!(!token.revoked);
+    assert_eq!(mock.last_request().unwrap().method, Method::POST);
+
+    let tokens = ufm.list_tokens().await.unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].access_token, token.access_token);
+
+    // Authenticate by the new token.
+    let by_token = libufm::connect(UFMConfig {
+        username: None,
+        password: None,
+        token: Some(token.access_token.clone()),
+        ..config(&mock)
+    })
+    .unwrap();
+    assert_eq!(by_token.version().await.unwrap(), ufmmock::UFM_VERSION);
+
+    ufm.revoke_token(&token.access_token).await.unwrap();
+    assert!(mock.tokens()[0].revoked);
+    assert!(ufm.list_tokens().await.unwrap()[0].revoked);
+
+    let err = by_token.version().await.unwrap_err();
+    assert!(matches!(err, UFMError::Unauthorized(_)), "{err:?}");
+
+    let err = ufm.revoke_token("nonexistent").await.unwrap_err();
+    assert!(matches!(err, UFMError::NotFound(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn test_monitoring_session() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let guids = [guid(GUID_PF), guid("b83fd203002a1f3a")];
+    let counters = [Counter::XmitData, Counter::SymbolErrors];
+    let session = ufm
+        .create_monitoring_session(&guids, &counters, Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert_eq!(session.ports.len(), 2);
+    assert_eq!(session.ports["1070fd0300176625_2"], guid(GUID_PF));
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.method, Method::POST);
+    assert_eq!(req.path, "/monitoring/session");
+    assert_eq!(
+        req.body["attributes"],
+        json!(["PortXmitDataExtended", "SymbolErrorCounter"])
+    );
+    assert_eq!(req.body["interval"], 5);
+    assert_eq!(mock.monitoring_sessions().len(), 1);
+
+    mock.set_counters(
+        1700000000.0,
+        json!({
+            "1070fd0300176625_2": {
+                "PortXmitDataExtended": 1024,
+                "PortRcvDataExtended": 2048,
+                "SymbolErrorCounter": 3.0,
+            },
+        }),
+    );
+    let samples = ufm.monitoring_data(&session).await.unwrap();
+    let sample = samples.iter().find(|s| s.guid == guid(GUID_PF)).unwrap();
+    assert_eq!(sample.timestamp, 1700000000.0);
+    assert_eq!(sample.port_name, "1070fd0300176625_2");
+    assert_eq!(sample.counters.xmit_data, Some(1024));
+    assert_eq!(sample.counters.symbol_errors, Some(3));
+    // The counters which are not monitored.
+    assert_eq!(sample.counters.rcv_data, None);
+
+    ufm.delete_monitoring_session(&session).await.unwrap();
+    assert!(mock.monitoring_sessions().is_empty());
+    let res = ufm.delete_monitoring_session(&session).await;
+    assert!(matches!(res, Err(UFMError::NotFound(_))), "{res:?}");
+
+    let res = ufm
+        .create_monitoring_session(&[guid(GUID_UNKNOWN)], &counters, Duration::from_secs(5))
+        .await;
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+}
+
+#[test]
+fn test_counter_rate() {
+    let sample = |timestamp: f64, xmit_data: u64, link_downed: Option<u64>| CounterSample {
+        timestamp,
+        guid: guid(GUID_PF),
+        port_name: "1070fd0300176625_2".to_string(),
+        counters: PortCounters {
+            xmit_data: Some(xmit_data),
+            link_downed,
+            ..PortCounters::default()
+        },
+    };
+
+    let prev = sample(100.0, 1000, Some(1));
+    let cur = sample(102.0, 3000, Some(2));
+    // The data is in units of 4 bytes.
+    assert_eq!(cur.rate(&prev, Counter::XmitData), Some(4000.0));
+    assert_eq!(cur.rate(&prev, Counter::LinkDowned), Some(0.5));
+    assert_eq!(cur.rate(&prev, Counter::RcvData), None);
+
+    // The counter was reset.
+    assert_eq!(prev.rate(&cur, Counter::XmitData), None);
+    let reset = sample(104.0, 10, None);
+    assert_eq!(reset.rate(&cur, Counter::XmitData), None);
+    assert_eq!(reset.rate(&cur, Counter::LinkDowned), None);
+
+    assert_eq!("xmit-data".parse::<Counter>().unwrap(), Counter::XmitData);
+    assert_eq!(
+        "PortXmitWait".parse::<Counter>().unwrap(),
+        Counter::XmitWait
+    );
+    assert!("xmit".parse::<Counter>().is_err());
+}
+
+const PARTITIONS_YAML: &str = r#"
+partitions:
+  - pkey: 0x5
+    ipoib: false
+    qos:
+      mtu_limit: 4
+      service_level: 0
+      rate_limit: 2.5
+    members:
+      - guid: "1070fd0300176625"
+        membership: full
+        index0: true
+      - guid: "b83fd203002a1f3a"
+        membership: limited
+  - pkey: "0x6"
+    ipoib: true
+    members:
+      - guid: "0011223344560200"
+"#;
+
+#[test]
+fn test_partition_spec() {
+    let specs = PartitionSpec::from_yaml(PARTITIONS_YAML).unwrap();
+    assert_eq!(specs.len(), 2);
+    assert_eq!(specs[0].partition.pkey.to_string(), "0x5");
+    assert_eq!(specs[0].partition.qos.mtu_limit, IbMtu::Mtu4096);
+    assert_eq!(specs[0].members[1].membership, PortMembership::Limited);
+    assert!(!specs[0].members[1].index0);
+    assert_eq!(specs[1].partition.pkey.to_string(), "0x6");
+    assert_eq!(specs[1].partition.qos.mtu_limit, IbMtu::Mtu2048);
+    assert_eq!(specs[1].members[0].membership, PortMembership::Full);
+
+    let toml = r#"
+[[partitions]]
+pkey = "0x5"
+
+[[partitions.members]]
+guid = "1070fd0300176625"
+"#;
+    let specs = PartitionSpec::from_toml(toml).unwrap();
+    assert_eq!(specs[0].members[0].guid, guid(GUID_PF));
+
+    let res = PartitionSpec::from_yaml("partitions:\n  - pkey: 0x5\n    mtu: 4\n");
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+    let res =
+        PartitionSpec::from_yaml("partitions:\n  - pkey: 0x5\n    qos:\n      rate_limit: 50\n");
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+}
+
+#[test]
+fn test_plan() {
+    let desired = PartitionSpec::from_yaml(PARTITIONS_YAML).unwrap();
+    let current = PartitionSpec::from_yaml(
+        r#"
+partitions:
+  - pkey: 0x5
+    members:
+      - guid: "1070fd0300176625"
+        membership: full
+        index0: true
+      - guid: "b83fd203002a1f3a"
+        membership: full
+      - guid: "0011223344560201"
+  - pkey: 0x7
+"#,
+    )
+    .unwrap();
+
+    let lines =
+        |plan: &Plan| -> Vec<String> { plan.changes.iter().map(|c| c.to_string()).collect() };
+
+    let plan = Plan::new(&desired, &current, false).unwrap();
+    assert_eq!(
+        lines(&plan),
+        [
+            "~ partition 0x5: mtu_limit 2048 -> 4096",
+            "+ bind 0x5 (limited, index0=false): b83fd203002a1f3a",
+            "- unbind 0x5: 0011223344560201",
+            "+ partition 0x6: ipoib=true, mtu_limit=2048, service_level=0, rate_limit=2.5",
+            "+ bind 0x6 (full, index0=false): 0011223344560200",
+        ]
+    );
+
+    let plan = Plan::new(&desired, &current, true).unwrap();
+    assert_eq!(lines(&plan).last().unwrap(), "- partition 0x7");
+
+    // Nothing to change once applied.
+    assert!(Plan::new(&desired, &desired, true).unwrap().is_empty());
+
+    let res = Plan::new(&[desired[0].clone(), desired[0].clone()], &current, false);
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+    let res = Plan::new(
+        &desired[1..],
+        &PartitionSpec::from_yaml("partitions:\n  - pkey: 0x6\n").unwrap(),
+        false,
+    );
+    assert!(matches!(res, Err(UFMError::InvalidConfig(_))));
+}
+
+#[tokio::test]
+async fn test_apply_partitions() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+
+    let mut p = MockPartition::new("api_pkey_0x7");
+    p.members.insert(GUID_PF.to_string(), member("full", false));
+    mock.add_partition(0x7, p);
+
+    let desired = PartitionSpec::from_yaml(PARTITIONS_YAML).unwrap();
+
+    // Nothing is changed in the dry run.
+    let plan = ufm.plan_partitions(&desired, true).await.unwrap();
+    assert_eq!(plan.changes.len(), 6, "{plan}");
+    assert!(mock.partition(0x6).is_none());
+
+    let report = ufm.apply_partitions(&desired, true).await.unwrap();
+    assert_eq!(report.applied.len(), 6, "{report:?}");
+    assert_eq!(report.total, 6);
+    assert!(report.failed.is_none());
+
+    let p5 = mock.partition(0x5).unwrap();
+    assert_eq!(p5.mtu_limit, 4);
+    assert_eq!(p5.members.len(), 2);
+    assert_eq!(p5.members[GUID_PF], member("full", true));
+    assert_eq!(p5.members["b83fd203002a1f3a"], member("limited", false));
+    let p6 = mock.partition(0x6).unwrap();
+    assert!(p6.ip_over_ib);
+    assert_eq!(p6.members[GUID_VF], member("full", false));
+    assert!(mock.partition(0x7).is_none());
+    assert!(mock.partition(0x7fff).is_some());
+
+    // Apply again without any changes.
+    let report = ufm.apply_partitions(&desired, true).await.unwrap();
+    assert!(report.is_empty(), "{report:?}");
+}
+
+#[tokio::test]
+async fn test_apply_partitions_failed() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+    mock.add_partition(0x7, MockPartition::new("api_pkey_0x7"));
+    mock.fail_requests(Method::DELETE, "/resources/pkeys/0x7", StatusCode::CONFLICT);
+
+    // The changes before the failed one are applied, and reported.
+    let desired = PartitionSpec::from_yaml(PARTITIONS_YAML).unwrap();
+    let report = ufm.apply_partitions(&desired, true).await.unwrap();
+    assert_eq!(report.total, 6);
+    assert_eq!(report.applied.len(), 5, "{report:?}");
+    let (change, err) = report.failed.unwrap();
+    assert!(matches!(change, PartitionChange::Delete(_)), "{change}");
+    assert!(matches!(err, UFMError::Conflict(_)), "{err}");
+    assert!(mock.partition(0x6).is_some());
+    assert!(mock.partition(0x7).is_some());
+}
+
+#[tokio::test]
+async fn test_batch() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+    mock.add_partition(0x12, MockPartition::new("api_pkey_0x12"));
+
+    let ops: Vec<BatchOp> = serde_json::from_value(json!([
+        {"op": "create", "pkey": "0x10", "ipoib": true, "members": [{"guid": GUID_PF, "index0": true}]},
+        {"op": "bind", "pkey": "0x10", "members": [{"guid": GUID_VF, "membership": "limited"}]},
+        {"op": "create", "pkey": "0x11", "qos": {"mtu_limit": 4, "service_level": 1, "rate_limit": 100.0}},
+        {"op": "unbind", "pkey": "0x5", "guids": [GUID_UNKNOWN]},
+        {"op": "delete", "pkey": "0x12"},
+        {"op": "delete", "pkey": "0x13"},
+        {"op": "create", "pkey": "0x5"},
+    ]))
+    .unwrap();
+
+    let report = ufm.batch(ops, 4).await;
+    assert_eq!(report.results.len(), 7);
+    assert_eq!(report.succeeded(), 5);
+    let failed: Vec<String> = report.failed().map(|r| r.op.to_string()).collect();
+    assert_eq!(failed, ["delete 0x13", "create 0x5 (0 ports)"]);
+    assert!(matches!(
+        report.results[5].result,
+        Err(UFMError::NotFound(_))
+    ));
+    assert!(matches!(
+        report.results[6].result,
+        Err(UFMError::Conflict(_))
+    ));
+    let err = report.error().unwrap().to_string();
+    assert!(
+        err.starts_with("2 of 7 operations failed: delete 0x13: "),
+        "{err}"
+    );
+
+    // The operations on the same pkey run in order.
+    let p = mock.partition(0x10).unwrap();
+    assert!(p.ip_over_ib);
+    assert_eq!(p.members[GUID_PF], member("full", true));
+    assert_eq!(p.members[GUID_VF], member("limited", false));
+    assert_eq!(mock.partition(0x11).unwrap().service_level, 1);
+    assert!(!mock
+        .partition(0x5)
+        .unwrap()
+        .members
+        .contains_key(GUID_UNKNOWN));
+    assert!(mock.partition(0x12).is_none());
+
+    let report = ufm
+        .delete_partitions(vec![PartitionKey::try_from("0x10").unwrap()], 1)
+        .await;
+    assert!(report.error().is_none());
+    assert!(mock.partition(0x10).is_none());
+}
+
+#[tokio::test]
+async fn test_batch_concurrency() {
+    let mock = start().await;
+    let ufm = libufm::connect(config(&mock)).unwrap();
+    mock.set_delay(Duration::from_millis(200));
+
+    let partitions = (0x20..0x28)
+        .map(|k: u16| (partition(&k.to_string()), vec![]))
+        .collect();
+    let start = std::time::Instant::now();
+    let report = ufm.create_partitions(partitions, 4).await;
+    assert_eq!(report.succeeded(), 8);
+    // 2 rounds of 4 requests, rather than 8 requests one by one.
+    assert!(
+        start.elapsed() < Duration::from_millis(1200),
+        "{:?}",
+        start.elapsed()
+    );
+
+    // The clones share the handle across tasks.
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            let ufm = ufm.clone();
+            tokio::spawn(async move { ufm.version().await })
+        })
+        .collect();
+    for t in tasks {
+        assert_eq!(t.await.unwrap().unwrap(), ufmmock::UFM_VERSION);
+    }
+}
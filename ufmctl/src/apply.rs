@@ -0,0 +1,99 @@
+use serde::Serialize;
+
+use libufm::{ApplyReport, PartitionChange, PartitionSpec, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat};
+
+pub struct ApplyOptions {
+    pub file: String,
+    pub dry_run: bool,
+    pub prune: bool,
+}
+
+/// The changes which were applied, in the JSON and YAML output.
+#[derive(Serialize)]
+struct ApplyReportView<'a> {
+    applied: &'a [PartitionChange],
+    /// The change which failed, if any; the following ones were not applied.
+    failed: Option<&'a PartitionChange>,
+    error: Option<String>,
+    total: usize,
+}
+
+/// Print the changes to make the partitions as declared in the file.
+pub async fn diff(
+    conf: UFMConfig,
+    opt: &ApplyOptions,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    // The changes are not rows of the same columns.
+    if format == OutputFormat::Csv {
+        return Err(output::unsupported(format, "diff"));
+    }
+
+    let ufm = libufm::connect(conf)?;
+    let desired = PartitionSpec::load(&opt.file)?;
+
+    let plan = ufm.plan_partitions(&desired, opt.prune).await?;
+    if output::print_document(format, "PartitionPlan", &plan.changes)? {
+        return Ok(());
+    }
+
+    match plan.is_empty() {
+        true => println!("No changes."),
+        false => print!("{}", plan),
+    }
+
+    Ok(())
+}
+
+pub async fn run(
+    conf: UFMConfig,
+    opt: &ApplyOptions,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    if opt.dry_run {
+        return diff(conf, opt, format).await;
+    }
+    if format == OutputFormat::Csv {
+        return Err(output::unsupported(format, "apply"));
+    }
+
+    let ufm = libufm::connect(conf)?;
+    let desired = PartitionSpec::load(&opt.file)?;
+
+    let report = ufm.apply_partitions(&desired, opt.prune).await?;
+    let view = ApplyReportView {
+        applied: &report.applied,
+        failed: report.failed.as_ref().map(|(c, _)| c),
+        error: report.failed.as_ref().map(|(_, e)| e.to_string()),
+        total: report.total,
+    };
+    if !output::print_document(format, "ApplyReport", &view)? {
+        print_report(&report);
+    }
+
+    match report.failed {
+        Some((_, e)) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn print_report(report: &ApplyReport) {
+    if report.is_empty() {
+        println!("No changes.");
+        return;
+    }
+
+    for c in &report.applied {
+        println!("{}", c);
+    }
+    if let Some((c, _)) = &report.failed {
+        println!("Failed: {}", c);
+    }
+    println!(
+        "Applied {} of {} changes.",
+        report.applied.len(),
+        report.total
+    );
+}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+
+use libufm::{BatchOp, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+/// The result of an operation, in the JSON, YAML and CSV output.
+#[derive(Serialize)]
+struct OpResult<'a> {
+    #[serde(flatten)]
+    op: &'a BatchOp,
+    /// The error of the operation, if it failed.
+    error: Option<String>,
+}
+
+pub async fn run(
+    conf: UFMConfig,
+    file: &str,
+    concurrency: usize,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let ops = BatchOp::load(file)?;
+
+    let report = ufm.batch(ops, concurrency).await;
+    let results: Vec<OpResult> = report
+        .results
+        .iter()
+        .map(|r| OpResult {
+            op: &r.op,
+            error: r.result.as_ref().err().map(|e| e.to_string()),
+        })
+        .collect();
+
+    if !output::print_document(format, "BatchReport", &results)? {
+        match format {
+            OutputFormat::Csv => {
+                let mut table = Table::new(&["Result", "Operation", "Error"]);
+                for r in results {
+                    table.add_row(vec![
+                        result(&r).to_string(),
+                        r.op.to_string(),
+                        r.error.unwrap_or_default(),
+                    ]);
+                }
+                table.print(format);
+            }
+            _ => {
+                for r in &results {
+                    match &r.error {
+                        None => println!("{:<8}{}", result(r), r.op),
+                        Some(e) => println!("{:<8}{}: {}", result(r), r.op, e),
+                    }
+                }
+                println!(
+                    "{} of {} operations succeeded.",
+                    report.succeeded(),
+                    report.results.len()
+                );
+            }
+        }
+    }
+
+    match report.error() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn result(r: &OpResult) -> &'static str {
+    match r.error {
+        None => "OK",
+        Some(_) => "FAILED",
+    }
+}
@@ -1,4 +1,4 @@
-use libufm::{Partition, PartitionKey, PortConfig, PortMembership, UFMConfig, UFMError};
+use libufm::{Guid, Partition, PartitionKey, PortConfig, PortMembership, UFMConfig, UFMError};
 
 pub async fn run(conf: UFMConfig, pkey: &str, guids: &Vec<String>) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
@@ -6,7 +6,7 @@ pub async fn run(conf: UFMConfig, pkey: &str, guids: &Vec<String>) -> Result<(),
     let mut pbs = vec![];
     for g in guids {
         pbs.push(PortConfig {
-            guid: g.to_string(),
+            guid: Guid::try_from(g)?,
             index0: true,
             membership: PortMembership::Full,
         })
@@ -1,5 +1,6 @@
 use libufm::{
-    Partition, PartitionKey, PartitionQoS, PortConfig, PortMembership, UFMConfig, UFMError,
+    Guid, IbMtu, IbRate, Partition, PartitionKey, PartitionQoS, PortConfig, PortMembership,
+    UFMConfig, UFMError,
 };
 
 pub struct CreateOptions {
@@ -8,9 +9,9 @@ pub struct CreateOptions {
     pub index0: bool,
     pub membership: String,
     pub guids: Vec<String>,
-    pub mtu: u16,
+    pub mtu: IbMtu,
     pub service_level: u8,
-    pub rate_limit: f64,
+    pub rate_limit: IbRate,
 }
 
 pub async fn run(conf: UFMConfig, opt: &CreateOptions) -> Result<(), UFMError> {
@@ -19,7 +20,7 @@ pub async fn run(conf: UFMConfig, opt: &CreateOptions) -> Result<(), UFMError> {
     let mut pbs = vec![];
     for g in &opt.guids {
         pbs.push(PortConfig {
-            guid: g.to_string(),
+            guid: Guid::try_from(g)?,
             index0: opt.index0,
             membership: PortMembership::try_from(opt.membership.clone())?,
         })
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+
+use libufm::{Alarm, Event, EventFilter, PartitionKey, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+pub struct EventsOptions {
+    pub filter: EventFilter,
+    /// Only the events of the ports in the partition.
+    pub pkey: Option<String>,
+    pub follow: bool,
+    pub interval: Duration,
+}
+
+const EVENT_HEADERS: [&str; 7] = [
+    "ID",
+    "Timestamp",
+    "Severity",
+    "Type",
+    "Object",
+    "Name",
+    "Description",
+];
+
+const ALARM_HEADERS: [&str; 7] = [
+    "ID",
+    "Timestamp",
+    "Severity",
+    "Type",
+    "Object",
+    "Name",
+    "Reason",
+];
+
+pub async fn run(
+    conf: UFMConfig,
+    opt: EventsOptions,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+
+    let guids = match &opt.pkey {
+        None => None,
+        Some(pkey) => {
+            let ports = ufm
+                .list_port(PartitionKey::try_from(pkey.to_owned())?)
+                .await?;
+            Some(ports.iter().map(|p| p.guid.to_string()).collect::<Vec<_>>())
+        }
+    };
+    let in_partition = |e: &Event| match &guids {
+        None => true,
+        Some(guids) => [&e.object_name, &e.object_path]
+            .into_iter()
+            .flatten()
+            .any(|o| guids.iter().any(|g| o.to_lowercase().contains(g))),
+    };
+
+    if !opt.follow {
+        let events: Vec<Event> = ufm
+            .list_events(&opt.filter)
+            .await?
+            .into_iter()
+            .filter(in_partition)
+            .collect();
+        if !output::print_document(format, "EventList", &events)? {
+            let mut table = Table::new(&EVENT_HEADERS);
+            for e in events {
+                table.add_row(event_row(e));
+            }
+            table.print(format);
+        }
+
+        return Ok(());
+    }
+
+    // The events are printed as they come, so the columns of the table are fixed.
+    print_header(format, &EVENT_HEADERS);
+    let events = ufm.follow_events(opt.filter, opt.interval);
+    tokio::pin!(events);
+    while let Some(e) = events.next().await {
+        match e {
+            Ok(e) if in_partition(&e) => {
+                if !output::print_stream_document(format, "Event", &e)? {
+                    print_row(format, &event_row(e));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to poll the events of UFM: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn event_row(e: Event) -> Vec<String> {
+    vec![
+        e.id.to_string(),
+        e.timestamp,
+        e.severity.to_string(),
+        e.object_type.unwrap_or_default(),
+        e.object_name.unwrap_or_default(),
+        e.name,
+        e.description.unwrap_or_default(),
+    ]
+}
+
+fn print_header(format: OutputFormat, headers: &[&str]) {
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => {}
+        _ => print_row(format, headers),
+    }
+}
+
+fn print_row<S: AsRef<str>>(format: OutputFormat, row: &[S]) {
+    if format == OutputFormat::Csv {
+        print!("{}", output::csv_row(row));
+        return;
+    }
+
+    let cell = |i: usize| match row[i].as_ref() {
+        "" => "-",
+        c => c,
+    };
+    println!(
+        "{:<10}{:<22}{:<10}{:<8}{:<25}{:<30}{}",
+        cell(0),
+        cell(1),
+        cell(2),
+        cell(3),
+        cell(4),
+        cell(5),
+        cell(6),
+    );
+}
+
+pub async fn alarms(
+    conf: UFMConfig,
+    filter: &EventFilter,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let alarms = ufm.list_alarms(filter).await?;
+
+    if output::print_document(format, "AlarmList", &alarms)? {
+        return Ok(());
+    }
+
+    let mut table = Table::new(&ALARM_HEADERS);
+    for a in alarms {
+        table.add_row(alarm_row(a));
+    }
+    table.print(format);
+
+    Ok(())
+}
+
+fn alarm_row(a: Alarm) -> Vec<String> {
+    vec![
+        a.id.to_string(),
+        a.timestamp,
+        a.severity.to_string(),
+        a.object_type.unwrap_or_default(),
+        a.object_name.unwrap_or_default(),
+        a.name,
+        a.reason.unwrap_or_default(),
+    ]
+}
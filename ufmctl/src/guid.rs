@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use libufm::{Guid, PartitionMembership, Port, PortFilter, PortType, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+/// The port with the partitions which it's a member of, in the JSON and YAML output.
+#[derive(Serialize)]
+struct GuidView {
+    guid: Guid,
+    /// The port in UFM; `None` if UFM does not know the GUID, e.g. the VM is down.
+    port: Option<Port>,
+    partitions: Vec<PartitionMembership>,
+}
+
+pub async fn view(conf: UFMConfig, guid: &str, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let guid = Guid::try_from(guid)?;
+
+    let filter = PortFilter {
+        guids: Some([guid].into()),
+        ..PortFilter::default()
+    };
+    let mut port = ufm.list_ports(&filter).await?.pop();
+    if port.is_none() {
+        port = ufm
+            .list_vports()
+            .await?
+            .into_iter()
+            .find(|p| p.guid == guid);
+    }
+    let partitions = ufm.partitions_for_guid(guid).await?;
+
+    let view = GuidView {
+        guid,
+        port,
+        partitions,
+    };
+    if output::print_document(format, "GuidView", &view)? {
+        return Ok(());
+    }
+
+    let mut table = Table::new(&["Pkey", "Name", "Membership", "Index0", "IPoIB"]);
+    if format.is_wide() {
+        table.extend_headers(&["MTU", "Rate", "Level"]);
+    }
+    for m in view.partitions {
+        let mut row = vec![
+            m.partition.pkey.to_string(),
+            m.partition.name,
+            m.membership.to_string(),
+            m.index0.to_string(),
+            m.partition.ipoib.to_string(),
+        ];
+        if format.is_wide() {
+            row.extend([
+                m.partition.qos.mtu_limit.to_string(),
+                m.partition.qos.rate_limit.to_string(),
+                m.partition.qos.service_level.to_string(),
+            ]);
+        }
+        table.add_row(row);
+    }
+
+    // The CSV has only the partitions; the port is in its JSON or YAML.
+    if format == OutputFormat::Csv {
+        table.print(format);
+        return Ok(());
+    }
+
+    let port = view.port.unwrap_or_default();
+    let or_dash = |s: String| match s.is_empty() {
+        true => "-".to_string(),
+        false => s,
+    };
+    let port_type = match port.port_type {
+        Some(PortType::Physical) => "pf",
+        Some(PortType::Virtual) => "vf",
+        None => "-",
+    };
+
+    println!("{:15}: {}", "GUID", view.guid);
+    println!("{:15}: {}", "Name", or_dash(port.name.unwrap_or_default()));
+    println!("{:15}: {}", "PortType", port_type);
+    println!(
+        "{:15}: {}",
+        "ParentGUID",
+        or_dash(port.parent_guid.map(|g| g.to_string()).unwrap_or_default())
+    );
+    println!("{:15}: {}", "SystemName", or_dash(port.system_name));
+    println!(
+        "{:15}: {}",
+        "LID",
+        or_dash(port.lid.map(|l| l.to_string()).unwrap_or_default())
+    );
+    println!(
+        "{:15}: {}",
+        "LogState",
+        or_dash(
+            port.logical_state
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+        )
+    );
+    println!("{:15}: ", "Partitions");
+    print!("{}", table.to_table("    "));
+
+    Ok(())
+}
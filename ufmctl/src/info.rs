@@ -1,16 +1,74 @@
+use std::collections::BTreeMap;
+
 use libufm::{UFMConfig, UFMError};
 
-pub async fn run(conf: UFMConfig) -> Result<(), UFMError> {
+use crate::output::{self, OutputFormat, Table};
+
+/// Set the parameters of the SM, if any, and print the configuration; the keys of the SM are
+/// masked unless `show_secrets`.
+pub async fn run(
+    conf: UFMConfig,
+    set: &[(String, String)],
+    show_secrets: bool,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
-    let config = ufm.get_configuration().await?;
-
-    println!("subnet prefix  : {}", config.subnet_prefix);
-    println!("m_key          : {}", config.m_key);
-    println!("m_key_per_port : {}", config.m_key_per_port);
-    println!("sm_key         : {}", config.sm_key);
-    println!("sa_key         : {}", config.sa_key);
-    println!("qos            : {}", config.qos);
-    println!("log_file       : {}", config.log_file);
+    let mut config = ufm.get_configuration().await?;
+
+    if !set.is_empty() {
+        let mut params = BTreeMap::new();
+        for (key, value) in set {
+            params.insert(key.clone(), config.parse_param(key, value)?);
+        }
+        ufm.update_configuration(&params).await?;
+        config = ufm.get_configuration().await?;
+    }
+
+    if !show_secrets {
+        config = config.masked();
+    }
+
+    if output::print_document(format, "Configuration", &config)? {
+        return Ok(());
+    }
+
+    let mut params = vec![
+        ("subnet_prefix", config.subnet_prefix),
+        ("m_key", config.m_key),
+        ("m_key_per_port", config.m_key_per_port.to_string()),
+        ("sm_key", config.sm_key),
+        ("sa_key", config.sa_key),
+        ("qos", config.qos.to_string()),
+        ("log_file", config.log_file),
+    ];
+    for (key, value) in &config.other {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+        params.push((key.as_str(), value));
+    }
+
+    if format == OutputFormat::Csv {
+        let headers: Vec<&str> = params.iter().map(|(k, _)| *k).collect();
+        let mut table = Table::new(&headers);
+        table.add_row(params.into_iter().map(|(_, v)| v).collect());
+        table.print(format);
+        return Ok(());
+    }
+
+    let width = params
+        .iter()
+        .map(|(k, _)| k.len())
+        .max()
+        .unwrap_or_default();
+    for (key, value) in params {
+        let key = match key {
+            "subnet_prefix" => "subnet prefix",
+            k => k,
+        };
+        println!("{:width$} : {}", key, value);
+    }
 
     Ok(())
 }
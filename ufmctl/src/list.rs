@@ -1,25 +1,49 @@
-use libufm::{UFMConfig, UFMError};
+use std::time::Duration;
+
+use libufm::{Partition, PartitionSnapshot, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+use crate::watch;
+
+/// List the partitions; keep printing the changes every `watch` if set.
+pub async fn run(
+    conf: UFMConfig,
+    format: OutputFormat,
+    watch: Option<Duration>,
+) -> Result<(), UFMError> {
+    if watch.is_some() {
+        watch::check(format)?;
+    }
 
-pub async fn run(conf: UFMConfig) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
     let ps = ufm.list_partition().await?;
+    let snapshot = PartitionSnapshot::new(ps.clone());
 
-    println!(
-        "{:<15}{:<10}{:<10}{:<10}{:<10}{:<10}",
-        "Name", "Pkey", "IPoIB", "MTU", "Rate", "Level"
-    );
+    let printed = match watch {
+        Some(_) => output::print_stream_document(format, "PartitionList", &ps)?,
+        None => output::print_document(format, "PartitionList", &ps)?,
+    };
+    if !printed {
+        print_table(ps, format);
+    }
 
+    match watch {
+        Some(interval) => watch::run(&ufm, None, snapshot, interval, format).await,
+        None => Ok(()),
+    }
+}
+
+fn print_table(ps: Vec<Partition>, format: OutputFormat) {
+    let mut table = Table::new(&["Name", "Pkey", "IPoIB", "MTU", "Rate", "Level"]);
     for p in ps {
-        println!(
-            "{:<15}{:<10}{:<10}{:<10}{:<10}{:<10}",
+        table.add_row(vec![
             p.name,
             p.pkey.to_string(),
-            p.ipoib,
-            p.qos.mtu_limit,
-            p.qos.rate_limit,
-            p.qos.service_level
-        )
+            p.ipoib.to_string(),
+            p.qos.mtu_limit.to_string(),
+            p.qos.rate_limit.to_string(),
+            p.qos.service_level.to_string(),
+        ]);
     }
-
-    Ok(())
+    table.print(format);
 }
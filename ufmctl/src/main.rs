@@ -1,16 +1,36 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use libufm::{UFMCert, UFMConfig, UFMError};
+use clap::{Args, Parser, Subcommand};
 
+use libufm::{
+    Counter, EventFilter, Guid, IbMtu, IbRate, PortFilter, Profile, ProfileConfig, Severity,
+    SystemType, UFMConfig, UFMError,
+};
+
+mod apply;
+mod batch;
 mod bind;
 mod create;
 mod delete;
+mod events;
+mod guid;
 mod info;
 mod list;
+mod monitor;
+mod output;
+mod port;
+mod system;
+mod token;
+mod topology;
 mod unbind;
 mod update;
 mod version;
 mod view;
+mod vport;
+mod watch;
+
+use output::OutputFormat;
 
 #[derive(Parser)]
 #[command(name = "ufmctl")]
@@ -18,6 +38,12 @@ mod view;
 #[command(version = "0.1.0")]
 #[command(about = "UFM command line", long_about = None)]
 struct Options {
+    /// The config file of UFM profiles; default to ~/.config/ufmctl/config.toml
+    #[clap(long, env = "UFMCTL_CONFIG")]
+    config: Option<PathBuf>,
+    /// The profile in the config file to use; the flags and environments override its settings
+    #[clap(long, env = "UFM_PROFILE")]
+    profile: Option<String>,
     #[clap(long, env = "UFM_ADDRESS")]
     ufm_address: Option<String>,
     #[clap(long, env = "UFM_USERNAME")]
@@ -26,12 +52,25 @@ struct Options {
     ufm_password: Option<String>,
     #[clap(long, env = "UFM_TOKEN")]
     ufm_token: Option<String>,
+    /// Log in a session by the username and password, instead of sending them in every request
+    #[clap(long, env = "UFM_SESSION")]
+    ufm_session: bool,
     #[clap(long, env = "UFM_CA_CRT")]
     ufm_ca_crt: Option<String>,
     #[clap(long, env = "UFM_TLS_KEY")]
     ufm_tls_key: Option<String>,
     #[clap(long, env = "UFM_TLS_CRT")]
     ufm_tls_crt: Option<String>,
+    /// Skip the verification of the UFM certificate; insecure
+    #[clap(long, env = "UFM_INSECURE_SKIP_VERIFY")]
+    ufm_insecure_skip_verify: bool,
+    /// The server name to verify the UFM certificate against, instead of the host of the address
+    #[clap(long, env = "UFM_SERVER_NAME")]
+    ufm_server_name: Option<String>,
+    /// The format of the output of all commands; JSON and YAML are versioned documents, and the
+    /// streams, e.g. of `events --follow`, are a JSON document per line or YAML documents
+    #[clap(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
     #[clap(subcommand)]
     command: Option<Commands>,
 }
@@ -43,13 +82,25 @@ enum Commands {
         /// The pkey of the partition to view
         #[arg(short, long)]
         pkey: String,
+        #[command(flatten)]
+        watch: WatchArgs,
     },
     /// List all partitions
-    List,
+    List {
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
     /// Get the version of UFM
     Version,
     /// Get the configuration information of UFM
-    Info,
+    Info {
+        /// Set the parameter of the SM before printing, e.g. `sm_priority=1`; repeat it to set more
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_param)]
+        set: Vec<(String, String)>,
+        /// Print m_key, sm_key and sa_key in plain text instead of masking them
+        #[arg(long)]
+        show_secrets: bool,
+    },
     /// Delete the partition
     Delete {
         /// The pkey of the partition to delete
@@ -73,15 +124,15 @@ enum Commands {
         /// The GUIDs of the new partition
         #[arg(short, long)]
         guids: Vec<String>,
-        /// The MTU of the new partition
-        #[arg(long, default_value_t = 4)]
-        mtu: u16,
+        /// The MTU of the new partition in bytes, 2048 or 4096, or in KB, 2 or 4
+        #[arg(long, default_value_t = IbMtu::Mtu4096)]
+        mtu: IbMtu,
         /// The ServiceLevel of the new partition
         #[arg(short, long, default_value_t = 0)]
         service_level: u8,
-        /// The RateLimit of the new partition
-        #[arg(short, long, default_value_t = 100f64)]
-        rate_limit: f64,
+        /// The RateLimit of the new partition in Gb/s, e.g. 2.5, 100 or 200
+        #[arg(short, long, default_value_t = IbRate::Rate100)]
+        rate_limit: IbRate,
     },
     /// Update the partition
     Update {
@@ -91,15 +142,15 @@ enum Commands {
         /// The IPOverIB of the new partition
         #[arg(long, default_value_t = true)]
         ipoib: bool,
-        /// The MTU of the new partition
-        #[arg(long, default_value_t = 4)]
-        mtu: u16,
+        /// The MTU of the new partition in bytes, 2048 or 4096, or in KB, 2 or 4
+        #[arg(long, default_value_t = IbMtu::Mtu4096)]
+        mtu: IbMtu,
         /// The ServiceLevel of the new partition
         #[arg(short, long, default_value_t = 0)]
         service_level: u8,
-        /// The RateLimit of the new partition
-        #[arg(short, long, default_value_t = 100f64)]
-        rate_limit: f64,
+        /// The RateLimit of the new partition in Gb/s, e.g. 2.5, 100 or 200
+        #[arg(short, long, default_value_t = IbRate::Rate100)]
+        rate_limit: IbRate,
     },
 
     /// Bind ports to the partition
@@ -121,6 +172,244 @@ enum Commands {
         #[arg(short, long)]
         guids: Vec<String>,
     },
+
+    /// Make the partitions as declared in the file, and print the changes
+    Apply {
+        #[command(flatten)]
+        args: ApplyArgs,
+    },
+
+    /// Print the changes to make the partitions as declared in the file
+    Diff {
+        #[command(flatten)]
+        args: ApplyArgs,
+    },
+
+    /// Run the partition operations in the JSON file concurrently, e.g. to onboard many tenants
+    Batch {
+        /// The JSON file of the operations: create, delete, bind and unbind
+        #[arg(short, long)]
+        file: String,
+        /// The number of operations to run at a time
+        #[arg(short, long, default_value_t = 8)]
+        concurrency: usize,
+    },
+
+    /// Manage the physical ports in the fabric
+    Port {
+        #[command(subcommand)]
+        command: PortCommands,
+    },
+
+    /// Manage the systems in the fabric, e.g. the switches and the hosts
+    System {
+        #[command(subcommand)]
+        command: SystemCommands,
+    },
+
+    /// Export the topology of the fabric
+    Topology {
+        /// The format of the topology; `-o json` or `-o yaml` exports the versioned document instead
+        #[arg(long, value_enum, default_value_t = topology::TopologyFormat::Dot)]
+        format: topology::TopologyFormat,
+        /// The file to write the topology to, instead of stdout
+        #[arg(short, long)]
+        file: Option<String>,
+    },
+
+    /// View the ports by GUID, e.g. the partitions of the port of a VM
+    Guid {
+        #[command(subcommand)]
+        command: GuidCommands,
+    },
+
+    /// Manage the virtual ports, e.g. the SR-IOV VFs
+    Vport {
+        #[command(subcommand)]
+        command: VportCommands,
+    },
+
+    /// List the events of UFM, e.g. the links which went down
+    Events {
+        #[command(flatten)]
+        filter: EventArgs,
+        /// Only the events of the ports in the partition
+        #[arg(short, long)]
+        pkey: Option<String>,
+        /// Keep polling and printing the new events
+        #[arg(short, long)]
+        follow: bool,
+        /// The interval in seconds to poll the new events
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+
+    /// List the alarms of UFM which are not cleared
+    Alarms {
+        #[command(flatten)]
+        filter: EventArgs,
+    },
+
+    /// Monitor the counters of the ports, e.g. the bandwidth of the ports of a job
+    Monitor {
+        /// The GUIDs of the ports to monitor
+        #[arg(short, long, required = true)]
+        guids: Vec<Guid>,
+        /// The counters to monitor, e.g. xmit-data,rcv-data,symbol-errors,link-downed
+        #[arg(
+            short,
+            long,
+            value_delimiter = ',',
+            default_value = "xmit-data,rcv-data"
+        )]
+        attrs: Vec<Counter>,
+        /// The interval in seconds to sample the counters
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Stop after the number of reports, instead of running until interrupted
+        #[arg(long)]
+        count: Option<usize>,
+    },
+
+    /// Manage the access tokens of UFM
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+}
+
+#[derive(Args)]
+struct ApplyArgs {
+    /// The YAML or TOML file of the partitions with their members
+    #[arg(short, long)]
+    file: String,
+    /// Only print the changes, without applying them
+    #[arg(long)]
+    dry_run: bool,
+    /// Delete the partitions which are not declared, except the default one
+    #[arg(long)]
+    prune: bool,
+}
+
+impl From<&ApplyArgs> for apply::ApplyOptions {
+    fn from(args: &ApplyArgs) -> Self {
+        apply::ApplyOptions {
+            file: args.file.clone(),
+            dry_run: args.dry_run,
+            prune: args.prune,
+        }
+    }
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    /// Keep polling and printing the changes, e.g. the members which join or leave
+    #[arg(short, long)]
+    watch: bool,
+    /// The interval in seconds to poll the changes
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+}
+
+impl WatchArgs {
+    fn interval(&self) -> Option<Duration> {
+        self.watch.then(|| Duration::from_secs(self.interval))
+    }
+}
+
+#[derive(Args)]
+struct EventArgs {
+    /// The lowest severity: info, warning, minor or critical
+    #[arg(long)]
+    severity: Option<Severity>,
+    /// The items at or after the time, e.g. "2024-01-01 10:00:00"
+    #[arg(long)]
+    since: Option<String>,
+    /// The items before the time, e.g. "2024-01-01 11:00:00"
+    #[arg(long)]
+    until: Option<String>,
+    /// A part of the name or the path of the object, e.g. a port GUID
+    #[arg(long)]
+    object: Option<String>,
+}
+
+impl From<&EventArgs> for EventFilter {
+    fn from(args: &EventArgs) -> Self {
+        EventFilter {
+            min_severity: args.severity,
+            since: args.since.clone(),
+            until: args.until.clone(),
+            object: args.object.clone(),
+            after_id: None,
+            limit: None,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum PortCommands {
+    /// List the ports, e.g. the ports which are down or at a degraded width
+    List {
+        /// The ID or name of the system of the ports
+        #[arg(long)]
+        system: Option<String>,
+        /// The type of the system of the ports: switch, computer or gateway
+        #[arg(long)]
+        sys_type: Option<SystemType>,
+        /// Whether the logical state of the ports is Active
+        #[arg(long)]
+        active: Option<bool>,
+        /// The active speed of the ports, e.g. HDR
+        #[arg(long)]
+        speed: Option<String>,
+        /// The active width of the ports, e.g. 4x
+        #[arg(long)]
+        width: Option<String>,
+        /// The GUIDs of the ports
+        #[arg(short, long)]
+        guids: Vec<Guid>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SystemCommands {
+    /// List all systems
+    List,
+    /// View the detail of the system
+    View {
+        /// The GUID of the system to view
+        #[arg(short, long)]
+        guid: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum GuidCommands {
+    /// View the port and the partitions which it's a member of, with its membership
+    View {
+        /// The GUID of the port
+        #[arg(short, long)]
+        guid: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum VportCommands {
+    /// List all virtual ports
+    List,
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Generate an access token for the current user
+    Create,
+    /// List the access tokens of the current user
+    List,
+    /// Revoke the access token
+    Revoke {
+        /// The access token to revoke
+        token: String,
+    },
 }
 
 #[tokio::main]
@@ -129,15 +418,96 @@ async fn main() -> Result<(), UFMError> {
 
     let opt: Options = Options::parse();
 
-    let conf = load_conf(&opt);
+    let conf = load_conf(&opt)?;
     match &opt.command {
         Some(Commands::Delete { pkey }) => delete::run(conf, pkey).await?,
-        Some(Commands::Version) => version::run(conf).await?,
-        Some(Commands::Info) => info::run(conf).await?,
-        Some(Commands::List) => list::run(conf).await?,
-        Some(Commands::View { pkey }) => view::run(conf, pkey).await?,
+        Some(Commands::Version) => version::run(conf, opt.output).await?,
+        Some(Commands::Info { set, show_secrets }) => {
+            info::run(conf, set, *show_secrets, opt.output).await?
+        }
+        Some(Commands::List { watch }) => list::run(conf, opt.output, watch.interval()).await?,
+        Some(Commands::View { pkey, watch }) => {
+            view::run(conf, pkey, opt.output, watch.interval()).await?
+        }
         Some(Commands::Bind { pkey, guids }) => bind::run(conf, pkey, guids).await?,
         Some(Commands::Unbind { pkey, guids }) => unbind::run(conf, pkey, guids).await?,
+        Some(Commands::Apply { args }) => apply::run(conf, &args.into(), opt.output).await?,
+        Some(Commands::Diff { args }) => apply::diff(conf, &args.into(), opt.output).await?,
+        Some(Commands::Batch { file, concurrency }) => {
+            batch::run(conf, file, *concurrency, opt.output).await?
+        }
+        Some(Commands::Port { command }) => match command {
+            PortCommands::List {
+                system,
+                sys_type,
+                active,
+                speed,
+                width,
+                guids,
+            } => {
+                let filter = PortFilter {
+                    system: system.clone(),
+                    sys_type: *sys_type,
+                    active: *active,
+                    speed: speed.clone(),
+                    width: width.clone(),
+                    guids: match guids.is_empty() {
+                        true => None,
+                        false => Some(guids.iter().cloned().collect()),
+                    },
+                };
+                port::list(conf, &filter, opt.output).await?
+            }
+        },
+        Some(Commands::System { command }) => match command {
+            SystemCommands::List => system::list(conf, opt.output).await?,
+            SystemCommands::View { guid } => system::view(conf, guid, opt.output).await?,
+        },
+        Some(Commands::Topology { format, file }) => {
+            topology::run(conf, *format, opt.output, file.as_deref()).await?
+        }
+        Some(Commands::Guid { command }) => match command {
+            GuidCommands::View { guid } => guid::view(conf, guid, opt.output).await?,
+        },
+        Some(Commands::Vport { command }) => match command {
+            VportCommands::List => vport::list(conf, opt.output).await?,
+        },
+        Some(Commands::Events {
+            filter,
+            pkey,
+            follow,
+            interval,
+        }) => {
+            let events_opt = events::EventsOptions {
+                filter: filter.into(),
+                pkey: pkey.clone(),
+                follow: *follow,
+                interval: Duration::from_secs(*interval),
+            };
+            events::run(conf, events_opt, opt.output).await?
+        }
+        Some(Commands::Alarms { filter }) => {
+            events::alarms(conf, &filter.into(), opt.output).await?
+        }
+        Some(Commands::Monitor {
+            guids,
+            attrs,
+            interval,
+            count,
+        }) => {
+            let monitor_opt = monitor::MonitorOptions {
+                guids: guids.clone(),
+                counters: attrs.clone(),
+                interval: Duration::from_secs(*interval),
+                count: *count,
+            };
+            monitor::run(conf, &monitor_opt, opt.output).await?
+        }
+        Some(Commands::Token { command }) => match command {
+            TokenCommands::Create => token::create(conf, opt.output).await?,
+            TokenCommands::List => token::list(conf, opt.output).await?,
+            TokenCommands::Revoke { token } => token::revoke(conf, token).await?,
+        },
         Some(Commands::Update {
             pkey,
             mtu,
@@ -183,28 +553,87 @@ async fn main() -> Result<(), UFMError> {
     Ok(())
 }
 
-fn load_conf(opt: &Options) -> UFMConfig {
-    let ufm_address = match opt.ufm_address.clone() {
-        Some(s) => s,
-        None => panic!("UFM_ADDRESS environment or ufm_address parameter not found"),
-    };
+fn load_conf(opt: &Options) -> Result<UFMConfig, UFMError> {
+    let mut profile = load_profile(opt)?;
+
+    if opt.ufm_address.is_some() {
+        profile.address = opt.ufm_address.clone();
+    }
 
-    let cert = if opt.ufm_ca_crt.is_some() && opt.ufm_tls_key.is_some() && opt.ufm_tls_crt.is_some()
+    // The authentication from the flags replaces the one of the profile.
+    if opt.ufm_token.is_some()
+        || opt.ufm_username.is_some()
+        || opt.ufm_password.is_some()
+        || opt.ufm_tls_key.is_some()
+        || opt.ufm_tls_crt.is_some()
     {
-        Some(UFMCert {
-            ca_crt: opt.ufm_ca_crt.clone().unwrap(),
-            tls_key: opt.ufm_tls_key.clone().unwrap(),
-            tls_crt: opt.ufm_tls_crt.clone().unwrap(),
-        })
-    } else {
-        None
+        profile.token = opt.ufm_token.clone();
+        profile.username = opt.ufm_username.clone();
+        profile.password = opt.ufm_password.clone();
+        profile.tls_key = opt.ufm_tls_key.clone();
+        profile.tls_crt = opt.ufm_tls_crt.clone();
+    }
+
+    if opt.ufm_session {
+        profile.session = true;
+    }
+    if opt.ufm_ca_crt.is_some() {
+        profile.ca_crt = opt.ufm_ca_crt.clone();
+    }
+    if opt.ufm_insecure_skip_verify {
+        profile.insecure_skip_verify = true;
+    }
+    if opt.ufm_server_name.is_some() {
+        profile.server_name = opt.ufm_server_name.clone();
+    }
+
+    if profile.address.is_none() {
+        return Err(UFMError::InvalidConfig(
+            "UFM_ADDRESS environment, ufm_address parameter or address of the profile not found"
+                .to_string(),
+        ));
+    }
+
+    profile.builder()?.build()
+}
+
+fn load_profile(opt: &Options) -> Result<Profile, UFMError> {
+    let path = match (&opt.config, default_config_path()) {
+        (Some(path), _) => path.clone(),
+        (None, Some(path)) if path.exists() => path,
+        (None, _) => {
+            return match &opt.profile {
+                Some(name) => Err(UFMError::InvalidConfig(format!(
+                    "profile '{}' not found; no config file",
+                    name
+                ))),
+                None => Ok(Profile::default()),
+            }
+        }
+    };
+
+    let conf = ProfileConfig::load(path)?;
+    let profile = match &opt.profile {
+        Some(name) => Some(conf.get(name)?),
+        None => conf.default_profile()?,
     };
 
-    UFMConfig {
-        address: ufm_address,
-        username: opt.ufm_username.clone(),
-        password: opt.ufm_password.clone(),
-        token: opt.ufm_token.clone(),
-        cert,
+    Ok(profile.cloned().unwrap_or_default())
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(dir.join("ufmctl").join("config.toml"))
+}
+
+/// Parse a parameter of the SM as `KEY=VALUE`.
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid parameter '{}', expected KEY=VALUE", s)),
     }
 }
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use libufm::{Counter, CounterSample, Guid, MonitoringSession, UFMConfig, UFMError, Ufm};
+
+use crate::output::{self, OutputFormat};
+
+pub struct MonitorOptions {
+    pub guids: Vec<Guid>,
+    pub counters: Vec<Counter>,
+    pub interval: Duration,
+    /// Stop after the number of reports; run until interrupted if not set.
+    pub count: Option<usize>,
+}
+
+/// The rate of a counter of a port, in the JSON, YAML and CSV output.
+#[derive(Serialize)]
+struct CounterRate<'a> {
+    /// The time of the sample, in seconds since the epoch.
+    timestamp: f64,
+    guid: Guid,
+    port_name: &'a str,
+    counter: Counter,
+    /// The rate per second; the data counters are in bytes per second.
+    rate: f64,
+}
+
+pub async fn run(
+    conf: UFMConfig,
+    opt: &MonitorOptions,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let session = ufm
+        .create_monitoring_session(&opt.guids, &opt.counters, opt.interval)
+        .await?;
+
+    let res = tokio::select! {
+        res = report(&ufm, &session, opt, format) => res,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    // Do not leave the session sampling in UFM.
+    ufm.delete_monitoring_session(&session).await?;
+
+    res
+}
+
+async fn report(
+    ufm: &Ufm,
+    session: &MonitoringSession,
+    opt: &MonitorOptions,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => {}
+        OutputFormat::Csv => print!(
+            "{}",
+            output::csv_row(&["Timestamp", "GUID", "Port", "Counter", "Rate"])
+        ),
+        _ => println!(
+            "{:<20}{:<20}{:<24}{:<22}{:<15}",
+            "Timestamp", "GUID", "Port", "Counter", "Rate"
+        ),
+    }
+
+    let mut prev: HashMap<Guid, CounterSample> = HashMap::new();
+    let mut reports = 0;
+    loop {
+        let samples = match ufm.monitoring_data(session).await {
+            Ok(samples) => samples,
+            Err(e) => {
+                eprintln!("Failed to poll the counters of UFM: {}", e);
+                vec![]
+            }
+        };
+
+        let mut reported = false;
+        for s in samples {
+            if let Some(p) = prev.get(&s.guid) {
+                for c in &opt.counters {
+                    if let Some(rate) = s.rate(p, *c) {
+                        let r = CounterRate {
+                            timestamp: s.timestamp,
+                            guid: s.guid,
+                            port_name: &s.port_name,
+                            counter: *c,
+                            rate,
+                        };
+                        print_rate(&r, format)?;
+                        reported = true;
+                    }
+                }
+            }
+            // Keep the previous sample until UFM samples again.
+            if prev.get(&s.guid).is_none_or(|p| s.timestamp > p.timestamp) {
+                prev.insert(s.guid, s);
+            }
+        }
+
+        if reported {
+            reports += 1;
+            if opt.count.is_some_and(|c| reports >= c) {
+                return Ok(());
+            }
+        }
+
+        tokio::time::sleep(opt.interval).await;
+    }
+}
+
+fn print_rate(r: &CounterRate, format: OutputFormat) -> Result<(), UFMError> {
+    if output::print_stream_document(format, "CounterRate", r)? {
+        return Ok(());
+    }
+
+    match format {
+        OutputFormat::Csv => print!(
+            "{}",
+            output::csv_row(&[
+                r.timestamp.to_string(),
+                r.guid.to_string(),
+                r.port_name.to_string(),
+                r.counter.to_string(),
+                r.rate.to_string(),
+            ])
+        ),
+        _ => println!(
+            "{:<20}{:<20}{:<24}{:<22}{:<15}",
+            r.timestamp,
+            r.guid,
+            r.port_name,
+            r.counter,
+            format_rate(r.counter, r.rate)
+        ),
+    }
+
+    Ok(())
+}
+
+/// The data in Gb/s, and the other counters per second.
+fn format_rate(counter: Counter, rate: f64) -> String {
+    match counter.is_data() {
+        true => format!("{:.2} Gb/s", rate * 8.0 / 1e9),
+        false => format!("{:.2}/s", rate),
+    }
+}
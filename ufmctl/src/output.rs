@@ -0,0 +1,217 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use libufm::UFMError;
+
+/// The version of the schema of the JSON and YAML output; it's changed only for incompatible
+/// changes, e.g. a field is removed or renamed.
+pub const API_VERSION: &str = "ufmctl/v1";
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The aligned columns for humans
+    #[default]
+    Table,
+    /// The table with the additional columns
+    Wide,
+    /// The versioned document in JSON
+    Json,
+    /// The versioned document in YAML
+    Yaml,
+    /// The header and the rows with all the columns of the wide table
+    Csv,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(v) => f.write_str(v.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Whether the additional columns are printed.
+    pub fn is_wide(&self) -> bool {
+        matches!(self, OutputFormat::Wide | OutputFormat::Csv)
+    }
+}
+
+/// The output in JSON or YAML, e.g.
+///
+/// ```json
+/// {"apiVersion": "ufmctl/v1", "kind": "PartitionList", "data": [...]}
+/// ```
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Document<'a, T: Serialize> {
+    api_version: &'static str,
+    kind: &'a str,
+    data: &'a T,
+}
+
+/// Print the data of the kind as a versioned document if the format is JSON or YAML; return
+/// `false` for the other formats, which are printed by the command.
+pub fn print_document<T: Serialize>(
+    format: OutputFormat,
+    kind: &str,
+    data: &T,
+) -> Result<bool, UFMError> {
+    match to_document(format, kind, data, true)? {
+        Some(out) => print!("{}", out),
+        None => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Print an item of a stream, e.g. a new event, as a versioned document if the format is JSON
+/// or YAML: a line of JSON, or a YAML document which starts with `---`, so the stream can be
+/// parsed item by item. Return `false` for the other formats.
+pub fn print_stream_document<T: Serialize>(
+    format: OutputFormat,
+    kind: &str,
+    data: &T,
+) -> Result<bool, UFMError> {
+    let out = match to_document(format, kind, data, false)? {
+        Some(out) if format == OutputFormat::Yaml => format!("---\n{}", out),
+        Some(out) => out,
+        None => return Ok(false),
+    };
+    print!("{}", out);
+
+    Ok(true)
+}
+
+/// The versioned document of the data in JSON or YAML; `None` for the other formats.
+pub fn to_document<T: Serialize>(
+    format: OutputFormat,
+    kind: &str,
+    data: &T,
+    pretty: bool,
+) -> Result<Option<String>, UFMError> {
+    let doc = Document {
+        api_version: API_VERSION,
+        kind,
+        data,
+    };
+
+    let out = match format {
+        OutputFormat::Json if pretty => {
+            serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+        }
+        OutputFormat::Json => serde_json::to_string(&doc).map_err(|e| e.to_string()),
+        OutputFormat::Yaml => to_yaml(&doc).map_err(|e| e.to_string()),
+        _ => return Ok(None),
+    }
+    .map_err(|e| UFMError::Internal(format!("failed to print {}: {}", kind, e)))?;
+
+    match format {
+        OutputFormat::Json => Ok(Some(out + "\n")),
+        _ => Ok(Some(out)),
+    }
+}
+
+/// The YAML of the data, whose enums are maps of the variants as in JSON, e.g. `delete: 0x7`
+/// instead of the tag `!delete 0x7`.
+fn to_yaml<T: Serialize>(data: &T) -> Result<String, serde_yaml::Error> {
+    let mut out = vec![];
+    let mut serializer = serde_yaml::Serializer::new(&mut out);
+    serde_yaml::with::singleton_map_recursive::serialize(data, &mut serializer)?;
+
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// The error of the format which the command can't print, e.g. the CSV of a topology.
+pub fn unsupported(format: OutputFormat, command: &str) -> UFMError {
+    UFMError::InvalidConfig(format!("{} output is not supported by {}", format, command))
+}
+
+/// The rows of a table or a CSV; the empty cells are printed as `-` in the table.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: vec![],
+        }
+    }
+
+    /// Add the headers of the additional columns.
+    pub fn extend_headers(&mut self, headers: &[&str]) {
+        self.headers.extend(headers.iter().map(|h| h.to_string()));
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Print the table, or the CSV if the format is CSV.
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Csv => print!("{}", self.to_csv()),
+            _ => print!("{}", self.to_table("")),
+        }
+    }
+
+    /// The columns are as wide as their widest cells, so the long names are never cut or joined.
+    pub fn to_table(&self, indent: &str) -> String {
+        let cell = |c: &str| match c.is_empty() {
+            true => "-".to_string(),
+            false => c.to_string(),
+        };
+
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (i, c) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell(c).len());
+            }
+        }
+
+        let line = |cells: Vec<String>| {
+            let l: String = cells
+                .iter()
+                .zip(&widths)
+                .map(|(c, w)| format!("{:<w$}  ", c, w = w))
+                .collect();
+            format!("{}{}\n", indent, l.trim_end())
+        };
+
+        let mut out = line(self.headers.clone());
+        for row in &self.rows {
+            out.push_str(&line(row.iter().map(|c| cell(c)).collect()));
+        }
+
+        out
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = csv_row(&self.headers);
+        for row in &self.rows {
+            out.push_str(&csv_row(row));
+        }
+
+        out
+    }
+}
+
+/// A line of CSV, e.g. to print a row of a stream.
+pub fn csv_row<S: AsRef<str>>(cells: &[S]) -> String {
+    let l: Vec<String> = cells.iter().map(|c| csv_field(c.as_ref())).collect();
+    l.join(",") + "\n"
+}
+
+/// Quote the field if it has a comma, a quote or a line break, as RFC 4180.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
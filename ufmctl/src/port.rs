@@ -0,0 +1,70 @@
+use libufm::{PortFilter, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+pub async fn list(
+    conf: UFMConfig,
+    filter: &PortFilter,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let ports = ufm.list_ports(filter).await?;
+
+    if output::print_document(format, "PortList", &ports)? {
+        return Ok(());
+    }
+
+    let mut table = Table::new(&[
+        "GUID",
+        "Name",
+        "SystemName",
+        "LID",
+        "LogState",
+        "PhyState",
+        "Speed",
+        "Width",
+        "Rate",
+        "MTU",
+        "PeerGUID",
+        "PeerPort",
+        "Firmware",
+    ]);
+    if format.is_wide() {
+        table.extend_headers(&["SystemID", "PeerNode", "ExternalNumber"]);
+    }
+
+    for port in ports {
+        let mut row = vec![
+            port.guid.to_string(),
+            port.name.clone().unwrap_or_default(),
+            port.system_name.clone(),
+            port.lid.map(|l| l.to_string()).unwrap_or_default(),
+            port.logical_state
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            port.physical_state
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            port.active_speed.clone().unwrap_or_default(),
+            port.active_width.clone().unwrap_or_default(),
+            port.rate().map(|r| r.to_string()).unwrap_or_default(),
+            port.mtu.map(|m| m.to_string()).unwrap_or_default(),
+            port.peer_guid.map(|g| g.to_string()).unwrap_or_default(),
+            port.peer_port.clone().unwrap_or_default(),
+            port.firmware_version.clone().unwrap_or_default(),
+        ];
+        if format.is_wide() {
+            row.extend([
+                port.system_id.clone(),
+                port.peer_node_name.clone().unwrap_or_default(),
+                port.external_number
+                    .map(|n| n.to_string())
+                    .unwrap_or_default(),
+            ]);
+        }
+        table.add_row(row);
+    }
+    table.print(format);
+
+    Ok(())
+}
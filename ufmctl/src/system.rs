@@ -0,0 +1,82 @@
+use libufm::{Guid, System, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+pub async fn list(conf: UFMConfig, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let systems = ufm.list_systems().await?;
+
+    if output::print_document(format, "SystemList", &systems)? {
+        return Ok(());
+    }
+
+    systems_table(systems, format).print(format);
+
+    Ok(())
+}
+
+pub async fn view(conf: UFMConfig, guid: &str, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let s = ufm.get_system(Guid::try_from(guid)?).await?;
+
+    if output::print_document(format, "System", &s)? {
+        return Ok(());
+    }
+    if format == OutputFormat::Csv {
+        systems_table(vec![s], format).print(format);
+        return Ok(());
+    }
+
+    println!("{:15}: {}", "GUID", s.guid);
+    println!("{:15}: {}", "Name", s.system_name);
+    println!("{:15}: {}", "Type", s.system_type);
+    println!(
+        "{:15}: {}",
+        "Description",
+        s.description.unwrap_or_default()
+    );
+    println!("{:15}: {}", "Model", s.model.unwrap_or_default());
+    println!("{:15}: {}", "Vendor", s.vendor.unwrap_or_default());
+    println!(
+        "{:15}: {}",
+        "Firmware",
+        s.firmware_version.unwrap_or_default()
+    );
+    println!("{:15}: {}", "IP", s.ip.unwrap_or_default());
+    println!("{:15}: {}", "State", s.state.unwrap_or_default());
+    println!("{:15}: ", "Ports");
+    for port in s.ports {
+        println!("    {}", port);
+    }
+
+    Ok(())
+}
+
+fn systems_table(systems: Vec<System>, format: OutputFormat) -> Table {
+    let mut table = Table::new(&[
+        "GUID", "Name", "Type", "Model", "Vendor", "Firmware", "IP", "State", "Ports",
+    ]);
+    if format.is_wide() {
+        table.extend_headers(&["Description"]);
+    }
+
+    for s in systems {
+        let mut row = vec![
+            s.guid.to_string(),
+            s.system_name,
+            s.system_type,
+            s.model.unwrap_or_default(),
+            s.vendor.unwrap_or_default(),
+            s.firmware_version.unwrap_or_default(),
+            s.ip.unwrap_or_default(),
+            s.state.unwrap_or_default(),
+            s.ports.len().to_string(),
+        ];
+        if format.is_wide() {
+            row.push(s.description.unwrap_or_default());
+        }
+        table.add_row(row);
+    }
+
+    table
+}
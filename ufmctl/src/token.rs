@@ -0,0 +1,49 @@
+use libufm::{AccessToken, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+pub async fn create(conf: UFMConfig, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let token = ufm.create_token().await?;
+
+    if output::print_document(format, "AccessToken", &token)? {
+        return Ok(());
+    }
+
+    match format {
+        OutputFormat::Csv => print_table(vec![token], format),
+        _ => println!("{}", token.access_token),
+    }
+
+    Ok(())
+}
+
+pub async fn list(conf: UFMConfig, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let tokens = ufm.list_tokens().await?;
+
+    if !output::print_document(format, "AccessTokenList", &tokens)? {
+        print_table(tokens, format);
+    }
+
+    Ok(())
+}
+
+fn print_table(tokens: Vec<AccessToken>, format: OutputFormat) {
+    let mut table = Table::new(&["Token", "Revoked", "CreationDate"]);
+    for t in tokens {
+        table.add_row(vec![
+            t.access_token,
+            t.revoked.to_string(),
+            t.creation_date.unwrap_or_default(),
+        ]);
+    }
+    table.print(format);
+}
+
+pub async fn revoke(conf: UFMConfig, token: &str) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    ufm.revoke_token(token).await?;
+
+    Ok(())
+}
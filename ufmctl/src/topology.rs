@@ -0,0 +1,45 @@
+use clap::ValueEnum;
+
+use libufm::{UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TopologyFormat {
+    Dot,
+    Json,
+}
+
+/// Export the topology in the format, or as a versioned document if the output is JSON or YAML.
+pub async fn run(
+    conf: UFMConfig,
+    format: TopologyFormat,
+    output: OutputFormat,
+    file: Option<&str>,
+) -> Result<(), UFMError> {
+    // The topology is a graph, which has no rows.
+    if output == OutputFormat::Csv {
+        return Err(output::unsupported(output, "topology"));
+    }
+
+    let ufm = libufm::connect(conf)?;
+    let topo = ufm.topology().await?;
+
+    let data = match (
+        output::to_document(output, "Topology", &topo, true)?,
+        format,
+    ) {
+        (Some(doc), _) => doc,
+        (None, TopologyFormat::Dot) => topo.to_dot(),
+        (None, TopologyFormat::Json) => topo.to_json()?,
+    };
+
+    match file {
+        None => print!("{}", data),
+        Some(path) => std::fs::write(path, data).map_err(|e| {
+            UFMError::Internal(format!("failed to write topology to '{}': {}", path, e))
+        })?,
+    }
+
+    Ok(())
+}
@@ -1,11 +1,16 @@
-use libufm::{PartitionKey, UFMConfig, UFMError};
+use libufm::{Guid, PartitionKey, UFMConfig, UFMError};
 
 pub async fn run(conf: UFMConfig, pkey: &str, guids: &[String]) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
 
     let p = PartitionKey::try_from(pkey.to_owned())?;
 
-    ufm.unbind_ports(p, guids.to_owned()).await?;
+    let guids = guids
+        .iter()
+        .map(Guid::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    ufm.unbind_ports(p, guids).await?;
 
     Ok(())
 }
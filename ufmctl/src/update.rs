@@ -1,11 +1,11 @@
-use libufm::{Partition, PartitionKey, PartitionQoS, UFMConfig, UFMError};
+use libufm::{IbMtu, IbRate, Partition, PartitionKey, PartitionQoS, UFMConfig, UFMError};
 
 pub struct UpdateOptions {
     pub pkey: String,
-    pub mtu: u16,
+    pub mtu: IbMtu,
     pub ipoib: bool,
     pub service_level: u8,
-    pub rate_limit: f64,
+    pub rate_limit: IbRate,
 }
 
 pub async fn run(conf: UFMConfig, opt: &UpdateOptions) -> Result<(), UFMError> {
@@ -1,10 +1,30 @@
+use serde::Serialize;
+
 use libufm::{UFMConfig, UFMError};
 
-pub async fn run(conf: UFMConfig) -> Result<(), UFMError> {
+use crate::output::{self, OutputFormat, Table};
+
+#[derive(Serialize)]
+struct Version {
+    version: String,
+}
+
+pub async fn run(conf: UFMConfig, format: OutputFormat) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
     let v = ufm.version().await?;
 
-    println!("{}", v);
+    if output::print_document(format, "Version", &Version { version: v.clone() })? {
+        return Ok(());
+    }
+
+    match format {
+        OutputFormat::Csv => {
+            let mut table = Table::new(&["Version"]);
+            table.add_row(vec![v]);
+            table.print(format);
+        }
+        _ => println!("{}", v),
+    }
 
     Ok(())
 }
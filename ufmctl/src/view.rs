@@ -1,48 +1,115 @@
-use libufm::{PortType, UFMConfig, UFMError};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use libufm::{Partition, PartitionSnapshot, Port, PortType, UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+use crate::watch;
+
+/// The partition with its ports, in the JSON and YAML output.
+#[derive(Serialize)]
+struct PartitionView {
+    #[serde(flatten)]
+    partition: Partition,
+    ports: Vec<Port>,
+}
+
+/// View the partition with its ports; keep printing the changes of the partition and its
+/// members every `watch` if set.
+pub async fn run(
+    conf: UFMConfig,
+    pkey: &str,
+    format: OutputFormat,
+    watch: Option<Duration>,
+) -> Result<(), UFMError> {
+    if watch.is_some() {
+        watch::check(format)?;
+    }
 
-pub async fn run(conf: UFMConfig, pkey: &str) -> Result<(), UFMError> {
     let ufm = libufm::connect(conf)?;
     let p = ufm.get_partition(pkey).await?;
     let ps = ufm.list_port(p.pkey).await?;
 
-    println!("{:15}: {}", "Name", p.name);
-    println!("{:15}: {}", "Pkey", pkey);
-    println!("{:15}: {}", "IPoIB", p.ipoib);
-    println!("{:15}: {}", "MTU", p.qos.mtu_limit);
-    println!("{:15}: {}", "Rate Limit", p.qos.rate_limit);
-    println!("{:15}: {}", "Service Level", p.qos.service_level);
-    println!("{:15}: ", "Ports");
+    let pkey = p.pkey;
+    let mut snapshot = PartitionSnapshot::new(vec![p.clone()]);
+    snapshot.add_ports(pkey, ps.clone());
 
-    println!(
-        "    {:<20}{:<20}{:<10}{:<20}{:<10}{:<10}{:<20}{:<15}",
-        "GUID", "ParentGUID", "PortType", "SystemID", "LID", "LogState", "Name", "SystemName",
-    );
-    for port in ps {
-        let name = match port.name.clone() {
-            Some(n) => n,
-            None => "-".to_string(),
-        };
-        let parent_guid = match port.parent_guid.clone() {
-            Some(p) => p,
-            None => "-".to_string(),
-        };
+    let view = PartitionView {
+        partition: p,
+        ports: ps,
+    };
+    let printed = match watch {
+        Some(_) => output::print_stream_document(format, "Partition", &view)?,
+        None => output::print_document(format, "Partition", &view)?,
+    };
+    if !printed {
+        print_view(view, format);
+    }
+
+    match watch {
+        Some(interval) => watch::run(&ufm, Some(pkey), snapshot, interval, format).await,
+        None => Ok(()),
+    }
+}
+
+fn print_view(view: PartitionView, format: OutputFormat) {
+    let mut table = Table::new(&[
+        "GUID",
+        "ParentGUID",
+        "PortType",
+        "SystemID",
+        "LID",
+        "LogState",
+        "Name",
+        "SystemName",
+    ]);
+    if format.is_wide() {
+        table.extend_headers(&["PhyState", "Speed", "Width"]);
+    }
+    for port in view.ports {
         let port_type = match port.port_type {
             Some(PortType::Physical) => "pf".to_string(),
             Some(PortType::Virtual) => "vf".to_string(),
-            None => "-".to_string(),
+            None => "".to_string(),
         };
-        println!(
-            "    {:<20}{:<20}{:<10}{:<20}{:<10}{:<10}{:<20}{:<15}",
-            port.guid,
-            parent_guid,
+        let mut row = vec![
+            port.guid.to_string(),
+            port.parent_guid.map(|p| p.to_string()).unwrap_or_default(),
             port_type,
             port.system_id,
-            port.lid,
-            port.logical_state,
-            name,
+            port.lid.map(|l| l.to_string()).unwrap_or_default(),
+            port.logical_state
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            port.name.unwrap_or_default(),
             port.system_name,
-        )
+        ];
+        if format.is_wide() {
+            row.extend([
+                port.physical_state
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                port.active_speed.unwrap_or_default(),
+                port.active_width.unwrap_or_default(),
+            ]);
+        }
+        table.add_row(row);
+    }
+
+    // The CSV has only the ports; the partition is in its JSON or YAML.
+    if format == OutputFormat::Csv {
+        table.print(format);
+        return;
     }
 
-    Ok(())
+    let p = view.partition;
+    println!("{:15}: {}", "Name", p.name);
+    println!("{:15}: {}", "Pkey", p.pkey);
+    println!("{:15}: {}", "IPoIB", p.ipoib);
+    println!("{:15}: {}", "MTU", p.qos.mtu_limit);
+    println!("{:15}: {}", "Rate Limit", p.qos.rate_limit);
+    println!("{:15}: {}", "Service Level", p.qos.service_level);
+    println!("{:15}: ", "Ports");
+    print!("{}", table.to_table("    "));
 }
@@ -0,0 +1,36 @@
+use libufm::{UFMConfig, UFMError};
+
+use crate::output::{self, OutputFormat, Table};
+
+pub async fn list(conf: UFMConfig, format: OutputFormat) -> Result<(), UFMError> {
+    let ufm = libufm::connect(conf)?;
+    let vports = ufm.list_vports().await?;
+
+    if output::print_document(format, "VirtualPortList", &vports)? {
+        return Ok(());
+    }
+
+    let mut table = Table::new(&[
+        "GUID",
+        "ParentGUID",
+        "SystemID",
+        "LID",
+        "LogState",
+        "SystemName",
+    ]);
+    for port in vports {
+        table.add_row(vec![
+            port.guid.to_string(),
+            port.parent_guid.map(|p| p.to_string()).unwrap_or_default(),
+            port.system_id,
+            port.lid.map(|l| l.to_string()).unwrap_or_default(),
+            port.logical_state
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            port.system_name,
+        ]);
+    }
+    table.print(format);
+
+    Ok(())
+}
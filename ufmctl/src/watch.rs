@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+
+use libufm::{PartitionKey, PartitionSnapshot, UFMError, Ufm};
+
+use crate::output::{self, OutputFormat};
+
+/// The CSV is only the snapshot, which can't be followed by the changes.
+pub fn check(format: OutputFormat) -> Result<(), UFMError> {
+    match format {
+        OutputFormat::Csv => Err(output::unsupported(format, "--watch")),
+        _ => Ok(()),
+    }
+}
+
+/// Print the changes of the partitions since the snapshot until interrupted; each change is a
+/// document of the stream in JSON or YAML, after the one of the snapshot.
+pub async fn run(
+    ufm: &Ufm,
+    pkey: Option<PartitionKey>,
+    from: PartitionSnapshot,
+    interval: Duration,
+    format: OutputFormat,
+) -> Result<(), UFMError> {
+    let changes = ufm.watch_partitions(pkey, from, interval);
+    tokio::pin!(changes);
+    while let Some(c) = changes.next().await {
+        match c {
+            Ok(c) => {
+                if !output::print_stream_document(format, "PartitionChange", &c)? {
+                    println!("{}", c);
+                }
+            }
+            Err(e) => eprintln!("Failed to poll the partitions of UFM: {}", e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1022 @@
+use std::time::Duration;
+
+use hyper::{Method, StatusCode};
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
+
+use ufmmock::{MockMember, MockPartition, MockUfm};
+
+const GUID_PF: &str = "1070fd0300176625";
+const GUID_VF: &str = "0011223344560200";
+
+async fn start() -> MockUfm {
+    let mock = MockUfm::start().await.unwrap();
+
+    mock.add_port(
+        "Computer",
+        json!({
+            "guid": GUID_PF,
+            "name": "1070fd0300176625_2",
+            "systemID": "1070fd0300176624",
+            "lid": 4,
+            "system_name": "hpc-cloud01",
+            "logical_state": "Active",
+        }),
+    );
+
+    mock.add_vport(json!({
+        "virtual_port_guid": GUID_VF,
+        "system_guid": "1070fd0300176624",
+        "virtual_port_lid": 7,
+        "system_name": "hpc-cloud01",
+        "virtual_port_state": "Active",
+        "port_guid": GUID_PF,
+    }));
+
+    let mut p = MockPartition::new("api_pkey_0x5");
+    p.members.insert(
+        GUID_PF.to_string(),
+        MockMember {
+            membership: "full".to_string(),
+            index0: true,
+        },
+    );
+    mock.add_partition(0x5, p);
+
+    mock
+}
+
+/// A ufmctl command without any UFM settings from the environment or the config file.
+fn command(args: &[&str]) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_ufmctl"));
+    cmd.args(args)
+        .env(
+            "XDG_CONFIG_HOME",
+            std::env::temp_dir().join("ufmctl-nonexistent"),
+        )
+        .env_remove("UFMCTL_CONFIG")
+        .env_remove("UFM_PROFILE")
+        .env_remove("UFM_ADDRESS")
+        .env_remove("UFM_USERNAME")
+        .env_remove("UFM_PASSWORD")
+        .env_remove("UFM_TOKEN")
+        .env_remove("UFM_SESSION")
+        .env_remove("UFM_CA_CRT")
+        .env_remove("UFM_TLS_KEY")
+        .env_remove("UFM_TLS_CRT")
+        .env_remove("UFM_INSECURE_SKIP_VERIFY")
+        .env_remove("UFM_SERVER_NAME");
+    cmd
+}
+
+/// Run the command, and return its stdout.
+async fn run(mut cmd: Command) -> String {
+    let output = cmd.output().await.unwrap();
+
+    assert!(
+        output.status.success(),
+        "ufmctl failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run ufmctl against the mock UFM, and return its stdout.
+async fn ufmctl(mock: &MockUfm, args: &[&str]) -> String {
+    let mut cmd = command(args);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+
+    run(cmd).await
+}
+
+/// Run ufmctl against the mock UFM, which is expected to fail, and return its stderr.
+async fn ufmctl_err(mock: &MockUfm, args: &[&str]) -> String {
+    let mut cmd = command(args);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[tokio::test]
+async fn test_version() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["version"]).await;
+    assert_eq!(out.trim(), ufmmock::UFM_VERSION);
+}
+
+#[tokio::test]
+async fn test_version_over_tls() {
+    let mock = MockUfm::start_tls("ufm.example.com").await.unwrap();
+
+    let ca_crt = std::env::temp_dir().join(format!("ufmctl-{}-ca.crt", std::process::id()));
+    std::fs::write(&ca_crt, mock.ca_crt().unwrap()).unwrap();
+    let ca_crt = ca_crt.to_string_lossy().to_string();
+
+    let args = [
+        "--ufm-ca-crt",
+        &ca_crt,
+        "--ufm-server-name",
+        "ufm.example.com",
+        "version",
+    ];
+    let out = ufmctl(&mock, &args).await;
+    assert_eq!(out.trim(), ufmmock::UFM_VERSION);
+
+    let out = ufmctl(&mock, &["--ufm-insecure-skip-verify", "version"]).await;
+    assert_eq!(out.trim(), ufmmock::UFM_VERSION);
+}
+
+#[tokio::test]
+async fn test_profile() {
+    let mock = start().await;
+
+    let dir = std::env::temp_dir().join(format!("ufmctl-profile-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("ufmctl")).unwrap();
+    std::fs::write(
+        dir.join("ufmctl").join("config.toml"),
+        format!(
+            r#"
+default = "lab"
+
+[profiles.lab]
+address = "{address}"
+username = "admin"
+password = "123456"
+
+[profiles.prod]
+address = "{address}"
+token = "XlojlA7zgotVegyIEIP5vnw5C7ZYT9"
+"#,
+            address = mock.address()
+        ),
+    )
+    .unwrap();
+
+    // The default profile in the default config file.
+    let mut cmd = command(&["version"]);
+    cmd.env("XDG_CONFIG_HOME", &dir);
+    assert_eq!(run(cmd).await.trim(), ufmmock::UFM_VERSION);
+    let req = mock.last_request().unwrap();
+    assert!(req.authorization.unwrap().starts_with("Basic "));
+
+    let config = dir.join("ufmctl").join("config.toml");
+    let config = config.to_str().unwrap();
+
+    let cmd = command(&["--config", config, "--profile", "prod", "version"]);
+    assert_eq!(run(cmd).await.trim(), ufmmock::UFM_VERSION);
+    let req = mock.last_request().unwrap();
+    assert_eq!(
+        req.authorization.as_deref(),
+        Some("Basic XlojlA7zgotVegyIEIP5vnw5C7ZYT9")
+    );
+
+    // The flags override the authentication of the profile.
+    let mut cmd = command(&["--config", config, "--profile", "prod", "version"]);
+    cmd.env("UFM_TOKEN", "NewToken");
+    assert_eq!(run(cmd).await.trim(), ufmmock::UFM_VERSION);
+    let req = mock.last_request().unwrap();
+    assert_eq!(req.authorization.as_deref(), Some("Basic NewToken"));
+
+    // An unknown profile is an error rather than a panic.
+    let output = command(&["--config", config, "--profile", "dev", "version"])
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("profile 'dev' not found"));
+}
+
+#[tokio::test]
+async fn test_missing_address() {
+    let output = command(&["version"]).output().await.unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("UFM_ADDRESS"));
+}
+
+#[tokio::test]
+async fn test_info() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["info"]).await;
+    assert!(out.contains("0xfe80000000000000"), "{out}");
+    assert!(out.contains("/var/log/opensm.log"), "{out}");
+    assert!(out.contains("sweep_interval"), "{out}");
+    assert!(!out.contains("0x0000000000000001"), "{out}");
+    assert!(out.contains("********"), "{out}");
+
+    let out = ufmctl(&mock, &["info", "--show-secrets"]).await;
+    assert!(out.contains("0x0000000000000001"), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "json", "info", "--set", "sweep_interval=30"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["data"]["sweep_interval"], 30);
+    assert_eq!(doc["data"]["sm_key"], "********");
+
+    let mut cmd = command(&["info", "--set", "sweep_interval=fast"]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("sweep_interval"));
+    assert_eq!(mock.last_request().unwrap().method, "GET");
+
+    let output = command(&["info", "--set", "sweep_interval"])
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected KEY=VALUE"));
+}
+
+#[tokio::test]
+async fn test_list() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["list"]).await;
+    assert!(out.contains("api_pkey_0x5"), "{out}");
+    assert!(out.contains("management"), "{out}");
+}
+
+#[tokio::test]
+async fn test_view() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["view", "--pkey", "0x5"]).await;
+    assert!(out.contains("api_pkey_0x5"), "{out}");
+    assert!(out.contains(GUID_PF), "{out}");
+    assert!(out.contains("hpc-cloud01"), "{out}");
+}
+
+#[tokio::test]
+async fn test_output() {
+    let mock = start().await;
+    mock.add_partition(0x6, MockPartition::new("a_very_long_partition_name"));
+
+    // The long names are not joined with the next columns.
+    let out = ufmctl(&mock, &["list"]).await;
+    let line = out
+        .lines()
+        .find(|l| l.starts_with("a_very_long_partition_name"))
+        .unwrap();
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    assert_eq!(
+        fields,
+        [
+            "a_very_long_partition_name",
+            "0x6",
+            "false",
+            "2048",
+            "2.5",
+            "0"
+        ]
+    );
+
+    let out = ufmctl(&mock, &["--output", "json", "list"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["apiVersion"], "ufmctl/v1");
+    assert_eq!(doc["kind"], "PartitionList");
+    let p6 = doc["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["pkey"] == "0x6")
+        .unwrap();
+    assert_eq!(p6["name"], "a_very_long_partition_name");
+    assert_eq!(p6["qos"]["rate_limit"], 2.5);
+
+    let out = ufmctl(&mock, &["-o", "yaml", "view", "--pkey", "0x5"]).await;
+    let doc: serde_json::Value = serde_yaml::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "Partition");
+    assert_eq!(doc["data"]["pkey"], "0x5");
+    assert_eq!(doc["data"]["ports"][0]["guid"], GUID_PF);
+
+    let out = ufmctl(&mock, &["-o", "csv", "view", "--pkey", "0x5"]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines[0],
+        "GUID,ParentGUID,PortType,SystemID,LID,LogState,Name,SystemName,PhyState,Speed,Width"
+    );
+    assert!(lines[1].starts_with(&format!("{GUID_PF},,")), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "wide", "view", "--pkey", "0x5"]).await;
+    assert!(out.contains("PhyState"), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "json", "version"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "Version");
+    assert_eq!(doc["data"]["version"], ufmmock::UFM_VERSION);
+
+    let out = ufmctl(&mock, &["-o", "csv", "info"]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[0].starts_with("subnet_prefix,"), "{out}");
+    assert!(lines[1].starts_with("0xfe80000000000000,"), "{out}");
+}
+
+#[tokio::test]
+async fn test_create() {
+    let mock = start().await;
+
+    ufmctl(
+        &mock,
+        &[
+            "create",
+            "--pkey",
+            "0x6",
+            "--guids",
+            GUID_PF,
+            "--guids",
+            "0011:2233:4456:0200",
+        ],
+    )
+    .await;
+
+    let p = mock.partition(0x6).unwrap();
+    assert_eq!(p.mtu_limit, 4);
+    assert_eq!(p.rate_limit, 100.0);
+    assert!(p.members.contains_key(GUID_PF));
+    assert!(p.members.contains_key(GUID_VF));
+
+    // The MTU is also taken in KB, as UFM does.
+    ufmctl(&mock, &["create", "--pkey", "0x7", "--mtu", "2"]).await;
+    let p = mock.partition(0x7).unwrap();
+    assert!(p.members.is_empty());
+    assert_eq!(p.mtu_limit, 2);
+}
+
+#[tokio::test]
+async fn test_update() {
+    let mock = start().await;
+
+    ufmctl(
+        &mock,
+        &[
+            "update",
+            "--pkey",
+            "0x5",
+            "--mtu",
+            "4096",
+            "--service-level",
+            "7",
+            "--rate-limit",
+            "200",
+        ],
+    )
+    .await;
+
+    let p = mock.partition(0x5).unwrap();
+    assert_eq!(p.mtu_limit, 4);
+    assert_eq!(p.service_level, 7);
+    assert_eq!(p.rate_limit, 200.0);
+
+    let mut cmd = command(&["update", "--pkey", "0x5", "--service-level", "16"]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("service level 16 is not in 0-15"));
+    assert_eq!(mock.partition(0x5).unwrap().service_level, 7);
+
+    let mut cmd = command(&["update", "--pkey", "0x5", "--mtu", "1024"]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mtu 1024 is not supported by UFM"));
+    assert_eq!(mock.partition(0x5).unwrap().mtu_limit, 4);
+
+    let output = command(&["update", "--pkey", "0x5", "--rate-limit", "50"])
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("rate limit 50 is not one of"));
+}
+
+#[tokio::test]
+async fn test_bind_and_unbind() {
+    let mock = start().await;
+
+    ufmctl(&mock, &["bind", "--pkey", "0x5", "--guids", GUID_VF]).await;
+    assert!(mock.partition(0x5).unwrap().members.contains_key(GUID_VF));
+
+    ufmctl(&mock, &["unbind", "--pkey", "0x5", "--guids", GUID_VF]).await;
+    assert!(!mock.partition(0x5).unwrap().members.contains_key(GUID_VF));
+}
+
+#[tokio::test]
+async fn test_delete() {
+    let mock = start().await;
+
+    ufmctl(&mock, &["delete", "--pkey", "0x5"]).await;
+    assert!(mock.partition(0x5).is_none());
+}
+
+#[tokio::test]
+async fn test_token() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["--ufm-session", "token", "create"]).await;
+    let token = out.trim().to_string();
+    let tokens = mock.tokens();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].access_token, token);
+    assert!(mock.last_request().unwrap().cookie.is_some());
+
+    let out = ufmctl(&mock, &["token", "list"]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with(&token), "{out}");
+    assert!(lines[1].contains("false"), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "json", "token", "list"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "AccessTokenList");
+    assert_eq!(doc["data"][0]["access_token"], token);
+
+    ufmctl(&mock, &["token", "revoke", &token]).await;
+    assert!(mock.tokens()[0].revoked);
+}
+
+#[tokio::test]
+async fn test_vport_list() {
+    let mock = start().await;
+
+    let out = ufmctl(&mock, &["vport", "list"]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let fields: Vec<&str> = lines[1].split_whitespace().collect();
+    assert_eq!(
+        fields,
+        [
+            GUID_VF,
+            GUID_PF,
+            "1070fd0300176624",
+            "7",
+            "Active",
+            "hpc-cloud01"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_guid_view() {
+    let mock = start().await;
+    let mut p = MockPartition::new("api_pkey_0x6");
+    p.members.insert(
+        GUID_VF.to_string(),
+        MockMember {
+            membership: "limited".to_string(),
+            index0: false,
+        },
+    );
+    mock.add_partition(0x6, p);
+
+    let out = ufmctl(&mock, &["guid", "view", "--guid", GUID_VF]).await;
+    assert!(out.contains("hpc-cloud01"), "{out}");
+    let line = out.lines().find(|l| l.trim().starts_with("0x6")).unwrap();
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    assert_eq!(fields, ["0x6", "api_pkey_0x6", "limited", "false", "false"]);
+    assert!(!out.contains("0x5"), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "json", "guid", "view", "--guid", GUID_PF]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "GuidView");
+    assert_eq!(doc["data"]["port"]["name"], "1070fd0300176625_2");
+    let partitions = doc["data"]["partitions"].as_array().unwrap();
+    assert_eq!(partitions.len(), 1);
+    assert_eq!(partitions[0]["partition"]["pkey"], "0x5");
+    assert_eq!(partitions[0]["membership"], "full");
+    assert_eq!(partitions[0]["index0"], true);
+}
+
+#[tokio::test]
+async fn test_port_list() {
+    let mock = start().await;
+    mock.add_port(
+        "Switch",
+        json!({
+            "guid": "b83fd203002a1f3a",
+            "name": "b83fd203002a1f3a_1",
+            "systemID": "b83fd203002a1f3a",
+            "lid": 1,
+            "system_name": "switch01",
+            "logical_state": "Down",
+            "physical_state": "Polling",
+        }),
+    );
+
+    let out = ufmctl(&mock, &["port", "list"]).await;
+    assert_eq!(out.lines().count(), 3, "{out}");
+
+    let out = ufmctl(
+        &mock,
+        &["port", "list", "--sys-type", "switch", "--active", "false"],
+    )
+    .await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{out}");
+    assert!(lines[1].starts_with("b83fd203002a1f3a"), "{out}");
+    assert!(lines[1].contains("Polling"), "{out}");
+    assert_eq!(
+        mock.last_request().unwrap().query.as_deref(),
+        Some("sys_type=Switch")
+    );
+
+    let out = ufmctl(&mock, &["port", "list", "--guids", GUID_PF]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{out}");
+    assert!(lines[1].starts_with(GUID_PF), "{out}");
+}
+
+#[tokio::test]
+async fn test_system() {
+    let mock = start().await;
+    mock.add_system(json!({
+        "system_guid": "b83fd203002a1f3a",
+        "system_name": "switch01",
+        "type": "switch",
+        "model": "MQM8700",
+        "vendor": "Mellanox",
+        "fw_version": "27.2010.1202",
+        "ip": "10.0.0.5",
+        "state": "active",
+        "ports": ["b83fd203002a1f3a_1", "b83fd203002a1f3a_2"],
+    }));
+
+    let out = ufmctl(&mock, &["system", "list"]).await;
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{out}");
+    let fields: Vec<&str> = lines[1].split_whitespace().collect();
+    assert_eq!(
+        fields,
+        [
+            "b83fd203002a1f3a",
+            "switch01",
+            "switch",
+            "MQM8700",
+            "Mellanox",
+            "27.2010.1202",
+            "10.0.0.5",
+            "active",
+            "2"
+        ]
+    );
+
+    let out = ufmctl(&mock, &["system", "view", "--guid", "b83fd203002a1f3a"]).await;
+    assert!(out.contains("switch01"), "{out}");
+    assert!(out.contains("b83fd203002a1f3a_2"), "{out}");
+}
+
+#[tokio::test]
+async fn test_topology() {
+    let mock = start().await;
+    mock.add_system(json!({
+        "system_guid": "b83fd203002a1f3a",
+        "system_name": "switch01",
+        "type": "switch",
+    }));
+    mock.add_link(json!({
+        "source_guid": "b83fd203002a1f3a",
+        "source_port": 1,
+        "source_port_guid": "b83fd203002a1f3a",
+        "destination_guid": "1070fd0300176624",
+        "destination_port": 2,
+        "destination_port_guid": "1070fd0300176625",
+        "speed": "HDR",
+        "width": "4x",
+    }));
+
+    let out = ufmctl(&mock, &["topology"]).await;
+    assert!(out.starts_with("graph fabric {"), "{out}");
+    assert!(
+        out.contains(r#""1070fd0300176624" -- "b83fd203002a1f3a" [taillabel="2 (1070fd0300176625)", headlabel="1 (b83fd203002a1f3a)", label="HDR 4x"];"#),
+        "{out}"
+    );
+
+    let path = std::env::temp_dir().join(format!("ufmctl-topology-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    ufmctl(&mock, &["topology", "--format", "json", "--file", path]).await;
+    let topo: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(topo["nodes"].as_array().unwrap().len(), 2);
+    assert_eq!(topo["links"][0]["destination"]["port_number"], 1);
+    assert_eq!(topo["links"][0]["source"]["port_guid"], "1070fd0300176625");
+
+    let out = ufmctl(&mock, &["-o", "yaml", "topology"]).await;
+    let doc: serde_json::Value = serde_yaml::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "Topology");
+    assert_eq!(doc["data"], topo);
+
+    let err = ufmctl_err(&mock, &["-o", "csv", "topology"]).await;
+    assert!(
+        err.contains("csv output is not supported by topology"),
+        "{err}"
+    );
+}
+
+fn event(id: u64, severity: &str, object: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": "Link is down",
+        "severity": severity,
+        "timestamp": format!("2024-01-01 10:00:{:02}", id),
+        "type": "Port",
+        "object_name": object,
+        "description": format!("Link went down: {object}"),
+    })
+}
+
+/// The ID in the next line of the followed events.
+async fn next_id(lines: &mut Lines<BufReader<ChildStdout>>) -> String {
+    let line = tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    line.split_whitespace().next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_events() {
+    let mock = start().await;
+    mock.add_event(event(1, "Info", "b83fd203002a1f3a_1"));
+    mock.add_event(event(2, "Critical", "b83fd203002a1f3a_2"));
+    mock.add_event(event(3, "Warning", "1070fd0300176625_2"));
+    mock.add_alarm(json!({
+        "id": 10,
+        "name": "Link is down",
+        "severity": "Critical",
+        "timestamp": "2024-01-01 10:00:02",
+        "type": "Port",
+        "object_name": "b83fd203002a1f3a_2",
+        "reason": "Link went down",
+    }));
+
+    let ids = |out: &str| -> Vec<String> {
+        out.lines()
+            .skip(1)
+            .map(|l| l.split_whitespace().next().unwrap().to_string())
+            .collect()
+    };
+
+    let out = ufmctl(&mock, &["events"]).await;
+    assert_eq!(ids(&out), ["1", "2", "3"], "{out}");
+
+    let out = ufmctl(&mock, &["events", "--severity", "warning"]).await;
+    assert_eq!(ids(&out), ["2", "3"], "{out}");
+
+    let out = ufmctl(&mock, &["events", "--pkey", "0x5"]).await;
+    assert_eq!(ids(&out), ["3"], "{out}");
+
+    let out = ufmctl(&mock, &["alarms"]).await;
+    assert_eq!(ids(&out), ["10"], "{out}");
+    assert!(out.contains("Link went down"), "{out}");
+
+    let out = ufmctl(&mock, &["-o", "json", "events", "--severity", "warning"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "EventList");
+    assert_eq!(doc["data"][1]["id"], 3);
+
+    let out = ufmctl(&mock, &["-o", "csv", "alarms"]).await;
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        [
+            "ID,Timestamp,Severity,Type,Object,Name,Reason",
+            "10,2024-01-01 10:00:02,Critical,Port,b83fd203002a1f3a_2,Link is down,Link went down",
+        ]
+    );
+
+    // Follow the events from the latest one, and print the new ones only.
+    let mut cmd = command(&[
+        "events",
+        "--follow",
+        "--interval",
+        "1",
+        "--since",
+        "2024-01-01 10:00:03",
+    ]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456")
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = cmd.spawn().unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    assert_eq!(next_id(&mut lines).await, "ID");
+    let started = || {
+        mock.requests()
+            .iter()
+            .any(|r| r.query.as_deref().is_some_and(|q| q.contains("after_id=3")))
+    };
+    while !started() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    mock.add_event(event(4, "Minor", "b83fd203002a1f3a_1"));
+    assert_eq!(next_id(&mut lines).await, "4");
+
+    child.kill().await.unwrap();
+}
+
+/// Spawn ufmctl against the mock UFM, and return the lines of its stdout.
+fn spawn(mock: &MockUfm, args: &[&str]) -> (Child, Lines<BufReader<ChildStdout>>) {
+    let mut cmd = command(args);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456")
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = cmd.spawn().unwrap();
+    let lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    (child, lines)
+}
+
+async fn next_line(lines: &mut Lines<BufReader<ChildStdout>>) -> String {
+    tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_watch() {
+    let mock = start().await;
+
+    let (mut child, mut lines) = spawn(
+        &mock,
+        &["view", "--pkey", "0x5", "--watch", "--interval", "1"],
+    );
+    while !next_line(&mut lines).await.contains(GUID_PF) {}
+
+    let mut p = mock.partition(0x5).unwrap();
+    p.members.insert(
+        GUID_VF.to_string(),
+        MockMember {
+            membership: "limited".to_string(),
+            index0: false,
+        },
+    );
+    mock.add_partition(0x5, p);
+    assert_eq!(
+        next_line(&mut lines).await,
+        format!("+ member 0x5: {GUID_VF} (Active)")
+    );
+    child.kill().await.unwrap();
+
+    let (mut child, mut lines) = spawn(&mock, &["list", "--watch", "--interval", "1"]);
+    for _ in 0..mock.partitions().len() + 1 {
+        next_line(&mut lines).await;
+    }
+
+    mock.add_partition(0x6, MockPartition::new("api_pkey_0x6"));
+    assert_eq!(
+        next_line(&mut lines).await,
+        "+ partition 0x6 (api_pkey_0x6)"
+    );
+    child.kill().await.unwrap();
+
+    // The snapshot and the changes are a JSON document per line.
+    let (mut child, mut lines) =
+        spawn(&mock, &["-o", "json", "list", "--watch", "--interval", "1"]);
+    let doc: serde_json::Value = serde_json::from_str(&next_line(&mut lines).await).unwrap();
+    assert_eq!(doc["kind"], "PartitionList");
+
+    mock.add_partition(0x7, MockPartition::new("api_pkey_0x7"));
+    let doc: serde_json::Value = serde_json::from_str(&next_line(&mut lines).await).unwrap();
+    assert_eq!(doc["kind"], "PartitionChange");
+    assert_eq!(doc["data"]["partition_added"]["pkey"], "0x7");
+    child.kill().await.unwrap();
+
+    let err = ufmctl_err(&mock, &["-o", "csv", "list", "--watch"]).await;
+    assert!(
+        err.contains("csv output is not supported by --watch"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn test_monitor() {
+    let mock = start().await;
+    mock.set_counters(
+        100.0,
+        json!({ "1070fd0300176625_2": { "PortXmitDataExtended": 0, "LinkDownedCounter": 1 } }),
+    );
+
+    let mut cmd = command(&[
+        "monitor",
+        "--guids",
+        GUID_PF,
+        "--attrs",
+        "xmit-data,link-downed",
+        "--interval",
+        "1",
+        "--count",
+        "1",
+    ]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456")
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let child = cmd.spawn().unwrap();
+
+    // Sample the counters again after the first snapshot.
+    let polled = || mock.requests().iter().any(|r| r.path.ends_with("/data"));
+    for _ in 0..100 {
+        if polled() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(polled());
+    mock.set_counters(
+        102.0,
+        json!({ "1070fd0300176625_2": { "PortXmitDataExtended": 250_000_000u64, "LinkDownedCounter": 2 } }),
+    );
+
+    let output = tokio::time::timeout(Duration::from_secs(10), child.wait_with_output())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(output.status.success());
+    let out = String::from_utf8(output.stdout).unwrap();
+
+    let rates: Vec<Vec<&str>> = out
+        .lines()
+        .skip(1)
+        .map(|l| l.split_whitespace().skip(2).collect())
+        .collect();
+    assert_eq!(
+        rates,
+        [
+            vec!["1070fd0300176625_2", "xmit-data", "4.00", "Gb/s"],
+            vec!["1070fd0300176625_2", "link-downed", "0.50/s"],
+        ],
+        "{out}"
+    );
+
+    // The session is deleted at the end.
+    assert!(mock.monitoring_sessions().is_empty());
+}
+
+#[tokio::test]
+async fn test_apply() {
+    let mock = start().await;
+    mock.add_partition(0x7, MockPartition::new("api_pkey_0x7"));
+
+    let path = std::env::temp_dir().join(format!("ufmctl-partitions-{}.yaml", std::process::id()));
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+partitions:
+  - pkey: "0x5"
+    members:
+      - guid: "{GUID_PF}"
+        membership: limited
+  - pkey: "0x6"
+    qos:
+      mtu_limit: 4
+      service_level: 0
+      rate_limit: 100
+    members:
+      - guid: "{GUID_VF}"
+"#
+        ),
+    )
+    .unwrap();
+    let path = path.to_str().unwrap();
+
+    let out = ufmctl(&mock, &["-o", "json", "diff", "-f", path, "--prune"]).await;
+    let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "PartitionPlan");
+    assert_eq!(doc["data"].as_array().unwrap().len(), 4);
+    assert_eq!(doc["data"][3], json!({"delete": "0x7"}));
+    let err = ufmctl_err(&mock, &["-o", "csv", "diff", "-f", path]).await;
+    assert!(err.contains("csv output is not supported by diff"), "{err}");
+
+    let out = ufmctl(&mock, &["diff", "-f", path, "--prune"]).await;
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        [
+            "+ bind 0x5 (limited, index0=false): 1070fd0300176625",
+            "+ partition 0x6: ipoib=false, mtu_limit=4096, service_level=0, rate_limit=100",
+            "+ bind 0x6 (full, index0=false): 0011223344560200",
+            "- partition 0x7",
+        ]
+    );
+
+    ufmctl(&mock, &["apply", "-f", path, "--dry-run"]).await;
+    assert!(mock.partition(0x6).is_none());
+
+    // The undeclared partitions are kept without --prune.
+    let out = ufmctl(&mock, &["apply", "-f", path]).await;
+    assert!(out.ends_with("Applied 3 of 3 changes.\n"), "{out}");
+    assert_eq!(
+        mock.partition(0x5).unwrap().members[GUID_PF].membership,
+        "limited"
+    );
+    assert_eq!(mock.partition(0x6).unwrap().mtu_limit, 4);
+    assert!(mock.partition(0x7).is_some());
+
+    let out = ufmctl(&mock, &["-o", "yaml", "apply", "-f", path, "--prune"]).await;
+    let doc: serde_json::Value = serde_yaml::from_str(&out).unwrap();
+    assert_eq!(doc["kind"], "ApplyReport");
+    assert_eq!(
+        doc["data"],
+        json!({"applied": [{"delete": "0x7"}], "failed": null, "error": null, "total": 1})
+    );
+    assert!(mock.partition(0x7).is_none());
+
+    let out = ufmctl(&mock, &["diff", "-f", path, "--prune"]).await;
+    assert_eq!(out, "No changes.\n");
+}
+
+#[tokio::test]
+async fn test_apply_failed() {
+    let mock = start().await;
+    mock.add_partition(0x7, MockPartition::new("api_pkey_0x7"));
+    mock.add_partition(0x8, MockPartition::new("api_pkey_0x8"));
+    mock.fail_requests(Method::DELETE, "/resources/pkeys/0x8", StatusCode::CONFLICT);
+
+    let path = std::env::temp_dir().join(format!("ufmctl-failed-{}.yaml", std::process::id()));
+    std::fs::write(
+        &path,
+        format!("partitions:\n  - pkey: \"0x5\"\n    members:\n      - guid: \"{GUID_PF}\"\n        index0: true\n"),
+    )
+    .unwrap();
+
+    let mut cmd = command(&["apply", "-f", path.to_str().unwrap(), "--prune"]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "- partition 0x7\nFailed: - partition 0x8\nApplied 1 of 2 changes.\n"
+    );
+    assert!(mock.partition(0x7).is_none());
+    assert!(mock.partition(0x8).is_some());
+}
+
+#[tokio::test]
+async fn test_batch() {
+    let mock = start().await;
+
+    let path = std::env::temp_dir().join(format!("ufmctl-ops-{}.json", std::process::id()));
+    let ops = json!([
+        {"op": "create", "pkey": "0x10", "members": [{"guid": GUID_PF}]},
+        {"op": "bind", "pkey": "0x10", "members": [{"guid": GUID_VF, "membership": "limited"}]},
+        {"op": "delete", "pkey": "0x13"},
+    ]);
+    std::fs::write(&path, ops.to_string()).unwrap();
+
+    let mut cmd = command(&["batch", "-f", path.to_str().unwrap(), "--concurrency", "2"]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+
+    // It fails if any of the operations fails.
+    assert!(!output.status.success());
+    let out = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "OK      create 0x10 (1 ports)");
+    assert_eq!(lines[1], "OK      bind 0x10 (1 ports)");
+    assert!(lines[2].starts_with("FAILED  delete 0x13: "), "{out}");
+    assert_eq!(lines[3], "2 of 3 operations succeeded.");
+
+    let p = mock.partition(0x10).unwrap();
+    assert_eq!(p.members.len(), 2);
+    assert_eq!(p.members[GUID_VF].membership, "limited");
+
+    let mut cmd = command(&["-o", "json", "batch", "-f", path.to_str().unwrap()]);
+    cmd.env("UFM_ADDRESS", mock.address())
+        .env("UFM_USERNAME", "admin")
+        .env("UFM_PASSWORD", "123456");
+    let output = cmd.output().await.unwrap();
+    assert!(!output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["kind"], "BatchReport");
+    assert_eq!(doc["data"][2]["op"], "delete");
+    assert_eq!(doc["data"][2]["pkey"], "0x13");
+    assert!(doc["data"][2]["error"].is_string());
+    assert!(doc["data"][1]["error"].is_null());
+}
@@ -0,0 +1,983 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+use self::tls::MockCa;
+
+mod tls;
+
+/// The default partition of the fabric.
+pub const DEFAULT_PKEY: u16 = 0x7fff;
+
+/// The version reported by `/app/ufm_version`.
+pub const UFM_VERSION: &str = "6.11.1-2";
+
+/// The username and password accepted by `/dologin` by default.
+pub const USERNAME: &str = "admin";
+pub const PASSWORD: &str = "123456";
+
+/// The membership of a GUID in a partition.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockMember {
+    pub membership: String,
+    pub index0: bool,
+}
+
+/// A partition kept by the mock UFM.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockPartition {
+    pub name: String,
+    pub ip_over_ib: bool,
+    pub mtu_limit: u16,
+    pub service_level: u8,
+    pub rate_limit: f64,
+    /// The members of the partition, keyed by GUID in UFM's notation.
+    pub members: BTreeMap<String, MockMember>,
+}
+
+impl MockPartition {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ip_over_ib: false,
+            mtu_limit: 2,
+            service_level: 0,
+            rate_limit: 2.5,
+            members: BTreeMap::new(),
+        }
+    }
+}
+
+/// A request received by the mock UFM.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: Method,
+    /// The path without the `/ufmRest*` prefix, e.g. `/resources/pkeys`.
+    pub path: String,
+    /// The query string, if any.
+    pub query: Option<String>,
+    pub authorization: Option<String>,
+    /// The `Cookie` header, if any.
+    pub cookie: Option<String>,
+    /// Whether the client presented a certificate signed by the CA of the mock UFM.
+    pub client_cert: bool,
+    /// The JSON body; `Value::Null` if the body is empty or not JSON.
+    pub body: Value,
+}
+
+#[derive(Debug)]
+struct MockPort {
+    sys_type: String,
+    data: Value,
+}
+
+/// An access token issued by `/app/tokens`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockToken {
+    pub access_token: String,
+    pub revoked: bool,
+    pub creation_date: String,
+}
+
+/// A monitoring session created by `/monitoring/session`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockMonitoringSession {
+    /// The names of the ports, e.g. `b83fd203002a1f3a_1`.
+    pub objects: Vec<String>,
+    /// The counters in UFM's names, e.g. `PortXmitDataExtended`.
+    pub attributes: Vec<String>,
+    pub interval: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    partitions: BTreeMap<u16, MockPartition>,
+    ports: Vec<MockPort>,
+    vports: Vec<Value>,
+    systems: Vec<Value>,
+    links: Vec<Value>,
+    events: Vec<Value>,
+    alarms: Vec<Value>,
+    smconf: Value,
+    version: String,
+    requests: Vec<MockRequest>,
+    failures: VecDeque<StatusCode>,
+    /// The requests which always fail, by their methods and paths.
+    failing: HashMap<(Method, String), StatusCode>,
+    delay: Duration,
+    credentials: (String, String),
+    sessions: HashSet<String>,
+    tokens: Vec<MockToken>,
+    /// The monitoring sessions by their IDs.
+    monitoring: BTreeMap<u64, MockMonitoringSession>,
+    /// The snapshot of the counters, and its time in seconds since the epoch.
+    counters: (f64, Value),
+    /// The sequence of the sessions and tokens.
+    seq: u64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let mut partitions = BTreeMap::new();
+        let mut management = MockPartition::new("management");
+        management.ip_over_ib = true;
+        partitions.insert(DEFAULT_PKEY, management);
+
+        Self {
+            partitions,
+            ports: vec![],
+            vports: vec![],
+            systems: vec![],
+            links: vec![],
+            events: vec![],
+            alarms: vec![],
+            smconf: json!({
+                "subnet_prefix": "0xfe80000000000000",
+                "m_key": "0x0000000000000000",
+                "m_key_per_port": false,
+                "sm_key": "0x0000000000000001",
+                "sa_key": "0x0000000000000001",
+                "log_file": "/var/log/opensm.log",
+                "qos": 0,
+                "sm_priority": 0,
+                "sweep_interval": 10,
+                "lmc": 0,
+            }),
+            version: UFM_VERSION.to_string(),
+            requests: vec![],
+            failures: VecDeque::new(),
+            failing: HashMap::new(),
+            delay: Duration::ZERO,
+            credentials: (USERNAME.to_string(), PASSWORD.to_string()),
+            sessions: HashSet::new(),
+            tokens: vec![],
+            monitoring: BTreeMap::new(),
+            counters: (0.0, json!({})),
+            seq: 0,
+        }
+    }
+}
+
+/// An in-process mock of UFM REST API.
+///
+/// It keeps the partitions, ports and virtual ports in memory, and serves them
+/// with the same JSON shapes as UFM under any `/ufmRest*` base path; the requests
+/// under `/ufmRestV2` require a session logged in by `/dologin`. The server is
+/// stopped when the `MockUfm` is dropped.
+pub struct MockUfm {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    ca: Option<MockCa>,
+}
+
+impl MockUfm {
+    /// Start a mock UFM on a random local port; it has to be called within a tokio runtime.
+    pub async fn start() -> io::Result<MockUfm> {
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let svc_state = state.clone();
+        let make_svc = make_service_fn(move |_| {
+            let state = svc_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), false, req))) }
+        });
+
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, e))?
+            .serve(make_svc);
+        let addr = server.local_addr();
+
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+
+        Ok(MockUfm {
+            addr,
+            state,
+            shutdown: Some(tx),
+            ca: None,
+        })
+    }
+
+    /// Start a mock UFM serving HTTPS on a random local port, with a certificate for
+    /// `server_name` signed by a generated CA; see `ca_crt`.
+    pub async fn start_tls(server_name: &str) -> io::Result<MockUfm> {
+        let state = Arc::new(Mutex::new(State::default()));
+        let ca = MockCa::new()?;
+        let acceptor = ca.acceptor(server_name)?;
+
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let addr = listener.local_addr()?;
+
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(tls::serve(listener, acceptor, state.clone(), rx));
+
+        Ok(MockUfm {
+            addr,
+            state,
+            shutdown: Some(tx),
+            ca: Some(ca),
+        })
+    }
+
+    /// The address of the mock UFM, e.g. `http://127.0.0.1:38271`.
+    pub fn address(&self) -> String {
+        match self.ca {
+            None => format!("http://{}", self.addr),
+            Some(_) => format!("https://{}", self.addr),
+        }
+    }
+
+    /// The PEM of the CA which signed the certificate of a mock UFM serving HTTPS.
+    pub fn ca_crt(&self) -> Option<String> {
+        self.ca.as_ref().map(|ca| ca.pem())
+    }
+
+    /// Issue a client certificate signed by the CA of a mock UFM serving HTTPS;
+    /// it returns the PEMs of the certificate and its private key.
+    pub fn issue_client_cert(&self) -> io::Result<(String, String)> {
+        match &self.ca {
+            Some(ca) => ca.issue_client_cert(),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the mock UFM does not serve HTTPS",
+            )),
+        }
+    }
+
+    /// Add a partition, or replace the existing one with the same pkey.
+    pub fn add_partition(&self, pkey: u16, partition: MockPartition) {
+        self.lock().partitions.insert(pkey, partition);
+    }
+
+    /// Get a partition by pkey.
+    pub fn partition(&self, pkey: u16) -> Option<MockPartition> {
+        self.lock().partitions.get(&pkey).cloned()
+    }
+
+    /// All the partitions, keyed by pkey.
+    pub fn partitions(&self) -> BTreeMap<u16, MockPartition> {
+        self.lock().partitions.clone()
+    }
+
+    /// Add a physical port in UFM's JSON shape, e.g. `{"guid": "...", "name": "...", ...}`;
+    /// `sys_type` is the type of its system used by the `sys_type` filter, e.g. `Computer`.
+    pub fn add_port(&self, sys_type: &str, port: Value) {
+        self.lock().ports.push(MockPort {
+            sys_type: sys_type.to_string(),
+            data: port,
+        });
+    }
+
+    /// Update the fields of the physical ports of the GUID, e.g. `{"logical_state": "Down"}`.
+    pub fn update_port(&self, guid: &str, fields: Value) {
+        let mut state = self.lock();
+        for p in state.ports.iter_mut().filter(|p| p.data["guid"] == guid) {
+            if let (Some(data), Some(fields)) = (p.data.as_object_mut(), fields.as_object()) {
+                data.extend(fields.clone());
+            }
+        }
+    }
+
+    /// Add a virtual port in UFM's JSON shape, e.g. `{"virtual_port_guid": "...", ...}`.
+    pub fn add_vport(&self, vport: Value) {
+        self.lock().vports.push(vport);
+    }
+
+    /// Add a system in UFM's JSON shape, e.g. `{"system_guid": "...", "type": "switch", ...}`.
+    pub fn add_system(&self, system: Value) {
+        self.lock().systems.push(system);
+    }
+
+    /// Add a link in UFM's JSON shape, e.g. `{"source_guid": "...", "source_port": 1, ...}`.
+    pub fn add_link(&self, link: Value) {
+        self.lock().links.push(link);
+    }
+
+    /// Add an event in UFM's JSON shape, e.g. `{"id": 1, "name": "Link is down", ...}`.
+    pub fn add_event(&self, event: Value) {
+        self.lock().events.push(event);
+    }
+
+    /// Add an alarm in UFM's JSON shape, e.g. `{"id": 1, "name": "Link is down", ...}`.
+    pub fn add_alarm(&self, alarm: Value) {
+        self.lock().alarms.push(alarm);
+    }
+
+    /// Replace the SM configuration returned by `/app/smconf`.
+    pub fn set_smconf(&self, smconf: Value) {
+        self.lock().smconf = smconf;
+    }
+
+    /// Fail the next `count` requests with `status`, regardless of the resources.
+    pub fn fail_next(&self, status: StatusCode, count: usize) {
+        let mut state = self.lock();
+        for _ in 0..count {
+            state.failures.push_back(status);
+        }
+    }
+
+    /// Fail all the requests of the method to the path with `status`, e.g. `/resources/vports`
+    /// of an older UFM, or a step in the middle of a sequence of requests.
+    pub fn fail_requests(&self, method: Method, path: &str, status: StatusCode) {
+        self.lock()
+            .failing
+            .insert((method, path.to_string()), status);
+    }
+
+    /// Delay the responses, e.g. to simulate a large fabric.
+    pub fn set_delay(&self, delay: Duration) {
+        self.lock().delay = delay;
+    }
+
+    /// Expire all the sessions, so the clients have to log in again.
+    pub fn expire_sessions(&self) {
+        self.lock().sessions.clear();
+    }
+
+    /// Replace the snapshot of the counters which is served by the monitoring sessions, e.g.
+    /// `{"b83fd203002a1f3a_1": {"PortXmitDataExtended": 1024}}`.
+    pub fn set_counters(&self, timestamp: f64, counters: Value) {
+        self.lock().counters = (timestamp, counters);
+    }
+
+    /// The monitoring sessions which are not deleted, by their IDs.
+    pub fn monitoring_sessions(&self) -> BTreeMap<u64, MockMonitoringSession> {
+        self.lock().monitoring.clone()
+    }
+
+    /// The access tokens issued so far, including the revoked ones.
+    pub fn tokens(&self) -> Vec<MockToken> {
+        self.lock().tokens.clone()
+    }
+
+    /// All the requests received by the mock UFM, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// The last request received by the mock UFM.
+    pub fn last_request(&self) -> Option<MockRequest> {
+        self.lock().requests.last().cloned()
+    }
+
+    /// Forget the requests received so far.
+    pub fn clear_requests(&self) {
+        self.lock().requests.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("mock state poisoned")
+    }
+}
+
+impl Drop for MockUfm {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Parse a pkey in UFM's notation, e.g. `0x5`, `5` or `0x7FFF`.
+pub fn parse_pkey(pkey: &str) -> Option<u16> {
+    let pkey = pkey.trim().to_lowercase();
+    match pkey.strip_prefix("0x") {
+        Some(p) => u16::from_str_radix(p, 16).ok(),
+        None => pkey.parse().ok(),
+    }
+}
+
+fn format_pkey(pkey: u16) -> String {
+    format!("0x{:x}", pkey)
+}
+
+fn normalize_guid(guid: &str) -> String {
+    let guid = guid.trim().to_lowercase();
+    guid.trim_start_matches("0x").replace(':', "")
+}
+
+/// An error replied to the client as `{"error": "..."}`, as UFM does.
+struct MockError {
+    status: StatusCode,
+    msg: String,
+}
+
+impl MockError {
+    fn new(status: StatusCode, msg: &str) -> Self {
+        Self {
+            status,
+            msg: msg.to_string(),
+        }
+    }
+
+    fn bad_request(msg: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, msg)
+    }
+
+    fn not_found(msg: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, msg)
+    }
+}
+
+impl From<MockError> for Response<Body> {
+    fn from(e: MockError) -> Self {
+        response(e.status, json!({ "error": e.msg }))
+    }
+}
+
+type MockResult = Result<Response<Body>, MockError>;
+
+fn response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+fn ok(body: Value) -> MockResult {
+    Ok(response(StatusCode::OK, body))
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    client_cert: bool,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let query = req.uri().query().map(|q| q.to_string());
+    let authorization = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let cookie = req
+        .headers()
+        .get(hyper::header::COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    // Strip the base path, e.g. "/ufmRest", "/ufmRestV3".
+    let full_path = req.uri().path().to_string();
+    let (base, path) = match full_path.strip_prefix('/').and_then(|p| p.split_once('/')) {
+        Some((base, rest)) if base.starts_with("ufmRest") => (base.to_string(), format!("/{rest}")),
+        _ if full_path == "/dologin" => (String::new(), full_path.clone()),
+        _ => return Ok(MockError::not_found(&format!("'{full_path}' not found")).into()),
+    };
+
+    let raw = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+    let body = serde_json::from_slice(&raw).unwrap_or(Value::Null);
+
+    log::debug!("Mock UFM: {method} {path}?{query:?}, Body: {body}");
+
+    let delay = state.lock().expect("mock state poisoned").delay;
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut state = state.lock().expect("mock state poisoned");
+    state.requests.push(MockRequest {
+        method: method.clone(),
+        path: path.clone(),
+        query: query.clone(),
+        authorization: authorization.clone(),
+        cookie: cookie.clone(),
+        client_cert,
+        body: body.clone(),
+    });
+
+    if let Some(status) = state.failures.pop_front() {
+        return Ok(MockError::new(status, "injected failure").into());
+    }
+    if let Some(status) = state.failing.get(&(method.clone(), path.clone())) {
+        return Ok(MockError::new(*status, "injected failure").into());
+    }
+
+    if path == "/dologin" {
+        return Ok(login(&mut state, &method, &raw).unwrap_or_else(Response::from));
+    }
+    if let Err(e) = authenticate(&state, &base, authorization.as_deref(), cookie.as_deref()) {
+        return Ok(e.into());
+    }
+
+    let params: HashMap<String, String> =
+        url::form_urlencoded::parse(query.as_deref().unwrap_or("").as_bytes())
+            .into_owned()
+            .collect();
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let resp = match (&method, segments.as_slice()) {
+        (&Method::GET, ["app", "ufm_version"]) => {
+            ok(json!({ "ufm_release_version": state.version }))
+        }
+        (&Method::GET, ["app", "smconf"]) => ok(state.smconf.clone()),
+        (&Method::PUT, ["app", "smconf"]) => update_smconf(&mut state, &body),
+        (&Method::GET, ["resources", "pkeys"]) => list_pkeys(&state, &params),
+        (&Method::GET, ["resources", "pkeys", pkey]) => get_pkey(&state, pkey, &params),
+        (&Method::POST, ["resources", "pkeys", "add"]) => add_pkey(&mut state, &body),
+        (&Method::PUT, ["resources", "pkeys"]) => set_pkey(&mut state, &body),
+        (&Method::POST, ["resources", "pkeys"]) => add_guids(&mut state, &body),
+        (&Method::PUT, ["resources", "pkeys", "qos_conf"]) => update_qos(&mut state, &body),
+        (&Method::DELETE, ["resources", "pkeys", pkey]) => delete_pkey(&mut state, pkey),
+        (&Method::POST, ["actions", "remove_guids_from_pkey"]) => remove_guids(&mut state, &body),
+        (&Method::GET, ["resources", "ports"]) => list_ports(&state, &params),
+        (&Method::GET, ["resources", "vports"]) => ok(Value::from(state.vports.clone())),
+        (&Method::GET, ["resources", "systems"]) => ok(Value::from(state.systems.clone())),
+        (&Method::GET, ["resources", "systems", id]) => get_system(&state, id),
+        (&Method::GET, ["resources", "links"]) => ok(Value::from(state.links.clone())),
+        (&Method::GET, ["app", "events"]) => ok(filter_events(&state.events, &params)),
+        (&Method::GET, ["app", "alarms"]) => ok(filter_events(&state.alarms, &params)),
+        (&Method::POST, ["app", "tokens"]) => create_token(&mut state),
+        (&Method::GET, ["app", "tokens"]) => ok(json!(state.tokens)),
+        (&Method::DELETE, ["app", "tokens", token]) => revoke_token(&mut state, token),
+        (&Method::POST, ["monitoring", "session"]) => create_session(&mut state, &base, &body),
+        (&Method::GET, ["monitoring", "session", id, "data"]) => session_data(&state, id),
+        (&Method::DELETE, ["monitoring", "session", id]) => delete_session(&mut state, id),
+        _ => Err(MockError::not_found(&format!("'{path}' not found"))),
+    };
+
+    Ok(resp.unwrap_or_else(Response::from))
+}
+
+/// Log in a session by the form of `httpd_username` and `httpd_password`.
+fn login(state: &mut State, method: &Method, form: &[u8]) -> MockResult {
+    if method != Method::POST {
+        return Err(MockError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "login requires POST",
+        ));
+    }
+
+    let form = String::from_utf8_lossy(form);
+    let params: HashMap<&str, &str> = form.split('&').filter_map(|p| p.split_once('=')).collect();
+    let (username, password) = &state.credentials;
+    if params.get("httpd_username") != Some(&username.as_str())
+        || params.get("httpd_password") != Some(&password.as_str())
+    {
+        return Err(MockError::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid username or password",
+        ));
+    }
+
+    state.seq += 1;
+    let session = format!("mocksession{:08}", state.seq);
+    state.sessions.insert(session.clone());
+
+    Ok(Response::builder()
+        .status(StatusCode::FOUND)
+        .header("location", "/")
+        .header(
+            "set-cookie",
+            format!("sessionid={session}; Path=/; HttpOnly"),
+        )
+        .header("set-cookie", "csrftoken=mockcsrf; Path=/")
+        .body(Body::empty())
+        .expect("valid response"))
+}
+
+/// Check the session for `/ufmRestV2`, and reject the revoked tokens for `/ufmRestV3`.
+fn authenticate(
+    state: &State,
+    base: &str,
+    authorization: Option<&str>,
+    cookie: Option<&str>,
+) -> Result<(), MockError> {
+    let unauthorized = || MockError::new(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    match base {
+        "ufmRestV2" => {
+            let session = cookie
+                .unwrap_or("")
+                .split(';')
+                .filter_map(|c| c.trim().strip_prefix("sessionid="))
+                .next();
+            match session {
+                Some(s) if state.sessions.contains(s) => Ok(()),
+                _ => Err(unauthorized()),
+            }
+        }
+        "ufmRestV3" => {
+            let token = authorization.unwrap_or("").trim_start_matches("Basic ");
+            match state.tokens.iter().find(|t| t.access_token == token) {
+                Some(t) if t.revoked => Err(unauthorized()),
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+fn create_token(state: &mut State) -> MockResult {
+    state.seq += 1;
+    let token = MockToken {
+        access_token: format!("MockToken{:021}", state.seq),
+        revoked: false,
+        creation_date: "2024-01-01 00:00:00".to_string(),
+    };
+    state.tokens.push(token.clone());
+
+    ok(json!(token))
+}
+
+fn revoke_token(state: &mut State, token: &str) -> MockResult {
+    match state.tokens.iter_mut().find(|t| t.access_token == token) {
+        Some(t) => {
+            t.revoked = true;
+            ok(json!({}))
+        }
+        None => Err(MockError::not_found(&format!("token '{token}' not found"))),
+    }
+}
+
+/// Create a monitoring session, which is accepted with its location like UFM.
+fn create_session(state: &mut State, base: &str, body: &Value) -> MockResult {
+    let session: MockMonitoringSession = serde_json::from_value(body.clone())
+        .map_err(|e| MockError::bad_request(&format!("invalid monitoring session: {e}")))?;
+
+    state.seq += 1;
+    let id = state.seq;
+    state.monitoring.insert(id, session);
+
+    Ok(Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header("location", format!("/{base}/monitoring/session/{id}"))
+        .body(Body::empty())
+        .expect("valid response"))
+}
+
+fn monitoring_session<'a>(
+    state: &'a State,
+    id: &str,
+) -> Result<(u64, &'a MockMonitoringSession), MockError> {
+    let not_found = || MockError::not_found(&format!("monitoring session '{id}' not found"));
+    let id = id.parse().map_err(|_| not_found())?;
+
+    state
+        .monitoring
+        .get(&id)
+        .map(|s| (id, s))
+        .ok_or_else(not_found)
+}
+
+/// The counters of the ports and the attributes of the session in the snapshot.
+fn session_data(state: &State, id: &str) -> MockResult {
+    let (_, session) = monitoring_session(state, id)?;
+    let (timestamp, counters) = &state.counters;
+
+    let mut ports = serde_json::Map::new();
+    for port in &session.objects {
+        let attrs: serde_json::Map<String, Value> = session
+            .attributes
+            .iter()
+            .filter_map(|a| Some((a.clone(), counters.get(port)?.get(a)?.clone())))
+            .collect();
+        ports.insert(port.clone(), Value::Object(attrs));
+    }
+
+    ok(json!({ "timestamp": timestamp, "Port": ports }))
+}
+
+fn delete_session(state: &mut State, id: &str) -> MockResult {
+    let (id, _) = monitoring_session(state, id)?;
+    state.monitoring.remove(&id);
+
+    ok(json!({}))
+}
+
+fn is_true(params: &HashMap<String, String>, key: &str) -> bool {
+    params.get(key).map(|s| s.as_str()) == Some("true")
+}
+
+fn pkey_json(p: &MockPartition, params: &HashMap<String, String>) -> Value {
+    let mut v = json!({
+        "partition": p.name,
+        "ip_over_ib": p.ip_over_ib,
+    });
+
+    if is_true(params, "qos_conf") {
+        v["qos_conf"] = json!({
+            "mtu_limit": p.mtu_limit,
+            "service_level": p.service_level,
+            "rate_limit": p.rate_limit,
+        });
+    }
+
+    if is_true(params, "guids_data") {
+        v["guids"] = p
+            .members
+            .iter()
+            .map(|(guid, m)| {
+                json!({
+                    "guid": guid,
+                    "membership": m.membership,
+                    "index0": m.index0,
+                })
+            })
+            .collect();
+    }
+
+    v
+}
+
+fn list_pkeys(state: &State, params: &HashMap<String, String>) -> MockResult {
+    if !is_true(params, "qos_conf") && !is_true(params, "guids_data") {
+        let pkeys: Vec<String> = state.partitions.keys().map(|k| format_pkey(*k)).collect();
+        return ok(json!(pkeys));
+    }
+
+    let pkeys: serde_json::Map<String, Value> = state
+        .partitions
+        .iter()
+        .map(|(k, p)| (format_pkey(*k), pkey_json(p, params)))
+        .collect();
+
+    ok(Value::Object(pkeys))
+}
+
+fn get_pkey(state: &State, pkey: &str, params: &HashMap<String, String>) -> MockResult {
+    // UFM returns an empty object for unknown pkeys.
+    match parse_pkey(pkey).and_then(|k| state.partitions.get(&k)) {
+        Some(p) => ok(pkey_json(p, params)),
+        None => ok(json!({})),
+    }
+}
+
+fn body_pkey(body: &Value) -> Result<u16, MockError> {
+    body["pkey"]
+        .as_str()
+        .and_then(parse_pkey)
+        .filter(|k| *k & 0x8000 == 0)
+        .ok_or_else(|| MockError::bad_request("invalid pkey"))
+}
+
+fn body_guids(body: &Value) -> Result<Vec<String>, MockError> {
+    match &body["guids"] {
+        Value::Array(guids) => guids
+            .iter()
+            .map(|g| {
+                g.as_str()
+                    .map(normalize_guid)
+                    .ok_or_else(|| MockError::bad_request("invalid guid"))
+            })
+            .collect(),
+        _ => Err(MockError::bad_request("guids are required")),
+    }
+}
+
+fn body_member(body: &Value) -> Result<MockMember, MockError> {
+    let membership = body["membership"].as_str().unwrap_or("full").to_string();
+    if membership != "full" && membership != "limited" {
+        return Err(MockError::bad_request("invalid membership"));
+    }
+
+    Ok(MockMember {
+        membership,
+        index0: body["index0"].as_bool().unwrap_or(false),
+    })
+}
+
+fn apply_qos(p: &mut MockPartition, body: &Value) {
+    if let Some(mtu) = body["mtu_limit"].as_u64() {
+        p.mtu_limit = mtu as u16;
+    }
+    if let Some(sl) = body["service_level"].as_u64() {
+        p.service_level = sl as u8;
+    }
+    if let Some(rate) = body["rate_limit"].as_f64() {
+        p.rate_limit = rate;
+    }
+}
+
+fn new_partition(pkey: u16, body: &Value) -> MockPartition {
+    let mut p = MockPartition::new(&format!("api_pkey_{}", format_pkey(pkey)));
+    p.ip_over_ib = body["ip_over_ib"].as_bool().unwrap_or(false);
+    apply_qos(&mut p, body);
+    p
+}
+
+fn add_pkey(state: &mut State, body: &Value) -> MockResult {
+    let pkey = body_pkey(body)?;
+
+    if state.partitions.contains_key(&pkey) {
+        return Err(MockError::new(
+            StatusCode::CONFLICT,
+            &format!("Pkey {} already exists", format_pkey(pkey)),
+        ));
+    }
+
+    state.partitions.insert(pkey, new_partition(pkey, body));
+
+    Ok(response(StatusCode::CREATED, json!({})))
+}
+
+fn set_pkey(state: &mut State, body: &Value) -> MockResult {
+    let pkey = body_pkey(body)?;
+    let guids = body_guids(body)?;
+    let member = body_member(body)?;
+
+    let p = state
+        .partitions
+        .entry(pkey)
+        .or_insert_with(|| new_partition(pkey, body));
+    p.ip_over_ib = body["ip_over_ib"].as_bool().unwrap_or(p.ip_over_ib);
+    apply_qos(p, body);
+    p.members = guids.into_iter().map(|g| (g, member.clone())).collect();
+
+    ok(json!({}))
+}
+
+fn add_guids(state: &mut State, body: &Value) -> MockResult {
+    let pkey = body_pkey(body)?;
+    let guids = body_guids(body)?;
+    let member = body_member(body)?;
+
+    let p = state
+        .partitions
+        .entry(pkey)
+        .or_insert_with(|| new_partition(pkey, body));
+    for g in guids {
+        p.members.insert(g, member.clone());
+    }
+
+    ok(json!({}))
+}
+
+fn partition_mut(state: &mut State, pkey: u16) -> Result<&mut MockPartition, MockError> {
+    state
+        .partitions
+        .get_mut(&pkey)
+        .ok_or_else(|| MockError::not_found(&format!("Pkey {} not found", format_pkey(pkey))))
+}
+
+fn update_qos(state: &mut State, body: &Value) -> MockResult {
+    let pkey = body_pkey(body)?;
+    apply_qos(partition_mut(state, pkey)?, body);
+
+    ok(json!({}))
+}
+
+fn update_smconf(state: &mut State, body: &Value) -> MockResult {
+    let params = body
+        .as_object()
+        .ok_or(MockError::bad_request("invalid SM parameters"))?;
+    if let Some(key) = params.keys().find(|k| state.smconf.get(k).is_none()) {
+        return Err(MockError::bad_request(&format!("unknown parameter {key}")));
+    }
+    for (key, value) in params {
+        state.smconf[key] = value.clone();
+    }
+
+    ok(json!({}))
+}
+
+fn delete_pkey(state: &mut State, pkey: &str) -> MockResult {
+    match parse_pkey(pkey).and_then(|k| state.partitions.remove(&k)) {
+        Some(_) => ok(json!({})),
+        None => Err(MockError::not_found(&format!("Pkey {pkey} not found"))),
+    }
+}
+
+fn remove_guids(state: &mut State, body: &Value) -> MockResult {
+    let pkey = body_pkey(body)?;
+    let guids = body_guids(body)?;
+
+    let p = partition_mut(state, pkey)?;
+    for g in guids {
+        p.members.remove(&g);
+    }
+
+    ok(json!({}))
+}
+
+fn get_system(state: &State, id: &str) -> MockResult {
+    let id = normalize_guid(id);
+    let system = state.systems.iter().find(|s| {
+        s["system_guid"].as_str().map(normalize_guid).as_deref() == Some(&id)
+            || s["system_name"].as_str() == Some(&id)
+    });
+
+    match system {
+        Some(s) => ok(s.clone()),
+        None => Err(MockError::not_found(&format!("system '{id}' not found"))),
+    }
+}
+
+/// Filter the events or alarms by `min_severity`, `since`, `until`, `object` and `after_id`, in
+/// the order of their IDs, or the reverse by `order=desc`, and take the first `limit` of them.
+fn filter_events(items: &[Value], params: &HashMap<String, String>) -> Value {
+    const SEVERITIES: [&str; 4] = ["info", "warning", "minor", "critical"];
+    let rank = |s: &str| SEVERITIES.iter().position(|v| v.eq_ignore_ascii_case(s));
+    let text = |item: &Value, key: &str| item[key].as_str().unwrap_or_default().to_string();
+
+    let mut items: Vec<Value> = items
+        .iter()
+        .filter(|i| match params.get("min_severity") {
+            Some(s) => rank(&text(i, "severity")) >= rank(s),
+            None => true,
+        })
+        .filter(|i| match params.get("since") {
+            Some(t) => text(i, "timestamp") >= *t,
+            None => true,
+        })
+        .filter(|i| match params.get("until") {
+            Some(t) => text(i, "timestamp") < *t,
+            None => true,
+        })
+        .filter(|i| match params.get("object") {
+            Some(o) => ["object_name", "object_path"]
+                .iter()
+                .any(|k| text(i, k).to_lowercase().contains(&o.to_lowercase())),
+            None => true,
+        })
+        .filter(
+            |i| match params.get("after_id").and_then(|a| a.parse().ok()) {
+                Some(a) => i["id"].as_u64().unwrap_or_default() > a,
+                None => true,
+            },
+        )
+        .cloned()
+        .collect();
+
+    items.sort_by_key(|i| i["id"].as_u64());
+    if params.get("order").is_some_and(|o| o == "desc") {
+        items.reverse();
+    }
+    if let Some(limit) = params.get("limit").and_then(|l| l.parse().ok()) {
+        items.truncate(limit);
+    }
+
+    Value::from(items)
+}
+
+fn list_ports(state: &State, params: &HashMap<String, String>) -> MockResult {
+    let ports: Vec<Value> = state
+        .ports
+        .iter()
+        .filter(|p| match params.get("sys_type") {
+            Some(t) => p.sys_type.eq_ignore_ascii_case(t),
+            None => true,
+        })
+        .filter(|p| match params.get("system") {
+            Some(s) => [&p.data["systemID"], &p.data["system_name"]].contains(&&json!(s)),
+            None => true,
+        })
+        .map(|p| p.data.clone())
+        .collect();
+
+    ok(Value::from(ports))
+}
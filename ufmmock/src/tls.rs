@@ -0,0 +1,134 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_rustls::rustls::server::AllowAnyAnonymousOrAuthenticatedClient;
+use tokio_rustls::rustls::{self, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use crate::{handle, State};
+
+/// The CA of a mock UFM serving HTTPS; it signs both the server and the client certificates.
+pub(crate) struct MockCa {
+    cert: Certificate,
+    der: Vec<u8>,
+    pem: String,
+}
+
+impl MockCa {
+    pub(crate) fn new() -> io::Result<MockCa> {
+        let mut params = CertificateParams::default();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Mock UFM CA");
+
+        let cert = Certificate::from_params(params).map_err(invalid)?;
+        let der = cert.serialize_der().map_err(invalid)?;
+        let pem = cert.serialize_pem().map_err(invalid)?;
+
+        Ok(MockCa { cert, der, pem })
+    }
+
+    pub(crate) fn pem(&self) -> String {
+        self.pem.clone()
+    }
+
+    /// Build a TLS acceptor with a certificate for `server_name`, which accepts clients both
+    /// with and without a certificate signed by this CA.
+    pub(crate) fn acceptor(&self, server_name: &str) -> io::Result<TlsAcceptor> {
+        let (cert, key) = self.issue(server_name, ExtendedKeyUsagePurpose::ServerAuth)?;
+
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(&rustls::Certificate(self.der.clone()))
+            .map_err(invalid)?;
+
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed())
+            .with_single_cert(
+                vec![rustls::Certificate(
+                    cert.serialize_der_with_signer(&self.cert)
+                        .map_err(invalid)?,
+                )],
+                PrivateKey(key),
+            )
+            .map_err(invalid)?;
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    pub(crate) fn issue_client_cert(&self) -> io::Result<(String, String)> {
+        let (cert, _) = self.issue("Mock UFM client", ExtendedKeyUsagePurpose::ClientAuth)?;
+        let pem = cert
+            .serialize_pem_with_signer(&self.cert)
+            .map_err(invalid)?;
+
+        Ok((pem, cert.serialize_private_key_pem()))
+    }
+
+    fn issue(
+        &self,
+        name: &str,
+        usage: ExtendedKeyUsagePurpose,
+    ) -> io::Result<(Certificate, Vec<u8>)> {
+        let mut params = CertificateParams::new(vec![name.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.extended_key_usages = vec![usage];
+
+        let cert = Certificate::from_params(params).map_err(invalid)?;
+        let key = cert.serialize_private_key_der();
+
+        Ok((cert, key))
+    }
+}
+
+/// Serve the mock UFM over TLS until `shutdown` fires.
+pub(crate) async fn serve(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    state: Arc<Mutex<State>>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        let stream = tokio::select! {
+            _ = &mut shutdown => return,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::debug!("failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::debug!("TLS handshake failed: {}", e);
+                    return;
+                }
+            };
+
+            let client_cert = stream.get_ref().1.peer_certificates().is_some();
+            let service = service_fn(move |req| handle(state.clone(), client_cert, req));
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                log::debug!("failed to serve connection: {}", e);
+            }
+        });
+    }
+}
+
+fn invalid<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}